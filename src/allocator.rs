@@ -1,42 +1,749 @@
-use core::alloc::{GlobalAlloc, Layout};
+use alloc::boxed::Box;
+use alloc::collections::TryReserveError;
+use alloc::vec::Vec;
+use core::alloc::{AllocError, Allocator, GlobalAlloc, Layout};
+#[cfg(not(feature = "host-sim"))]
 use core::ffi::c_void;
+use core::mem::size_of;
+use core::ptr::{self, NonNull};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crate::log::__kernel_print_fixed;
 
+/// The `POOL_TYPE` passed to `ExAllocatePoolWithTag`.
 #[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum PoolType {
-    NonPagedPool,
-    NonPagedPoolExecute,
+    NonPagedPool = 0,
+    PagedPool = 1,
+    NonPagedPoolMustSucceed = 2,
+    DontUseThisType = 3,
+    NonPagedPoolCacheAligned = 4,
+    PagedPoolCacheAligned = 5,
+    NonPagedPoolCacheAlignedMustS = 6,
+    NonPagedPoolSession = 32,
+    PagedPoolSession = 33,
+    NonPagedPoolMustSucceedSession = 34,
+    DontUseThisTypeSession = 35,
+    NonPagedPoolCacheAlignedSession = 36,
+    PagedPoolCacheAlignedSession = 37,
+    NonPagedPoolCacheAlignedMustSSession = 38,
+    NonPagedPoolNx = 512,
+    NonPagedPoolNxCacheAligned = 516,
+    NonPagedPoolSessionNx = 544,
 }
 
+#[allow(non_upper_case_globals)]
+impl PoolType {
+    /// Every distinct pool type, in declaration order.
+    pub const ALL: [PoolType; 17] = [
+        PoolType::NonPagedPool,
+        PoolType::PagedPool,
+        PoolType::NonPagedPoolMustSucceed,
+        PoolType::DontUseThisType,
+        PoolType::NonPagedPoolCacheAligned,
+        PoolType::PagedPoolCacheAligned,
+        PoolType::NonPagedPoolCacheAlignedMustS,
+        PoolType::NonPagedPoolSession,
+        PoolType::PagedPoolSession,
+        PoolType::NonPagedPoolMustSucceedSession,
+        PoolType::DontUseThisTypeSession,
+        PoolType::NonPagedPoolCacheAlignedSession,
+        PoolType::PagedPoolCacheAlignedSession,
+        PoolType::NonPagedPoolCacheAlignedMustSSession,
+        PoolType::NonPagedPoolNx,
+        PoolType::NonPagedPoolNxCacheAligned,
+        PoolType::NonPagedPoolSessionNx,
+    ];
+
+    /// The position of this pool type in [`PoolType::ALL`].
+    pub fn index(&self) -> usize {
+        match self {
+            PoolType::NonPagedPool => 0,
+            PoolType::PagedPool => 1,
+            PoolType::NonPagedPoolMustSucceed => 2,
+            PoolType::DontUseThisType => 3,
+            PoolType::NonPagedPoolCacheAligned => 4,
+            PoolType::PagedPoolCacheAligned => 5,
+            PoolType::NonPagedPoolCacheAlignedMustS => 6,
+            PoolType::NonPagedPoolSession => 7,
+            PoolType::PagedPoolSession => 8,
+            PoolType::NonPagedPoolMustSucceedSession => 9,
+            PoolType::DontUseThisTypeSession => 10,
+            PoolType::NonPagedPoolCacheAlignedSession => 11,
+            PoolType::PagedPoolCacheAlignedSession => 12,
+            PoolType::NonPagedPoolCacheAlignedMustSSession => 13,
+            PoolType::NonPagedPoolNx => 14,
+            PoolType::NonPagedPoolNxCacheAligned => 15,
+            PoolType::NonPagedPoolSessionNx => 16,
+        }
+    }
+
+    /// Executable non-paged pool, an alias of `NonPagedPool`.
+    pub const NonPagedPoolExecute: PoolType = PoolType::NonPagedPool;
+    pub const NonPagedPoolBase: PoolType = PoolType::NonPagedPool;
+    pub const NonPagedPoolBaseMustSucceed: PoolType = PoolType::NonPagedPoolMustSucceed;
+    pub const NonPagedPoolBaseCacheAligned: PoolType = PoolType::NonPagedPoolCacheAligned;
+    pub const NonPagedPoolBaseCacheAlignedMustS: PoolType = PoolType::NonPagedPoolCacheAlignedMustS;
+
+    /// Whether memory from this pool may be paged out, and so must only be touched below
+    /// `DISPATCH_LEVEL`.
+    pub fn is_paged(&self) -> bool {
+        (*self as u32) & 1 != 0
+    }
+
+    /// Whether memory from this pool is mapped non-executable.
+    pub fn is_nx(&self) -> bool {
+        (*self as u32) & 512 != 0
+    }
+}
+
+#[cfg(not(feature = "host-sim"))]
 #[link(name = "ntoskrnl")]
 extern "system" {
     pub fn ExAllocatePoolWithTag(pool_type: PoolType, number_of_bytes: usize, tag: u32) -> *mut c_void;
     pub fn ExFreePoolWithTag(pool: *mut c_void, tag: u32);
 }
 
-static ALLOC_TAG: u32 = u32::from_le_bytes(*b"krnl");
+#[cfg(feature = "host-sim")]
+pub use crate::sim::ntoskrnl::{ExAllocatePoolWithTag, ExFreePoolWithTag};
+
+/// The alignment every pool allocation is guaranteed to have (`MEMORY_ALLOCATION_ALIGNMENT`).
+pub const POOL_ALIGNMENT: usize = if cfg!(target_pointer_width = "64") { 16 } else { 8 };
+
+/// Builds a pool tag from its four characters, in the order pool-tag tools display them.
+pub const fn pool_tag(tag: [u8; 4]) -> u32 {
+    u32::from_le_bytes(tag)
+}
+
+/// The tag used by [`KernelAlloc::DEFAULT`].
+pub const DEFAULT_TAG: u32 = pool_tag(*b"krnl");
+
+/// A pool type and tag to allocate with.
+///
+/// `Pool` implements [`Allocator`], so individual containers can be placed in a specific pool or
+/// under a specific tag without changing the global allocator:
+///
+/// ```ignore
+/// const PAGED: Pool = Pool::new(PoolType::PagedPool, pool_tag(*b"Cach"));
+/// let entries = Vec::<u64, _>::with_capacity_in(64, PAGED);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Pool {
+    pool_type: PoolType,
+    tag: u32,
+}
+
+impl Pool {
+    pub const fn new(pool_type: PoolType, tag: u32) -> Self {
+        Self { pool_type, tag }
+    }
+
+    pub const fn pool_type(&self) -> PoolType {
+        self.pool_type
+    }
+
+    pub const fn tag(&self) -> u32 {
+        self.tag
+    }
+
+    /// Allocates `size` bytes with `ExAllocatePoolWithTag`, returning null on failure.
+    pub unsafe fn allocate_raw(&self, size: usize) -> *mut u8 {
+        ExAllocatePoolWithTag(self.pool_type, size, self.tag) as _
+    }
+
+    /// Frees memory returned by [`Pool::allocate_raw`] on a pool with the same tag.
+    pub unsafe fn free_raw(&self, ptr: *mut u8) {
+        ExFreePoolWithTag(ptr as _, self.tag);
+    }
+
+    /// Allocates memory for `layout`, returning null on failure.
+    ///
+    /// Alignments up to [`POOL_ALIGNMENT`] are served directly. Larger alignments over-allocate
+    /// by `layout.align()` bytes and store the address returned by the pool in the word just
+    /// below the aligned pointer, so [`Pool::free_layout`] can recover it.
+    pub unsafe fn allocate_layout(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.allocate_layout_inner(layout);
+        if ptr.is_null() {
+            stats::record_failure(self.pool_type);
+        } else {
+            stats::record_alloc(self.pool_type, layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn allocate_layout_inner(&self, layout: Layout) -> *mut u8 {
+        if layout.align() <= POOL_ALIGNMENT {
+            return self.allocate_raw(layout.size());
+        }
+
+        let size = match layout.size().checked_add(layout.align()) {
+            Some(size) => size,
+            None => return ptr::null_mut(),
+        };
+
+        let base = self.allocate_raw(size);
+        if base.is_null() {
+            return base;
+        }
+
+        // `base` is at least pool aligned, so there are always `POOL_ALIGNMENT` bytes free below
+        // the aligned pointer for the header.
+        let offset = layout.align() - (base as usize & (layout.align() - 1));
+        let aligned = base.add(offset);
+        (aligned as *mut *mut u8).sub(1).write(base);
+        aligned
+    }
+
+    /// Frees memory returned by [`Pool::allocate_layout`] with the same `layout`.
+    pub unsafe fn free_layout(&self, ptr: *mut u8, layout: Layout) {
+        stats::record_free(self.pool_type, layout.size());
+        if layout.align() <= POOL_ALIGNMENT {
+            self.free_raw(ptr);
+        } else {
+            self.free_raw((ptr as *mut *mut u8).sub(1).read());
+        }
+    }
+
+    /// Resizes an allocation made with `layout`, returning null (and leaving the old allocation
+    /// alone) on failure.
+    ///
+    /// The pool cannot grow a block, but a shrink keeps the block it has: the tail is simply
+    /// left unused until the allocation is freed.
+    pub unsafe fn reallocate_layout(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size <= layout.size() {
+            stats::record_shrink(self.pool_type, layout.size() - new_size);
+            return ptr;
+        }
+
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.allocate_layout(new_layout);
+        if !new_ptr.is_null() {
+            ptr::copy_nonoverlapping(ptr, new_ptr, layout.size());
+            self.free_layout(ptr, layout);
+        }
+        new_ptr
+    }
+}
+
+// The header of an over-aligned allocation has to fit below the aligned pointer.
+const _: () = assert!(POOL_ALIGNMENT >= size_of::<usize>());
+
+unsafe impl Allocator for Pool {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() == 0 {
+            let dangling = unsafe { NonNull::new_unchecked(layout.align() as *mut u8) };
+            return Ok(NonNull::slice_from_raw_parts(dangling, 0));
+        }
+
+        let ptr = unsafe { self.allocate_layout(layout) };
+        match NonNull::new(ptr) {
+            Some(ptr) => Ok(NonNull::slice_from_raw_parts(ptr, layout.size())),
+            None => Err(AllocError),
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            self.free_layout(ptr.as_ptr(), layout);
+        }
+    }
+}
+
+/// What [`KernelAlloc`] does when the pool can't satisfy an allocation.
+#[derive(Copy, Clone, Debug)]
+pub enum OomPolicy {
+    /// Panics, as the allocator did before allocations could fail.
+    Panic,
+    /// Prints the failed layout with `DbgPrintEx` and returns null.
+    LogAndNull,
+    /// Calls the hook with the failed layout and pool, then returns null.
+    Hook(fn(Layout, Pool)),
+}
 
 /// The global kernel allocator structure.
-pub struct KernelAlloc;
+///
+/// Each driver registers its own instance, choosing the pool and the tag its allocations are
+/// reported under:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static GLOBAL: KernelAlloc = KernelAlloc::new(PoolType::NonPagedPoolNx, pool_tag(*b"MyDr"));
+/// ```
+///
+/// A failed allocation returns null unless the [`OomPolicy`] says otherwise, so `try_reserve`
+/// and the `try_*` helpers in this module can recover from low memory. Infallible allocations
+/// such as `Box::new` still end up in the alloc error handler.
+pub struct KernelAlloc {
+    pool: Pool,
+    oom_policy: OomPolicy,
+}
+
+impl KernelAlloc {
+    /// Non-paged pool tagged `krnl`.
+    pub const DEFAULT: KernelAlloc = KernelAlloc::new(PoolType::NonPagedPool, DEFAULT_TAG);
+
+    /// An allocator for `pool_type` and `tag` that logs failed allocations and returns null.
+    pub const fn new(pool_type: PoolType, tag: u32) -> Self {
+        Self { pool: Pool::new(pool_type, tag), oom_policy: OomPolicy::LogAndNull }
+    }
+
+    pub const fn oom_policy(mut self, oom_policy: OomPolicy) -> Self {
+        self.oom_policy = oom_policy;
+        self
+    }
+
+    /// The pool and tag this allocator uses.
+    pub const fn pool(&self) -> Pool {
+        self.pool
+    }
+
+    #[cfg(not(feature = "alloc-tracking"))]
+    unsafe fn allocate_layout(&self, layout: Layout) -> *mut u8 {
+        self.pool.allocate_layout(layout)
+    }
+
+    #[cfg(not(feature = "alloc-tracking"))]
+    unsafe fn free_layout(&self, ptr: *mut u8, layout: Layout) {
+        self.pool.free_layout(ptr, layout)
+    }
+
+    #[cfg(not(feature = "alloc-tracking"))]
+    unsafe fn reallocate_layout(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.pool.reallocate_layout(ptr, layout, new_size)
+    }
+
+    #[cfg(feature = "alloc-tracking")]
+    unsafe fn allocate_layout(&self, layout: Layout) -> *mut u8 {
+        tracking::allocate_layout(self.pool, layout)
+    }
+
+    #[cfg(feature = "alloc-tracking")]
+    unsafe fn free_layout(&self, ptr: *mut u8, layout: Layout) {
+        tracking::free_layout(self.pool, ptr, layout)
+    }
+
+    #[cfg(feature = "alloc-tracking")]
+    unsafe fn reallocate_layout(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        tracking::reallocate_layout(self.pool, ptr, layout, new_size)
+    }
+
+    fn out_of_memory(&self, layout: Layout) -> *mut u8 {
+        match self.oom_policy {
+            OomPolicy::Panic => panic!("Failed to allocate pool: {:?}", layout),
+            OomPolicy::LogAndNull => __kernel_print_fixed(format_args!(
+                "[allocator] failed to allocate {} bytes (align {}) from {:?}",
+                layout.size(),
+                layout.align(),
+                self.pool.pool_type(),
+            )),
+            OomPolicy::Hook(hook) => hook(layout, self.pool),
+        }
+        ptr::null_mut()
+    }
+}
 
 unsafe impl GlobalAlloc for KernelAlloc {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        let pool = ExAllocatePoolWithTag(PoolType::NonPagedPool, layout.size() as _, ALLOC_TAG);
+        let pool = self.allocate_layout(layout);
 
+        if pool.is_null() {
+            return self.out_of_memory(layout);
+        }
+
+        pool
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let pool = self.alloc(layout);
+        if !pool.is_null() {
+            ptr::write_bytes(pool, 0, layout.size());
+        }
+        pool
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.free_layout(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let pool = self.reallocate_layout(ptr, layout, new_size);
 
         if pool.is_null() {
-            panic!("Failed to allocate pool");
+            return self.out_of_memory(Layout::from_size_align_unchecked(new_size, layout.align()));
         }
 
-        pool as _
+        pool
     }
+}
+
+/// Usage counters for every allocation made through [`Pool`] and [`KernelAlloc`].
+///
+/// The counters are plain atomics, so they can be read at any IRQL. Sizes are the sizes callers
+/// asked for, plus the header of each non-paged allocation when `alloc-tracking` is enabled.
+pub mod stats {
+    use super::*;
 
-    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
-        ExFreePoolWithTag(ptr as _, ALLOC_TAG);
+    /// Usage of one pool type, or of all of them.
+    #[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+    pub struct PoolStats {
+        pub bytes_in_use: usize,
+        /// The highest `bytes_in_use` seen since the driver was loaded.
+        pub peak_bytes: usize,
+        pub allocations: usize,
+        pub frees: usize,
+        /// Allocations the pool could not satisfy.
+        pub failures: usize,
     }
+
+    struct Counters {
+        bytes_in_use: AtomicUsize,
+        peak_bytes: AtomicUsize,
+        allocations: AtomicUsize,
+        frees: AtomicUsize,
+        failures: AtomicUsize,
+    }
+
+    impl Counters {
+        const fn new() -> Self {
+            Self {
+                bytes_in_use: AtomicUsize::new(0),
+                peak_bytes: AtomicUsize::new(0),
+                allocations: AtomicUsize::new(0),
+                frees: AtomicUsize::new(0),
+                failures: AtomicUsize::new(0),
+            }
+        }
+
+        fn add(&self, size: usize) {
+            let in_use = self.bytes_in_use.fetch_add(size, Ordering::Relaxed) + size;
+            self.peak_bytes.fetch_max(in_use, Ordering::Relaxed);
+        }
+
+        fn snapshot(&self) -> PoolStats {
+            PoolStats {
+                bytes_in_use: self.bytes_in_use.load(Ordering::Relaxed),
+                peak_bytes: self.peak_bytes.load(Ordering::Relaxed),
+                allocations: self.allocations.load(Ordering::Relaxed),
+                frees: self.frees.load(Ordering::Relaxed),
+                failures: self.failures.load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    #[allow(clippy::declare_interior_mutable_const)]
+    const NEW: Counters = Counters::new();
+    static TOTAL: Counters = Counters::new();
+    static PER_POOL: [Counters; PoolType::ALL.len()] = [NEW; PoolType::ALL.len()];
+
+    pub(super) fn record_alloc(pool_type: PoolType, size: usize) {
+        for counters in [&TOTAL, &PER_POOL[pool_type.index()]] {
+            counters.allocations.fetch_add(1, Ordering::Relaxed);
+            counters.add(size);
+        }
+    }
+
+    pub(super) fn record_free(pool_type: PoolType, size: usize) {
+        for counters in [&TOTAL, &PER_POOL[pool_type.index()]] {
+            counters.frees.fetch_add(1, Ordering::Relaxed);
+            counters.bytes_in_use.fetch_sub(size, Ordering::Relaxed);
+        }
+    }
+
+    pub(super) fn record_shrink(pool_type: PoolType, by: usize) {
+        for counters in [&TOTAL, &PER_POOL[pool_type.index()]] {
+            counters.bytes_in_use.fetch_sub(by, Ordering::Relaxed);
+        }
+    }
+
+    pub(super) fn record_failure(pool_type: PoolType) {
+        for counters in [&TOTAL, &PER_POOL[pool_type.index()]] {
+            counters.failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Usage across every pool type. The peak is the peak of the sum, not the sum of the peaks.
+    pub fn total() -> PoolStats {
+        TOTAL.snapshot()
+    }
+
+    /// Usage of one pool type.
+    pub fn pool(pool_type: PoolType) -> PoolStats {
+        PER_POOL[pool_type.index()].snapshot()
+    }
+
+    /// Usage of every pool type that has been allocated from, in [`PoolType::ALL`] order.
+    pub fn pools() -> impl Iterator<Item = (PoolType, PoolStats)> {
+        PoolType::ALL
+            .iter()
+            .map(|&pool_type| (pool_type, pool(pool_type)))
+            .filter(|(_, stats)| stats.allocations != 0 || stats.failures != 0)
+    }
+}
+
+/// Records of the live allocations made through [`KernelAlloc`], enabled by the
+/// `alloc-tracking` feature.
+///
+/// Every allocation is prefixed with a header linking it into a list of live allocations.
+/// The list is walked under a spin lock at `DISPATCH_LEVEL`, so only allocations from
+/// non-paged pools are tracked; allocations from paged pools go straight to the pool.
+/// Call [`report_leaks`](tracking::report_leaks) from the driver's unload routine, after
+/// everything the driver owns has been dropped, to print whatever is still allocated.
+#[cfg(feature = "alloc-tracking")]
+pub mod tracking {
+    use core::alloc::Layout;
+    use core::ptr::{self, null_mut};
+    use crate::log::__kernel_print_fixed;
+    use crate::sync::SpinLock;
+    use super::{Pool, PoolType};
+
+    /// A live allocation.
+    ///
+    /// The global allocator interface doesn't pass the caller, so allocations are identified by
+    /// their sequence number: the n-th allocation made since the driver was loaded.
+    #[derive(Copy, Clone, Debug)]
+    pub struct AllocationRecord {
+        pub address: usize,
+        pub size: usize,
+        pub align: usize,
+        pub pool_type: PoolType,
+        pub tag: u32,
+        pub sequence: u64,
+    }
+
+    /// Counters over every tracked allocation, i.e. every allocation from a non-paged pool.
+    #[derive(Copy, Clone, Debug, Default)]
+    pub struct TrackingCounters {
+        /// Allocations that have not been freed yet.
+        pub live_allocations: usize,
+        /// Bytes requested by the live allocations, excluding headers.
+        pub live_bytes: usize,
+        pub total_allocations: u64,
+        pub total_frees: u64,
+    }
+
+    #[repr(C)]
+    struct Header {
+        prev: *mut Header,
+        next: *mut Header,
+        record: AllocationRecord,
+    }
+
+    struct LiveList {
+        head: *mut Header,
+        counters: TrackingCounters,
+    }
+
+    unsafe impl Send for LiveList {}
+
+    static LIVE: SpinLock<LiveList> = SpinLock::new(LiveList {
+        head: null_mut(),
+        counters: TrackingCounters {
+            live_allocations: 0,
+            live_bytes: 0,
+            total_allocations: 0,
+            total_frees: 0,
+        },
+    });
+
+    /// The layout actually allocated for `layout`, and the offset of the caller's memory in it.
+    fn tracked_layout(layout: Layout) -> Option<(Layout, usize)> {
+        Layout::new::<Header>().extend(layout).ok()
+    }
+
+    unsafe fn header_of(ptr: *mut u8, layout: Layout) -> (*mut Header, Layout) {
+        let (full, offset) = tracked_layout(layout).unwrap();
+        (ptr.sub(offset) as *mut Header, full)
+    }
+
+    /// Whether allocations from `pool` get a header. Headers of paged allocations could be paged
+    /// out while the list is walked at `DISPATCH_LEVEL`.
+    fn is_tracked(pool: Pool) -> bool {
+        !pool.pool_type().is_paged()
+    }
+
+    pub(super) unsafe fn allocate_layout(pool: Pool, layout: Layout) -> *mut u8 {
+        if !is_tracked(pool) {
+            return pool.allocate_layout(layout);
+        }
+
+        let (full, offset) = match tracked_layout(layout) {
+            Some(tracked) => tracked,
+            None => return null_mut(),
+        };
+
+        let base = pool.allocate_layout(full);
+        if base.is_null() {
+            return base;
+        }
+
+        let header = base as *mut Header;
+        let ptr = base.add(offset);
+
+        let mut live = LIVE.lock();
+        live.counters.total_allocations += 1;
+        live.counters.live_allocations += 1;
+        live.counters.live_bytes += layout.size();
+
+        header.write(Header {
+            prev: null_mut(),
+            next: live.head,
+            record: AllocationRecord {
+                address: ptr as usize,
+                size: layout.size(),
+                align: layout.align(),
+                pool_type: pool.pool_type(),
+                tag: pool.tag(),
+                sequence: live.counters.total_allocations,
+            },
+        });
+        if !live.head.is_null() {
+            (*live.head).prev = header;
+        }
+        live.head = header;
+
+        ptr
+    }
+
+    pub(super) unsafe fn free_layout(pool: Pool, ptr: *mut u8, layout: Layout) {
+        if !is_tracked(pool) {
+            return pool.free_layout(ptr, layout);
+        }
+
+        let (header, full) = header_of(ptr, layout);
+
+        {
+            let mut live = LIVE.lock();
+            let Header { prev, next, record } = header.read();
+            if prev.is_null() {
+                live.head = next;
+            } else {
+                (*prev).next = next;
+            }
+            if !next.is_null() {
+                (*next).prev = prev;
+            }
+
+            live.counters.total_frees += 1;
+            live.counters.live_allocations -= 1;
+            live.counters.live_bytes -= record.size;
+        }
+
+        pool.free_layout(header as *mut u8, full);
+    }
+
+    pub(super) unsafe fn reallocate_layout(pool: Pool, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if !is_tracked(pool) {
+            return pool.reallocate_layout(ptr, layout, new_size);
+        }
+
+        if new_size <= layout.size() {
+            let (header, _) = header_of(ptr, layout);
+            super::stats::record_shrink(pool.pool_type(), layout.size() - new_size);
+            let mut live = LIVE.lock();
+            live.counters.live_bytes -= layout.size() - new_size;
+            (*header).record.size = new_size;
+            return ptr;
+        }
+
+        let new_ptr = allocate_layout(pool, Layout::from_size_align_unchecked(new_size, layout.align()));
+        if !new_ptr.is_null() {
+            ptr::copy_nonoverlapping(ptr, new_ptr, layout.size());
+            free_layout(pool, ptr, layout);
+        }
+        new_ptr
+    }
+
+    /// Returns the current counters.
+    pub fn counters() -> TrackingCounters {
+        LIVE.lock().counters
+    }
+
+    /// Copies records of the live allocations, most recent first, into `out` and returns the
+    /// total number of live allocations, which may be more than fit.
+    pub fn live_allocations(out: &mut [AllocationRecord]) -> usize {
+        let live = LIVE.lock();
+        let mut header = live.head;
+        let mut count = 0;
+        while !header.is_null() {
+            unsafe {
+                if let Some(slot) = out.get_mut(count) {
+                    *slot = (*header).record;
+                }
+                header = (*header).next;
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// Prints every live allocation with `DbgPrintEx` and returns how many there were.
+    ///
+    /// Nothing is allocated while printing, so this can be called when the heap is in any state.
+    pub fn report_leaks() -> usize {
+        let live = LIVE.lock();
+        let mut header = live.head;
+        while !header.is_null() {
+            let record = unsafe { &(*header).record };
+            let tag = record.tag.to_le_bytes();
+            __kernel_print_fixed(format_args!(
+                "[allocator] leak #{}: {} bytes at {:#x} (align {}, {:?}, tag '{}{}{}{}')",
+                record.sequence,
+                record.size,
+                record.address,
+                record.align,
+                record.pool_type,
+                tag[0] as char,
+                tag[1] as char,
+                tag[2] as char,
+                tag[3] as char,
+            ));
+            header = unsafe { (*header).next };
+        }
+
+        let counters = live.counters;
+        if counters.live_allocations != 0 {
+            __kernel_print_fixed(format_args!(
+                "[allocator] {} allocations ({} bytes) leaked",
+                counters.live_allocations, counters.live_bytes,
+            ));
+        }
+        counters.live_allocations
+    }
+}
+
+/// Boxes `value`, returning an error instead of aborting if the allocation fails.
+pub fn try_box<T>(value: T) -> Result<Box<T>, AllocError> {
+    Box::try_new(value)
+}
+
+/// Boxes `value` in `pool`, returning an error instead of aborting if the allocation fails.
+pub fn try_box_in<T>(value: T, pool: Pool) -> Result<Box<T, Pool>, AllocError> {
+    Box::try_new_in(value, pool)
+}
+
+/// Creates an empty vector with room for `capacity` elements.
+pub fn try_vec_with_capacity<T>(capacity: usize) -> Result<Vec<T>, TryReserveError> {
+    let mut vec = Vec::new();
+    vec.try_reserve_exact(capacity)?;
+    Ok(vec)
+}
+
+/// Creates a vector of `len` clones of `value`, like `vec![value; len]`.
+pub fn try_vec<T: Clone>(value: T, len: usize) -> Result<Vec<T>, TryReserveError> {
+    let mut vec = try_vec_with_capacity(len)?;
+    vec.resize(len, value);
+    Ok(vec)
+}
+
+/// Copies `slice` into a new vector.
+pub fn try_vec_from_slice<T: Clone>(slice: &[T]) -> Result<Vec<T>, TryReserveError> {
+    let mut vec = try_vec_with_capacity(slice.len())?;
+    vec.extend_from_slice(slice);
+    Ok(vec)
 }
 
 #[alloc_error_handler]
-#[cfg(not(test))]
+#[cfg(not(any(test, feature = "host-sim")))]
 fn alloc_error(layout: Layout) -> ! {
     panic!("{:?} alloc memory error", layout);
 }
@@ -1,21 +1,28 @@
 //! Kernel-Mode Types.
 #![allow(non_camel_case_types)]
 
-pub use winapi::shared::ntdef::*;
-use core::ffi::c_void;
-pub use winapi::shared::ntstatus;
+#[cfg(not(all(feature = "host-sim", not(windows))))]
 pub use winapi;
+#[cfg(not(all(feature = "host-sim", not(windows))))]
 pub use ntapi;
+// `winapi` and `ntapi` are empty on other hosts, so the simulation brings its own definitions.
+#[cfg(all(feature = "host-sim", not(windows)))]
+pub use crate::sim::ffi::{winapi, ntapi};
+
+pub use self::winapi::shared::ntdef::*;
+use core::ffi::c_void;
+pub use self::winapi::shared::ntstatus;
 
 pub type PEPROCESS = *mut c_void;
 pub type PMDL = *mut c_void;
+pub type KIRQL = u8;
+pub type KSPIN_LOCK = usize;
 
 /// Processor modes.
 #[repr(u8)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum KProcessorMode
 {
     KernelMode,
     UserMode,
 }
-
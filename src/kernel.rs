@@ -1,129 +1,215 @@
-use winapi::ctypes::c_void;
+use crate::basedef::winapi::ctypes::c_void;
 use core::ptr::{null_mut, NonNull};
 use crate::basedef::*;
 use crate::ntstatus::NtStatus;
-use ntapi::ntexapi::{SYSTEM_INFORMATION_CLASS, SystemModuleInformation, SystemProcessInformation, SYSTEM_THREAD_INFORMATION};
-use crate::vsb::VariableSizedBox;
-use ntapi::ntzwapi::ZwQuerySystemInformation;
-use alloc::prelude::v1::*;
-use ntapi::ntldr::RTL_PROCESS_MODULES;
-use core::{slice, mem};
-use ntapi::ntapi_base::KPRIORITY;
-use winapi::shared::basetsd::{ULONG_PTR, SIZE_T, PSIZE_T};
+use crate::basedef::ntapi::ntexapi::{SYSTEM_INFORMATION_CLASS, SystemModuleInformation, SystemProcessInformation, SYSTEM_THREAD_INFORMATION};
+use crate::basedef::ntapi::ntexapi::{SystemExtendedHandleInformation, SYSTEM_HANDLE_INFORMATION_EX, SystemBigPoolInformation, SYSTEM_BIGPOOL_INFORMATION};
+use crate::basedef::ntapi::ntexapi::{SystemCodeIntegrityInformation, SYSTEM_CODEINTEGRITY_INFORMATION, SystemKernelDebuggerInformation, SYSTEM_KERNEL_DEBUGGER_INFORMATION};
+use crate::basedef::ntapi::ntexapi::{SystemBootEnvironmentInformation, SYSTEM_BOOT_ENVIRONMENT_INFORMATION};
+use crate::vsb::{Entry, NextEntryOffset, VariableSizedBox};
+#[cfg(not(feature = "host-sim"))]
+use crate::basedef::ntapi::ntzwapi::ZwQuerySystemInformation;
+use alloc::{string::String, vec::Vec};
+use crate::basedef::ntapi::ntldr::RTL_PROCESS_MODULES;
+use core::mem;
+use crate::basedef::ntapi::ntapi_base::KPRIORITY;
+use crate::basedef::ntapi::ntkeapi::{KTHREAD_STATE, KWAIT_REASON};
+use crate::basedef::winapi::shared::basetsd::{ULONG_PTR, SIZE_T};
 use crate::string::UnicodeString;
-use ntapi::ntrtl::RtlFindExportedRoutineByName;
+#[cfg(not(feature = "host-sim"))]
+use crate::basedef::ntapi::ntrtl::RtlFindExportedRoutineByName;
+#[cfg(feature = "host-sim")]
+use crate::sim::ntoskrnl::{ZwQuerySystemInformation, RtlFindExportedRoutineByName};
 use cstr_core::CString;
+#[cfg(not(feature = "host-sim"))]
 use crate::basedef::ntapi::ntobapi::POBJECT_NAME_INFORMATION;
-use ntapi::ntobapi::OBJECT_NAME_INFORMATION;
-use alloc::string::FromUtf16Error;
-use winapi::um::winnt::PAGE_READWRITE;
-use crate::process::PeProcess;
-use alloc::prelude::v1::*;
+use crate::basedef::winapi::um::winnt::{PAGE_READWRITE, FIRMWARE_TYPE};
+use crate::basedef::winapi::shared::guiddef::GUID;
+use crate::memory::{copy_result, MemoryImage, MemoryReader};
+use crate::process::{PeProcess, ProcessRef};
+use crate::mdl::Mdl;
+use crate::pe::{ImageLayout, PeError, PeImage};
 use alloc::vec;
 
-#[allow(non_camel_case_types)]
-#[repr(i32)]
-pub enum LOCK_OPERATION {
-    IoReadAccess = 0,
-    IoWriteAccess = 1,
-    IoModifyAccess = 2,
-}
+pub use crate::mdl::{LOCK_OPERATION, MEMORY_CACHING_TYPE};
+pub use crate::mdl::{IoAllocateMdl, MmProbeAndLockPages, MmMapLockedPagesSpecifyCache, MmProtectMdlSystemAddress, MmUnmapLockedPages, MmUnlockPages, IoFreeMdl};
 
-#[allow(non_camel_case_types)]
-#[repr(i32)]
-pub enum MEMORY_CACHING_TYPE {
-    MmNonCached = 0,
-    MmCached = 1,
-    MmWriteCombined = 2,
-    MmHardwareCoherentCached = 3,
-    MmNonCachedUnordered = 4,
-    MmUSWCCached = 5,
-    MmMaximumCacheType = 6,
-    MmNotMapped = -1,
-}
+pub unsafe fn safe_copy(src: *const u8, dst: *mut u8, len: usize) -> Result<(), NtStatus> {
+    let map = Mdl::builder(dst as _, len)
+        .operation(LOCK_OPERATION::IoReadAccess)
+        .cache_type(MEMORY_CACHING_TYPE::MmNonCached)
+        .allocate()?
+        .lock()
+        .map()?;
 
-extern "system" {
-    fn IoAllocateMdl(
-        virtual_address: *mut c_void,
-        length: u32,
-        secondary_buffer: u8,
-        charge_quota: u8,
-        irp: *mut c_void,
-    ) -> PMDL;
-
-    pub fn MmProbeAndLockPages(
-        memory_descriptor_list: PMDL,
-        access_mode: KProcessorMode,
-        operation: LOCK_OPERATION,
-    );
+    map.protect(PAGE_READWRITE)?;
 
-    pub fn MmMapLockedPagesSpecifyCache(
-        memory_descriptor_list: PMDL,
-        access_mode: KProcessorMode,
-        cache_type: MEMORY_CACHING_TYPE,
-        requested_address: PVOID,
-        bug_check_on_failure: ULONG,
-        priority: ULONG,
-    ) -> PVOID;
+    core::ptr::copy_nonoverlapping(src, map.as_ptr(), len);
 
-    pub fn MmProtectMdlSystemAddress(memory_descriptor_list: PMDL, new_protect: ULONG) -> NtStatus;
-
-    pub fn MmUnmapLockedPages(base_address: PVOID, memory_descriptor_list: PMDL);
-    pub fn MmUnlockPages(memory_descriptor_list: PMDL);
-    pub fn IoFreeMdl(mdl: PMDL);
+    Ok(())
 }
 
-pub unsafe fn safe_copy(src: *const u8, dst: *mut u8, len: usize) -> Result<(), NTSTATUS> {
-    let mdl = IoAllocateMdl(dst as _, len as _, 0, 0, null_mut());
-    if mdl.is_null() {
-        return Err(ntstatus::STATUS_ACCESS_DENIED);
+/// How many times `query_system_information` retries when the information grew between calls.
+const QUERY_MAX_ATTEMPTS: usize = 8;
+
+/// Queries a variable sized `SYSTEM_INFORMATION_CLASS`, growing the buffer and retrying while
+/// the kernel reports that it is too small.
+pub unsafe fn query_system_information<T>(class: SYSTEM_INFORMATION_CLASS) -> Result<VariableSizedBox<T>, NtStatus> {
+    let mut buf: VariableSizedBox<T> = VariableSizedBox::new(mem::size_of::<T>());
+
+    for _ in 0..QUERY_MAX_ATTEMPTS {
+        let mut size = 0;
+        let status = ZwQuerySystemInformation(
+            class,
+            buf.as_mut_ptr() as _,
+            buf.len() as _,
+            &mut size,
+        );
+
+        match status {
+            ntstatus::STATUS_INFO_LENGTH_MISMATCH
+            | ntstatus::STATUS_BUFFER_TOO_SMALL
+            | ntstatus::STATUS_BUFFER_OVERFLOW => {
+                // Leave headroom for entries created before the next call, and always grow so
+                // classes that don't report a size still make progress.
+                let size = (size as usize).max(buf.len() * 2);
+                buf.resize(size + size / 4);
+            }
+            status => return NtStatus(status).to_result_with_value(buf),
+        }
     }
 
-    MmProbeAndLockPages(mdl, KProcessorMode::KernelMode, LOCK_OPERATION::IoReadAccess);
-    let map = MmMapLockedPagesSpecifyCache(
-        mdl,
-        KProcessorMode::KernelMode,
-        MEMORY_CACHING_TYPE::MmNonCached,
-        null_mut(),
-        FALSE as u32,
-        16, // NormalPagePriority
-    );
-
-    MmProtectMdlSystemAddress(mdl, 0x04 /* PAGE_READWRITE */).to_result()?;
-
-    core::ptr::copy_nonoverlapping(src, map as _, len);
-
-    MmUnmapLockedPages(map, mdl);
-    MmUnlockPages(mdl);
-    IoFreeMdl(mdl);
-
-    Ok(())
+    Err(NtStatus(ntstatus::STATUS_INFO_LENGTH_MISMATCH))
 }
 
-pub unsafe fn query_system_information<T>(class: SYSTEM_INFORMATION_CLASS) -> Result<VariableSizedBox<T>, NTSTATUS> {
+/// Queries a fixed size `SYSTEM_INFORMATION_CLASS` into `info`, which must be initialized with
+/// any input fields the class expects.
+pub unsafe fn query_system_information_fixed<T>(class: SYSTEM_INFORMATION_CLASS, mut info: T) -> Result<T, NtStatus> {
     let mut size = 0;
     let status = ZwQuerySystemInformation(
         class,
-        null_mut(),
-        size,
+        &mut info as *mut T as _,
+        mem::size_of::<T>() as _,
         &mut size,
     );
+    NtStatus(status).to_result_with_value(info)
+}
+
+/// An entry of the system handle table.
+#[derive(Copy, Clone, Debug)]
+pub struct HandleInfo {
+    pub object: usize,
+    pub process_id: u64,
+    pub handle: usize,
+    pub granted_access: ULONG,
+    pub object_type_index: USHORT,
+    pub attributes: ULONG,
+}
+
+pub unsafe fn get_system_handles() -> Result<Vec<HandleInfo>, NtStatus> {
+    let buf = query_system_information::<SYSTEM_HANDLE_INFORMATION_EX>(SystemExtendedHandleInformation)?;
+    let handles = buf.try_slice_from_count(buf.as_ref().Handles.as_ptr(), buf.as_ref().NumberOfHandles)
+        .ok_or(NtStatus(ntstatus::STATUS_INFO_LENGTH_MISMATCH))?;
+
+    Ok(handles.iter().map(|h| HandleInfo {
+        object: h.Object as _,
+        process_id: h.UniqueProcessId as _,
+        handle: h.HandleValue,
+        granted_access: h.GrantedAccess,
+        object_type_index: h.ObjectTypeIndex,
+        attributes: h.HandleAttributes,
+    }).collect())
+}
+
+/// A pool allocation large enough to be tracked in the big pool table.
+#[derive(Copy, Clone, Debug)]
+pub struct BigPoolEntry {
+    pub address: usize,
+    pub size: usize,
+    pub tag: u32,
+    pub non_paged: bool,
+}
+
+pub unsafe fn get_big_pool_allocations() -> Result<Vec<BigPoolEntry>, NtStatus> {
+    let buf = query_system_information::<SYSTEM_BIGPOOL_INFORMATION>(SystemBigPoolInformation)?;
+    let entries = buf.try_slice_from_count(buf.as_ref().AllocatedInfo.as_ptr(), buf.as_ref().Count as _)
+        .ok_or(NtStatus(ntstatus::STATUS_INFO_LENGTH_MISMATCH))?;
+
+    Ok(entries.iter().map(|e| BigPoolEntry {
+        // The low bit of the address is the non-paged flag.
+        address: e.u1.VirtualAddress as usize & !1,
+        size: e.SizeInBytes,
+        tag: e.u2.TagUlong,
+        non_paged: e.u1.NonPaged() != 0,
+    }).collect())
+}
+
+/// The `CodeIntegrityOptions` flags reported by `SystemCodeIntegrityInformation`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct CodeIntegrityOptions(pub ULONG);
+
+impl CodeIntegrityOptions {
+    pub const ENABLED: ULONG = 0x01;
+    pub const TESTSIGN: ULONG = 0x02;
+    pub const UMCI_ENABLED: ULONG = 0x04;
+    pub const DEBUGMODE_ENABLED: ULONG = 0x80;
+    pub const HVCI_KMCI_ENABLED: ULONG = 0x400;
 
-    if size == 0 {
-        return Err(ntstatus::STATUS_UNSUCCESSFUL);
+    pub fn contains(&self, flag: ULONG) -> bool {
+        self.0 & flag == flag
     }
 
-    let mut buf: VariableSizedBox<T> = VariableSizedBox::new(size as _);
+    pub fn is_enabled(&self) -> bool {
+        self.contains(Self::ENABLED)
+    }
 
-    let status = ZwQuerySystemInformation(
-        class,
-        buf.as_mut_ptr() as _,
-        size,
-        &mut size,
-    );
-    NtStatus(status).to_result()?;
+    pub fn is_test_signing(&self) -> bool {
+        self.contains(Self::TESTSIGN)
+    }
+
+    pub fn is_hvci(&self) -> bool {
+        self.contains(Self::HVCI_KMCI_ENABLED)
+    }
+}
+
+pub unsafe fn get_code_integrity_options() -> Result<CodeIntegrityOptions, NtStatus> {
+    let info = SYSTEM_CODEINTEGRITY_INFORMATION {
+        Length: mem::size_of::<SYSTEM_CODEINTEGRITY_INFORMATION>() as _,
+        CodeIntegrityOptions: 0,
+    };
+    let info = query_system_information_fixed(SystemCodeIntegrityInformation, info)?;
+    Ok(CodeIntegrityOptions(info.CodeIntegrityOptions))
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct KernelDebuggerInfo {
+    pub enabled: bool,
+    pub present: bool,
+}
 
-    Ok(buf)
+pub unsafe fn get_kernel_debugger_info() -> Result<KernelDebuggerInfo, NtStatus> {
+    let info = query_system_information_fixed(SystemKernelDebuggerInformation, mem::zeroed::<SYSTEM_KERNEL_DEBUGGER_INFORMATION>())?;
+    Ok(KernelDebuggerInfo {
+        enabled: info.KernelDebuggerEnabled != 0,
+        present: info.KernelDebuggerNotPresent == 0,
+    })
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct BootEnvironment {
+    pub boot_identifier: GUID,
+    /// `FirmwareTypeBios` or `FirmwareTypeUefi`.
+    pub firmware_type: FIRMWARE_TYPE,
+    pub boot_flags: ULONGLONG,
+}
+
+pub unsafe fn get_boot_environment() -> Result<BootEnvironment, NtStatus> {
+    let info = query_system_information_fixed(SystemBootEnvironmentInformation, mem::zeroed::<SYSTEM_BOOT_ENVIRONMENT_INFORMATION>())?;
+    Ok(BootEnvironment {
+        boot_identifier: info.BootIdentifier,
+        firmware_type: info.FirmwareType,
+        boot_flags: info.BootFlags,
+    })
 }
 
 #[repr(C)]
@@ -142,21 +228,92 @@ pub struct ProcessModuleInformation {
 }
 
 impl ProcessModuleInformation {
-    pub unsafe fn full_path(&self) -> &str {
-        crate::util::str_from_slice_unchecked(self.full_path_name.as_slice())
+    /// The path the module was loaded from, up to the first null or the end of the buffer. It is
+    /// in the system code page, so use [`core::str::from_utf8`] rather than assuming UTF-8.
+    pub fn full_path(&self) -> &[u8] {
+        until_nul(&self.full_path_name)
+    }
+
+    /// The file name part of `full_path`, e.g. `ntoskrnl.exe`.
+    pub fn file_name(&self) -> &[u8] {
+        let offset = (self.offset_to_file_name as usize).min(self.full_path_name.len());
+        until_nul(&self.full_path_name[offset..])
+    }
+
+    pub fn contains(&self, address: usize) -> bool {
+        address >= self.image_base && address - self.image_base < self.image_size as usize
     }
 
     pub unsafe fn get_export(&self, func_name: &str) -> Option<NonNull<c_void>> {
         get_kernel_export(self.image_base, func_name)
     }
+
+    /// Parses the headers of the mapped module. The image is read through [`KernelMemory`], so
+    /// discarded or paged out parts fail to read instead of faulting.
+    pub unsafe fn image(&self) -> Result<PeImage<MemoryImage<KernelMemory>>, PeError> {
+        PeImage::parse(MemoryImage::new(KernelMemory, self.image_base as _, self.image_size as _), ImageLayout::Mapped)
+    }
 }
 
-pub unsafe fn get_kernel_modules() -> Result<Vec<ProcessModuleInformation>, NTSTATUS> {
+pub unsafe fn get_kernel_modules() -> Result<Vec<ProcessModuleInformation>, NtStatus> {
     let buf = query_system_information::<RTL_PROCESS_MODULES>(SystemModuleInformation)?;
-    let modules = slice::from_raw_parts(buf.as_ref().Modules.as_ptr() as *const ProcessModuleInformation, buf.as_ref().NumberOfModules as usize);
+    let modules = buf.try_slice_from_count(buf.as_ref().Modules.as_ptr() as *const ProcessModuleInformation, buf.as_ref().NumberOfModules as _)
+        .ok_or(NtStatus(ntstatus::STATUS_INFO_LENGTH_MISMATCH))?;
     Ok(modules.to_vec())
 }
 
+/// Finds a loaded kernel module by file name, ignoring case and any leading path.
+pub unsafe fn find_kernel_module(name: &str) -> Result<Option<ProcessModuleInformation>, NtStatus> {
+    let name = name.rsplit(['\\', '/']).next().unwrap_or(name);
+    Ok(get_kernel_modules()?
+        .into_iter()
+        .find(|m| m.file_name().eq_ignore_ascii_case(name.as_bytes())))
+}
+
+fn until_nul(bytes: &[u8]) -> &[u8] {
+    bytes.split(|&b| b == 0).next().unwrap_or(bytes)
+}
+
+/// An address resolved to the kernel module containing it.
+#[derive(Copy, Clone)]
+pub struct ModuleAddress {
+    pub module: ProcessModuleInformation,
+    pub offset: usize,
+}
+
+impl ModuleAddress {
+    /// Resolves `address` against an already queried module list.
+    pub fn resolve(modules: &[ProcessModuleInformation], address: usize) -> Option<Self> {
+        modules
+            .iter()
+            .find(|m| m.contains(address))
+            .map(|m| Self { module: *m, offset: address - m.image_base })
+    }
+}
+
+/// Formats as `module.sys+0x1234`. Bytes of the name that aren't UTF-8 are replaced with U+FFFD.
+impl core::fmt::Display for ModuleAddress {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}+{:#x}", String::from_utf8_lossy(self.module.file_name()), self.offset)
+    }
+}
+
+impl core::fmt::Debug for ModuleAddress {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ModuleAddress")
+            .field("module", &String::from_utf8_lossy(self.module.file_name()))
+            .field("image_base", &format_args!("{:#x}", self.module.image_base))
+            .field("image_size", &format_args!("{:#x}", self.module.image_size))
+            .field("offset", &format_args!("{:#x}", self.offset))
+            .finish()
+    }
+}
+
+/// Resolves any kernel address to the module containing it and the offset into that module.
+pub unsafe fn module_containing(address: usize) -> Result<Option<ModuleAddress>, NtStatus> {
+    Ok(ModuleAddress::resolve(&get_kernel_modules()?, address))
+}
+
 pub unsafe fn get_kernel_export(module_base: usize, func_name: &str) -> Option<NonNull<c_void>> {
     let func_name = CString::new(func_name).unwrap();
     NonNull::new(RtlFindExportedRoutineByName(module_base as _, func_name.as_ptr() as _))
@@ -203,27 +360,154 @@ pub struct SystemProcessInformation {
 }
 
 impl SystemProcessInformation {
-    pub unsafe fn to_process(&self) -> Option<PeProcess> {
+    pub unsafe fn to_process(&self) -> Option<ProcessRef> {
         PeProcess::by_pid(self.unique_process_id as _)
     }
 }
 
-pub unsafe fn get_process_list() -> Result<Vec<SystemProcessInformation>, NTSTATUS> {
-    let buf = query_system_information::<SystemProcessInformation>(SystemProcessInformation)?;
+unsafe impl NextEntryOffset for SystemProcessInformation {
+    fn next_entry_offset(&self) -> u32 {
+        self.next_entry_offset
+    }
+}
 
-    let mut info = buf.as_ptr();
-    let mut structs = Vec::new();
+/// The scheduling state of a thread (`KTHREAD_STATE`).
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ThreadState {
+    Initialized,
+    Ready,
+    Running,
+    Standby,
+    Terminated,
+    Waiting,
+    Transition,
+    DeferredReady,
+    GateWaitObsolete,
+    WaitingForProcessInSwap,
+    Unknown(u32),
+}
+
+impl From<KTHREAD_STATE> for ThreadState {
+    fn from(state: KTHREAD_STATE) -> Self {
+        match state {
+            0 => Self::Initialized,
+            1 => Self::Ready,
+            2 => Self::Running,
+            3 => Self::Standby,
+            4 => Self::Terminated,
+            5 => Self::Waiting,
+            6 => Self::Transition,
+            7 => Self::DeferredReady,
+            8 => Self::GateWaitObsolete,
+            9 => Self::WaitingForProcessInSwap,
+            other => Self::Unknown(other),
+        }
+    }
+}
 
-    loop {
-        structs.push(*info);
+/// A thread of a [`ProcessSnapshot`]. Times are in 100ns units.
+#[derive(Copy, Clone, Debug)]
+pub struct ThreadInfo {
+    pub thread_id: u64,
+    pub process_id: u64,
+    pub start_address: usize,
+    pub state: ThreadState,
+    /// The `KWAIT_REASON`, only meaningful while `state` is `Waiting`.
+    pub wait_reason: KWAIT_REASON,
+    pub priority: KPRIORITY,
+    pub base_priority: i32,
+    pub context_switches: ULONG,
+    pub wait_time: ULONG,
+    pub kernel_time: i64,
+    pub user_time: i64,
+    pub create_time: i64,
+}
 
-        match (*info).next_entry_offset {
-            0 => break,
-            offset => info = (info as usize + offset as usize) as _
+impl From<&SYSTEM_THREAD_INFORMATION> for ThreadInfo {
+    fn from(info: &SYSTEM_THREAD_INFORMATION) -> Self {
+        unsafe {
+            Self {
+                thread_id: info.ClientId.UniqueThread as _,
+                process_id: info.ClientId.UniqueProcess as _,
+                start_address: info.StartAddress as _,
+                state: info.ThreadState.into(),
+                wait_reason: info.WaitReason,
+                priority: info.Priority,
+                base_priority: info.BasePriority,
+                context_switches: info.ContextSwitches,
+                wait_time: info.WaitTime,
+                kernel_time: *info.KernelTime.QuadPart(),
+                user_time: *info.UserTime.QuadPart(),
+                create_time: *info.CreateTime.QuadPart(),
+            }
         }
     }
+}
+
+/// An owned copy of a `SystemProcessInformation` entry, including all of its threads.
+#[derive(Clone, Debug)]
+pub struct ProcessSnapshot {
+    pub process_id: u64,
+    pub parent_process_id: u64,
+    pub image_name: String,
+    pub session_id: ULONG,
+    pub handle_count: ULONG,
+    pub base_priority: KPRIORITY,
+    pub create_time: i64,
+    pub user_time: i64,
+    pub kernel_time: i64,
+    pub virtual_size: SIZE_T,
+    pub working_set_size: SIZE_T,
+    pub private_page_count: SIZE_T,
+    pub threads: Vec<ThreadInfo>,
+}
+
+impl ProcessSnapshot {
+    /// Copies `entry`, failing with `STATUS_DATA_ERROR` if its threads or image name don't lie
+    /// within the entry. Names that aren't valid UTF-16 are converted lossily.
+    unsafe fn from_entry(entry: &Entry<SystemProcessInformation>) -> Result<Self, NtStatus> {
+        let info = entry.get();
+        let threads = entry
+            .try_slice_from_count(info.threads.as_ptr(), info.number_of_threads as _)
+            .ok_or(NtStatus(ntstatus::STATUS_DATA_ERROR))?;
+        let image_name = if info.image_name.buffer.is_null() {
+            &[][..]
+        } else {
+            entry
+                .try_slice_from_count(info.image_name.buffer, (info.image_name.length / 2) as _)
+                .ok_or(NtStatus(ntstatus::STATUS_DATA_ERROR))?
+        };
+
+        Ok(Self {
+            process_id: info.unique_process_id as _,
+            parent_process_id: info.inherited_from_unique_process_id as _,
+            image_name: String::from_utf16_lossy(image_name),
+            session_id: info.session_id,
+            handle_count: info.handle_count,
+            base_priority: info.base_priority,
+            create_time: info.create_time,
+            user_time: info.user_time,
+            kernel_time: info.kernel_time,
+            virtual_size: info.virtual_size,
+            working_set_size: info.working_set_size,
+            private_page_count: info.private_page_count,
+            threads: threads.iter().map(ThreadInfo::from).collect(),
+        })
+    }
 
-    Ok(structs)
+    pub unsafe fn to_process(&self) -> Option<ProcessRef> {
+        PeProcess::by_pid(self.process_id)
+    }
+
+    /// Returns the threads of this process that started at `start_address`.
+    pub fn threads_by_start_address(&self, start_address: usize) -> impl Iterator<Item = &ThreadInfo> {
+        self.threads.iter().filter(move |t| t.start_address == start_address)
+    }
+}
+
+pub unsafe fn get_process_list() -> Result<Vec<ProcessSnapshot>, NtStatus> {
+    let buf = query_system_information::<SystemProcessInformation>(SystemProcessInformation)?;
+    buf.entries().map(|entry| ProcessSnapshot::from_entry(&entry?)).collect()
 }
 
 #[repr(u32)]
@@ -273,6 +557,7 @@ pub enum RegNotifyClass {
     MaxRegNtNotifyClass = 41,
 }
 
+#[cfg(not(feature = "host-sim"))]
 extern "system" {
     pub fn CmRegisterCallback(
         func: *mut c_void,
@@ -283,9 +568,12 @@ extern "system" {
     pub fn CmUnRegisterCallback(cookie: u64) -> NtStatus;
 }
 
+#[cfg(feature = "host-sim")]
+pub use crate::sim::ntoskrnl::{CmRegisterCallback, CmUnRegisterCallback};
+
 pub type RegistryCallbackFunc<T> = extern "C" fn(callback_context: &mut T, class: RegNotifyClass, operation: *mut c_void) -> NTSTATUS;
 
-pub unsafe fn create_registry_callback<T>(func: RegistryCallbackFunc<T>, context: &'static mut T) -> Result<RegistryCallback, NTSTATUS> {
+pub unsafe fn create_registry_callback<T>(func: RegistryCallbackFunc<T>, context: &'static mut T) -> Result<RegistryCallback, NtStatus> {
     let mut cookie = 0;
     CmRegisterCallback(func as _, context as *mut T as _, &mut cookie).to_result()?;
     Ok(RegistryCallback(cookie))
@@ -295,7 +583,7 @@ pub unsafe fn create_registry_callback<T>(func: RegistryCallbackFunc<T>, context
 pub struct RegistryCallback(pub u64);
 
 impl RegistryCallback {
-    pub unsafe fn unregister(&self) -> Result<(), NTSTATUS> {
+    pub unsafe fn unregister(&self) -> Result<(), NtStatus> {
         CmUnRegisterCallback(self.0).to_result()
     }
 }
@@ -320,6 +608,7 @@ impl RegSetValueKeyInformation {
     }
 }
 
+#[cfg(not(feature = "host-sim"))]
 extern "system" {
     fn ObQueryNameString(
         object: PVOID,
@@ -329,15 +618,18 @@ extern "system" {
     ) -> NtStatus;
 }
 
-pub unsafe fn get_object_name(object: PVOID) -> Result<String, NTSTATUS> {
+#[cfg(feature = "host-sim")]
+use crate::sim::ntoskrnl::ObQueryNameString;
+
+pub unsafe fn get_object_name(object: PVOID) -> Result<String, NtStatus> {
     if object.is_null() {
-        return Err(ntstatus::STATUS_NOT_FOUND);
+        return Err(NtStatus(ntstatus::STATUS_NOT_FOUND));
     }
 
     let mut len = 0;
     let result = ObQueryNameString(object, null_mut(), 0, &mut len);
     if result.0 != ntstatus::STATUS_INFO_LENGTH_MISMATCH {
-        return Err(ntstatus::STATUS_NOT_FOUND);
+        return Err(NtStatus(ntstatus::STATUS_NOT_FOUND));
     }
 
     let mut name_info = VariableSizedBox::new(len as usize);
@@ -346,10 +638,11 @@ pub unsafe fn get_object_name(object: PVOID) -> Result<String, NTSTATUS> {
     let name: UnicodeString = name_info.as_ref().Name.into();
     match name.try_to_string() {
         Ok(s) => Ok(s),
-        Err(_) => Err(ntstatus::STATUS_UNSUCCESSFUL)
+        Err(_) => Err(NtStatus(ntstatus::STATUS_UNSUCCESSFUL))
     }
 }
 
+#[cfg(not(feature = "host-sim"))]
 extern "system" {
     pub fn MmCopyMemory(
         target: *mut u8,
@@ -360,25 +653,41 @@ extern "system" {
     ) -> NtStatus;
 }
 
+#[cfg(feature = "host-sim")]
+pub use crate::sim::ntoskrnl::MmCopyMemory;
+
 const MM_COPY_MEMORY_PHYSICAL: u32 = 0x1;
-const MM_COPY_MEMORY_VIRTUAL: u32 = 0x2;
 
-pub unsafe fn read_physical_memory(physical_address: u64, buf: &mut [u8]) -> Result<(), (NTSTATUS, usize)> {
+/// Copies physical memory into `buf`. On failure, the returned count is the number of bytes at
+/// the start of `buf` that were filled in.
+pub unsafe fn read_physical_memory(physical_address: u64, buf: &mut [u8]) -> Result<(), (NtStatus, usize)> {
     let mut bytes_transferred = 0;
     let mut intermediate_buf = vec![0u8; buf.len()];
-    MmCopyMemory(intermediate_buf.as_mut_ptr(), physical_address as _, intermediate_buf.len(), MM_COPY_MEMORY_PHYSICAL, &mut bytes_transferred).to_result().map_err(|e| (e, bytes_transferred))?;
-    buf.copy_from_slice(&intermediate_buf);
-    if bytes_transferred != intermediate_buf.len() {
-        return Err((ntstatus::STATUS_PARTIAL_COPY, bytes_transferred));
+    let status = MmCopyMemory(intermediate_buf.as_mut_ptr(), physical_address as _, intermediate_buf.len(), MM_COPY_MEMORY_PHYSICAL, &mut bytes_transferred);
+    let copied = bytes_transferred.min(buf.len());
+    buf[..copied].copy_from_slice(&intermediate_buf[..copied]);
+    copy_result(status, copied, buf.len())
+}
+
+/// Physical memory as a [`MemoryReader`], read with [`read_physical_memory`].
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PhysicalMemory;
+
+impl MemoryReader for PhysicalMemory {
+    unsafe fn read_memory(&self, address: u64, buf: &mut [u8]) -> Result<(), (NtStatus, usize)> {
+        read_physical_memory(address, buf)
     }
-    Ok(())
 }
 
+#[cfg(not(feature = "host-sim"))]
 extern "system" {
     pub fn MmMapIoSpaceEx(physical_address: i64, len: usize, protect: u32) -> *mut c_void;
     pub fn MmUnmapIoSpace(base_address: *mut c_void, len: usize);
 }
 
+#[cfg(feature = "host-sim")]
+pub use crate::sim::ntoskrnl::{MmMapIoSpaceEx, MmUnmapIoSpace};
+
 pub struct PhysicalMap {
     buf: *mut u8,
     len: usize,
@@ -426,19 +735,27 @@ impl Drop for PhysicalMap {
     }
 }
 
+#[cfg(not(feature = "host-sim"))]
 #[link(name = "hal")]
 extern "system" {
     pub fn KeQueryPerformanceCounter(performance_frequency: *mut i64) -> u64;
 }
 
+#[cfg(feature = "host-sim")]
+pub use crate::sim::ntoskrnl::KeQueryPerformanceCounter;
+
 pub unsafe fn query_performance_counter() -> u64 {
     KeQueryPerformanceCounter(null_mut())
 }
 
+#[cfg(not(feature = "host-sim"))]
 extern "system" {
     pub fn MmIsAddressValid(virtual_address: *mut c_void) -> BOOLEAN;
 }
 
+#[cfg(feature = "host-sim")]
+pub use crate::sim::ntoskrnl::MmIsAddressValid;
+
 pub unsafe fn is_address_valid(address: usize) -> bool {
     MmIsAddressValid(address as _) == 1
 }
@@ -446,3 +763,31 @@ pub unsafe fn is_address_valid(address: usize) -> bool {
 pub unsafe fn is_valid_ptr<T>(ptr: *const T) -> bool {
     is_address_valid(ptr as _)
 }
+
+/// Kernel virtual memory as a [`MemoryReader`]. Each page is checked with `MmIsAddressValid`
+/// before it is copied, so reads of unmapped pages, such as a driver's discarded `INIT`
+/// section, fail with `STATUS_PARTIAL_COPY` instead of bugchecking.
+///
+/// A pageable page can still be paged out between the check and the copy, which is only safe
+/// below `DISPATCH_LEVEL`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct KernelMemory;
+
+impl MemoryReader for KernelMemory {
+    unsafe fn read_memory(&self, address: u64, buf: &mut [u8]) -> Result<(), (NtStatus, usize)> {
+        let mut copied = 0;
+        while copied < buf.len() {
+            let next = address.wrapping_add(copied as u64);
+            if next < address || !is_address_valid(next as usize) {
+                break;
+            }
+            let to_page_end = (KERNEL_PAGE_SIZE - next % KERNEL_PAGE_SIZE) as usize;
+            let count = to_page_end.min(buf.len() - copied);
+            core::ptr::copy_nonoverlapping(next as *const u8, buf[copied..].as_mut_ptr(), count);
+            copied += count;
+        }
+        copy_result(NtStatus(ntstatus::STATUS_SUCCESS), copied, buf.len())
+    }
+}
+
+const KERNEL_PAGE_SIZE: u64 = 0x1000;
@@ -1,7 +1,7 @@
-#![no_std]
-#![feature(alloc_error_handler)]
-#![feature(alloc_prelude)]
-#![feature(core_intrinsics)]
+#![cfg_attr(not(feature = "host-sim"), no_std)]
+#![cfg_attr(not(feature = "host-sim"), feature(alloc_error_handler))]
+#![feature(allocator_api)]
+#![cfg_attr(feature = "host-sim", feature(c_variadic))]
 #![allow(clippy::missing_safety_doc)]
 
 extern crate alloc;
@@ -10,8 +10,16 @@ pub mod allocator;
 pub mod log;
 pub mod string;
 pub mod kernel;
+pub mod mdl;
+pub mod memory;
 pub mod basedef;
 pub mod ntstatus;
 pub mod process;
+pub mod pe;
 pub mod vsb;
-pub mod util;
\ No newline at end of file
+pub mod sync;
+pub mod lookaside;
+pub mod path;
+pub mod util;
+#[cfg(feature = "host-sim")]
+pub mod sim;
\ No newline at end of file
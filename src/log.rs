@@ -1,15 +1,55 @@
+use core::fmt::{self, Write};
 use log::{Level, LevelFilter, Metadata, Record, SetLoggerError};
 use alloc::string::String;
 
+#[cfg(not(feature = "host-sim"))]
 extern "cdecl" {
     pub fn DbgPrintEx(component_id: u32, level: u32, fmt: *const u8, ...) -> i32;
 }
 
-/// Prints a string using DbgPrintEx. Automatically adds a null terminator
+#[cfg(feature = "host-sim")]
+pub use crate::sim::ntoskrnl::DbgPrintEx;
+
+/// Prints a string using DbgPrintEx. Automatically adds a null terminator. The text is passed
+/// as a `%s` argument, so `%` in it is printed as is.
 pub fn __kernel_print(mut text: String) {
     text.push('\n');
     text.push('\0');
-    unsafe { DbgPrintEx(0, 0, text.as_ptr()) };
+    unsafe { DbgPrintEx(0, 0, c"%s".as_ptr().cast(), text.as_ptr()) };
+}
+
+/// Prints formatted text using DbgPrintEx without allocating, truncating it to 255 bytes. Used
+/// where the heap can't be relied on, e.g. when an allocation has just failed.
+pub fn __kernel_print_fixed(args: fmt::Arguments) {
+    struct StackBuf {
+        buf: [u8; 256],
+        len: usize,
+    }
+
+    impl Write for StackBuf {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            for &byte in s.as_bytes() {
+                // Leave room for the newline and the terminator, and escape `%` since the text is
+                // used as the format string.
+                let needed = if byte == b'%' { 2 } else { 1 };
+                if self.len + needed > self.buf.len() - 2 {
+                    return Err(fmt::Error);
+                }
+                self.buf[self.len] = byte;
+                if byte == b'%' {
+                    self.buf[self.len + 1] = b'%';
+                }
+                self.len += needed;
+            }
+            Ok(())
+        }
+    }
+
+    let mut text = StackBuf { buf: [0; 256], len: 0 };
+    let _ = text.write_fmt(args);
+    text.buf[text.len] = b'\n';
+    text.buf[text.len + 1] = 0;
+    unsafe { DbgPrintEx(0, 0, text.buf.as_ptr()) };
 }
 
 #[macro_export]
@@ -50,7 +90,7 @@ impl KernelLogger {
     pub fn init(level: LevelFilter, prefix: &'static str) -> Result<(), SetLoggerError> {
         unsafe {
             LOGGER.prefix = prefix;
-            log::set_logger(&LOGGER)
+            log::set_logger(&*core::ptr::addr_of!(LOGGER))
                 .map(|()| log::set_max_level(level))
         }
     }
@@ -0,0 +1,275 @@
+//! Typed lookaside lists.
+//!
+//! A [`LookasideList<T>`] caches freed blocks of `size_of::<T>()` bytes so hot paths don't go to
+//! the pool allocator for every object. `ExAllocateFromLookasideListEx` and
+//! `ExFreeToLookasideListEx` are inline functions in the WDK headers; they are reimplemented here
+//! on top of the exported interlocked S-list routines.
+//!
+//! Blocks are allocated and freed through [`Pool`], so they show up in
+//! [`allocator::stats`](crate::allocator::stats) like any other `Pool` allocation, while they sit
+//! in the cache as well as while they are in use. Like other `Pool` allocations, they are not
+//! recorded by `alloc-tracking`, which only covers the global allocator.
+#![allow(non_camel_case_types, non_snake_case)]
+
+use core::alloc::Layout;
+use core::cell::UnsafeCell;
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::mem::{self, align_of, size_of, ManuallyDrop};
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, addr_of, addr_of_mut, NonNull};
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use alloc::boxed::Box;
+use crate::allocator::{Pool, PoolType, POOL_ALIGNMENT};
+use crate::basedef::*;
+use crate::ntstatus::NtStatus;
+
+/// `SLIST_HEADER` (x64 layout). The low 16 bits of `alignment` hold the depth of the list.
+#[repr(C, align(16))]
+#[derive(Default)]
+pub struct SLIST_HEADER {
+    pub alignment: u64,
+    pub region: u64,
+}
+
+/// `SLIST_ENTRY`.
+#[repr(C, align(16))]
+pub struct SLIST_ENTRY {
+    pub next: *mut SLIST_ENTRY,
+}
+
+pub type PALLOCATE_FUNCTION_EX = Option<
+    unsafe extern "system" fn(pool_type: PoolType, number_of_bytes: usize, tag: u32, lookaside: *mut LOOKASIDE_LIST_EX) -> *mut c_void,
+>;
+pub type PFREE_FUNCTION_EX = Option<unsafe extern "system" fn(buffer: *mut c_void, lookaside: *mut LOOKASIDE_LIST_EX)>;
+
+/// `LOOKASIDE_LIST_EX`, i.e. `GENERAL_LOOKASIDE_POOL`.
+#[repr(C)]
+pub struct LOOKASIDE_LIST_EX {
+    pub list_head: SLIST_HEADER,
+    pub depth: u16,
+    pub maximum_depth: u16,
+    pub total_allocates: u32,
+    pub allocate_misses: u32,
+    pub total_frees: u32,
+    pub free_misses: u32,
+    pub pool_type: PoolType,
+    pub tag: u32,
+    pub size: u32,
+    pub allocate_ex: PALLOCATE_FUNCTION_EX,
+    pub free_ex: PFREE_FUNCTION_EX,
+    pub list_entry: LIST_ENTRY,
+    pub last_total_allocates: u32,
+    pub last_allocate_misses: u32,
+    pub future: [u32; 2],
+}
+
+/// Makes `ExAllocateFromLookasideListEx` return null instead of raising when the pool is empty.
+pub const EX_LOOKASIDE_LIST_EX_FLAGS_FAIL_NO_RAISE: ULONG = 0x2;
+
+#[cfg(not(feature = "host-sim"))]
+extern "system" {
+    pub fn ExInitializeLookasideListEx(
+        lookaside: *mut LOOKASIDE_LIST_EX,
+        allocate: PALLOCATE_FUNCTION_EX,
+        free: PFREE_FUNCTION_EX,
+        pool_type: PoolType,
+        flags: ULONG,
+        size: usize,
+        tag: ULONG,
+        depth: USHORT,
+    ) -> NtStatus;
+    pub fn ExDeleteLookasideListEx(lookaside: *mut LOOKASIDE_LIST_EX);
+    pub fn ExpInterlockedPopEntrySList(list_head: *mut SLIST_HEADER) -> *mut SLIST_ENTRY;
+    pub fn ExpInterlockedPushEntrySList(list_head: *mut SLIST_HEADER, list_entry: *mut SLIST_ENTRY) -> *mut SLIST_ENTRY;
+}
+
+#[cfg(feature = "host-sim")]
+pub use crate::sim::ntoskrnl::{ExInitializeLookasideListEx, ExDeleteLookasideListEx, ExpInterlockedPopEntrySList, ExpInterlockedPushEntrySList};
+
+/// Bumps one of the statistics counters of a list that may be in use on other processors.
+unsafe fn increment(counter: *mut u32) {
+    AtomicU32::from_ptr(counter).fetch_add(1, Ordering::Relaxed);
+}
+
+/// `ExAllocateFromLookasideListEx`.
+///
+/// The list is shared with every other processor using it, so its fields are only accessed
+/// through raw pointers, and the counters atomically.
+pub unsafe fn ExAllocateFromLookasideListEx(lookaside: *mut LOOKASIDE_LIST_EX) -> *mut c_void {
+    increment(addr_of_mut!((*lookaside).total_allocates));
+
+    let entry = ExpInterlockedPopEntrySList(addr_of_mut!((*lookaside).list_head));
+    if !entry.is_null() {
+        return entry as _;
+    }
+
+    increment(addr_of_mut!((*lookaside).allocate_misses));
+    match (*lookaside).allocate_ex {
+        Some(allocate) => allocate((*lookaside).pool_type, (*lookaside).size as _, (*lookaside).tag, lookaside),
+        None => ptr::null_mut(),
+    }
+}
+
+/// `ExFreeToLookasideListEx`.
+pub unsafe fn ExFreeToLookasideListEx(lookaside: *mut LOOKASIDE_LIST_EX, entry: *mut c_void) {
+    increment(addr_of_mut!((*lookaside).total_frees));
+
+    // The kernel retunes `depth` periodically while the list is in use.
+    let depth = ptr::read_volatile(addr_of!((*lookaside).depth));
+    if ExQueryDepthSList(addr_of!((*lookaside).list_head)) >= depth {
+        increment(addr_of_mut!((*lookaside).free_misses));
+        if let Some(free) = (*lookaside).free_ex {
+            free(entry, lookaside);
+        }
+    } else {
+        ExpInterlockedPushEntrySList(addr_of_mut!((*lookaside).list_head), entry as _);
+    }
+}
+
+/// `ExQueryDepthSList`.
+pub unsafe fn ExQueryDepthSList(list_head: *const SLIST_HEADER) -> u16 {
+    AtomicU64::from_ptr(addr_of!((*list_head).alignment) as *mut u64).load(Ordering::Relaxed) as u16
+}
+
+/// The `Allocate` callback of every [`LookasideList`], allocating through [`Pool`] so the block
+/// is counted in the pool statistics.
+unsafe extern "system" fn allocate_block(pool_type: PoolType, number_of_bytes: usize, tag: u32, _lookaside: *mut LOOKASIDE_LIST_EX) -> *mut c_void {
+    let layout = Layout::from_size_align_unchecked(number_of_bytes, POOL_ALIGNMENT);
+    Pool::new(pool_type, tag).allocate_layout(layout) as _
+}
+
+/// The `Free` callback of every [`LookasideList`], the counterpart of [`allocate_block`].
+unsafe extern "system" fn free_block(buffer: *mut c_void, lookaside: *mut LOOKASIDE_LIST_EX) {
+    let pool = Pool::new((*lookaside).pool_type, (*lookaside).tag);
+    let layout = Layout::from_size_align_unchecked((*lookaside).size as usize, POOL_ALIGNMENT);
+    pool.free_layout(buffer as _, layout);
+}
+
+/// A lookaside list handing out blocks for values of type `T`.
+pub struct LookasideList<T> {
+    lookaside: Box<UnsafeCell<LOOKASIDE_LIST_EX>, Pool>,
+    _marker: PhantomData<T>,
+}
+
+// The list itself is only modified through interlocked operations and the statistics counters
+// through atomics.
+unsafe impl<T: Send> Send for LookasideList<T> {}
+unsafe impl<T: Send> Sync for LookasideList<T> {}
+
+impl<T> LookasideList<T> {
+    /// Creates a list whose blocks come from `pool_type` under `tag`.
+    ///
+    /// The list header is allocated from non-paged pool under the same tag. Types with an
+    /// alignment above the pool alignment are rejected with `STATUS_INVALID_PARAMETER`.
+    pub fn new(pool_type: PoolType, tag: u32) -> Result<Self, NtStatus> {
+        if align_of::<T>() > POOL_ALIGNMENT {
+            return Err(NtStatus(ntstatus::STATUS_INVALID_PARAMETER));
+        }
+
+        // Free blocks are linked through their first bytes, so each must fit an `SLIST_ENTRY`.
+        let size = size_of::<T>().max(size_of::<SLIST_ENTRY>());
+        let header_pool = Pool::new(PoolType::NonPagedPoolNx, tag);
+        let lookaside = Box::try_new_in(UnsafeCell::new(unsafe { mem::zeroed() }), header_pool)
+            .map_err(|_| NtStatus(ntstatus::STATUS_INSUFFICIENT_RESOURCES))?;
+
+        unsafe {
+            ExInitializeLookasideListEx(
+                lookaside.get(),
+                Some(allocate_block),
+                Some(free_block),
+                pool_type,
+                EX_LOOKASIDE_LIST_EX_FLAGS_FAIL_NO_RAISE,
+                size,
+                tag,
+                0,
+            )
+            .to_result()?;
+
+            Ok(Self { lookaside, _marker: PhantomData })
+        }
+    }
+
+    /// The raw `LOOKASIDE_LIST_EX`, still owned by this value.
+    pub fn as_raw(&self) -> *mut LOOKASIDE_LIST_EX {
+        self.lookaside.get()
+    }
+
+    /// Moves `value` into a block from the list.
+    pub fn alloc(&self, value: T) -> Result<LookasideBox<'_, T>, NtStatus> {
+        let entry = unsafe { ExAllocateFromLookasideListEx(self.as_raw()) } as *mut T;
+        match NonNull::new(entry) {
+            Some(entry) => {
+                unsafe { entry.as_ptr().write(value) };
+                Ok(LookasideBox { entry, list: self })
+            }
+            None => Err(NtStatus(ntstatus::STATUS_INSUFFICIENT_RESOURCES)),
+        }
+    }
+
+    /// How many blocks have been handed out and how many of those missed the cache, as
+    /// `(total allocates, allocate misses)`.
+    pub fn allocation_counts(&self) -> (u32, u32) {
+        let lookaside = self.as_raw();
+        unsafe {
+            let total_allocates = AtomicU32::from_ptr(addr_of_mut!((*lookaside).total_allocates));
+            let allocate_misses = AtomicU32::from_ptr(addr_of_mut!((*lookaside).allocate_misses));
+            (total_allocates.load(Ordering::Relaxed), allocate_misses.load(Ordering::Relaxed))
+        }
+    }
+
+    /// How many cached blocks are waiting to be reused.
+    pub fn cached(&self) -> u16 {
+        unsafe { ExQueryDepthSList(addr_of!((*self.as_raw()).list_head)) }
+    }
+}
+
+impl<T> Drop for LookasideList<T> {
+    fn drop(&mut self) {
+        unsafe { ExDeleteLookasideListEx(self.as_raw()) };
+    }
+}
+
+/// A `T` in a block from a [`LookasideList`], returned to the list on drop.
+pub struct LookasideBox<'a, T> {
+    entry: NonNull<T>,
+    list: &'a LookasideList<T>,
+}
+
+unsafe impl<T: Send> Send for LookasideBox<'_, T> {}
+unsafe impl<T: Sync> Sync for LookasideBox<'_, T> {}
+
+impl<T> LookasideBox<'_, T> {
+    /// Moves the value out, returning the block to the list.
+    pub fn into_inner(this: Self) -> T {
+        let this = ManuallyDrop::new(this);
+        unsafe {
+            let value = this.entry.as_ptr().read();
+            ExFreeToLookasideListEx(this.list.as_raw(), this.entry.as_ptr() as _);
+            value
+        }
+    }
+}
+
+impl<T> Deref for LookasideBox<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.entry.as_ref() }
+    }
+}
+
+impl<T> DerefMut for LookasideBox<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.entry.as_mut() }
+    }
+}
+
+impl<T> Drop for LookasideBox<'_, T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.entry.as_ptr());
+            ExFreeToLookasideListEx(self.list.as_raw(), self.entry.as_ptr() as _);
+        }
+    }
+}
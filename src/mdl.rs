@@ -0,0 +1,280 @@
+//! Owned memory descriptor lists.
+//!
+//! An [`Mdl`] moves through the `Allocated -> Locked -> Mapped` states by value, and dropping it
+//! in any state undoes exactly the steps that were taken to get there.
+
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ptr::{null_mut, NonNull};
+use crate::basedef::*;
+use crate::ntstatus::NtStatus;
+
+#[allow(non_camel_case_types)]
+#[repr(i32)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LOCK_OPERATION {
+    IoReadAccess = 0,
+    IoWriteAccess = 1,
+    IoModifyAccess = 2,
+}
+
+#[allow(non_camel_case_types)]
+#[repr(i32)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MEMORY_CACHING_TYPE {
+    MmNonCached = 0,
+    MmCached = 1,
+    MmWriteCombined = 2,
+    MmHardwareCoherentCached = 3,
+    MmNonCachedUnordered = 4,
+    MmUSWCCached = 5,
+    MmMaximumCacheType = 6,
+    MmNotMapped = -1,
+}
+
+/// The priority passed to `MmMapLockedPagesSpecifyCache`.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MmPagePriority {
+    LowPagePriority = 0,
+    NormalPagePriority = 16,
+    HighPagePriority = 32,
+}
+
+/// Or'd into the page priority to map the pages as non-executable.
+const MDL_MAPPING_NO_EXECUTE: u32 = 0x40000000;
+
+#[cfg(not(feature = "host-sim"))]
+extern "system" {
+    pub fn IoAllocateMdl(
+        virtual_address: *mut c_void,
+        length: u32,
+        secondary_buffer: u8,
+        charge_quota: u8,
+        irp: *mut c_void,
+    ) -> PMDL;
+
+    pub fn MmProbeAndLockPages(
+        memory_descriptor_list: PMDL,
+        access_mode: KProcessorMode,
+        operation: LOCK_OPERATION,
+    );
+
+    pub fn MmMapLockedPagesSpecifyCache(
+        memory_descriptor_list: PMDL,
+        access_mode: KProcessorMode,
+        cache_type: MEMORY_CACHING_TYPE,
+        requested_address: PVOID,
+        bug_check_on_failure: ULONG,
+        priority: ULONG,
+    ) -> PVOID;
+
+    pub fn MmProtectMdlSystemAddress(memory_descriptor_list: PMDL, new_protect: ULONG) -> NtStatus;
+
+    pub fn MmUnmapLockedPages(base_address: PVOID, memory_descriptor_list: PMDL);
+    pub fn MmUnlockPages(memory_descriptor_list: PMDL);
+    pub fn IoFreeMdl(mdl: PMDL);
+}
+
+#[cfg(feature = "host-sim")]
+pub use crate::sim::ntoskrnl::{IoAllocateMdl, MmProbeAndLockPages, MmMapLockedPagesSpecifyCache, MmProtectMdlSystemAddress, MmUnmapLockedPages, MmUnlockPages, IoFreeMdl};
+
+/// A state an [`Mdl`] can be in.
+pub trait MdlState {
+    #[doc(hidden)]
+    const LOCKED: bool;
+    #[doc(hidden)]
+    const MAPPED: bool;
+}
+
+/// The MDL has been allocated but its pages are not locked.
+pub struct Allocated;
+/// The pages described by the MDL are probed and locked.
+pub struct Locked;
+/// The locked pages are mapped into system space.
+pub struct Mapped;
+
+impl MdlState for Allocated {
+    const LOCKED: bool = false;
+    const MAPPED: bool = false;
+}
+
+impl MdlState for Locked {
+    const LOCKED: bool = true;
+    const MAPPED: bool = false;
+}
+
+impl MdlState for Mapped {
+    const LOCKED: bool = true;
+    const MAPPED: bool = true;
+}
+
+/// Options used when locking and mapping an [`Mdl`].
+#[derive(Copy, Clone, Debug)]
+pub struct MdlBuilder {
+    address: *mut c_void,
+    len: usize,
+    access_mode: KProcessorMode,
+    operation: LOCK_OPERATION,
+    cache_type: MEMORY_CACHING_TYPE,
+    priority: MmPagePriority,
+    no_execute: bool,
+}
+
+impl MdlBuilder {
+    /// Describes `len` bytes at `address`. By default the pages are locked for read access in
+    /// kernel mode and mapped cached with normal priority.
+    pub fn new(address: *mut c_void, len: usize) -> Self {
+        Self {
+            address,
+            len,
+            access_mode: KProcessorMode::KernelMode,
+            operation: LOCK_OPERATION::IoReadAccess,
+            cache_type: MEMORY_CACHING_TYPE::MmCached,
+            priority: MmPagePriority::NormalPagePriority,
+            no_execute: false,
+        }
+    }
+
+    pub fn access_mode(mut self, access_mode: KProcessorMode) -> Self {
+        self.access_mode = access_mode;
+        self
+    }
+
+    pub fn operation(mut self, operation: LOCK_OPERATION) -> Self {
+        self.operation = operation;
+        self
+    }
+
+    pub fn cache_type(mut self, cache_type: MEMORY_CACHING_TYPE) -> Self {
+        self.cache_type = cache_type;
+        self
+    }
+
+    pub fn priority(mut self, priority: MmPagePriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn no_execute(mut self, no_execute: bool) -> Self {
+        self.no_execute = no_execute;
+        self
+    }
+
+    /// Allocates the MDL with `IoAllocateMdl`.
+    pub unsafe fn allocate(self) -> Result<Mdl<Allocated>, NtStatus> {
+        if self.len > u32::MAX as usize {
+            return Err(NtStatus(ntstatus::STATUS_INVALID_PARAMETER));
+        }
+
+        let mdl = IoAllocateMdl(self.address, self.len as _, FALSE, FALSE, null_mut());
+        match NonNull::new(mdl) {
+            Some(mdl) => Ok(Mdl { mdl, mapping: null_mut(), options: self, state: PhantomData }),
+            None => Err(NtStatus(ntstatus::STATUS_INSUFFICIENT_RESOURCES)),
+        }
+    }
+}
+
+/// An owned memory descriptor list in the state `S`.
+pub struct Mdl<S: MdlState> {
+    mdl: NonNull<c_void>,
+    mapping: PVOID,
+    options: MdlBuilder,
+    state: PhantomData<S>,
+}
+
+impl<S: MdlState> Mdl<S> {
+    /// The raw `PMDL`, still owned by this value.
+    pub fn as_raw(&self) -> PMDL {
+        self.mdl.as_ptr()
+    }
+
+    pub fn len(&self) -> usize {
+        self.options.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.options.len == 0
+    }
+
+    /// Moves to state `T` without running `Drop` on `self`.
+    fn transition<T: MdlState>(self, mapping: PVOID) -> Mdl<T> {
+        let this = ManuallyDrop::new(self);
+        Mdl { mdl: this.mdl, mapping, options: this.options, state: PhantomData }
+    }
+}
+
+impl Mdl<Allocated> {
+    pub fn builder(address: *mut c_void, len: usize) -> MdlBuilder {
+        MdlBuilder::new(address, len)
+    }
+
+    /// Probes and locks the described pages with `MmProbeAndLockPages`.
+    ///
+    /// # Safety
+    /// `MmProbeAndLockPages` raises an exception if the pages cannot be locked, so the range must
+    /// be known to be valid for the requested access.
+    pub unsafe fn lock(self) -> Mdl<Locked> {
+        MmProbeAndLockPages(self.as_raw(), self.options.access_mode, self.options.operation);
+        self.transition(null_mut())
+    }
+}
+
+impl Mdl<Locked> {
+    /// Maps the locked pages into system space. The pages are unlocked again if this fails.
+    pub unsafe fn map(self) -> Result<Mdl<Mapped>, NtStatus> {
+        let mut priority = self.options.priority as u32;
+        if self.options.no_execute {
+            priority |= MDL_MAPPING_NO_EXECUTE;
+        }
+
+        let mapping = MmMapLockedPagesSpecifyCache(
+            self.as_raw(),
+            self.options.access_mode,
+            self.options.cache_type,
+            null_mut(),
+            FALSE as _,
+            priority,
+        );
+
+        if mapping.is_null() {
+            return Err(NtStatus(ntstatus::STATUS_INSUFFICIENT_RESOURCES));
+        }
+
+        Ok(self.transition(mapping))
+    }
+}
+
+impl Mdl<Mapped> {
+    /// Changes the protection of the system mapping, e.g. to `PAGE_READWRITE`.
+    pub unsafe fn protect(&self, new_protect: ULONG) -> Result<(), NtStatus> {
+        MmProtectMdlSystemAddress(self.as_raw(), new_protect).to_result()
+    }
+
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.mapping as _
+    }
+
+    pub unsafe fn as_slice(&self) -> &[u8] {
+        core::slice::from_raw_parts(self.as_ptr(), self.len())
+    }
+
+    pub unsafe fn as_mut_slice(&mut self) -> &mut [u8] {
+        core::slice::from_raw_parts_mut(self.as_ptr(), self.len())
+    }
+}
+
+impl<S: MdlState> Drop for Mdl<S> {
+    fn drop(&mut self) {
+        unsafe {
+            if S::MAPPED {
+                MmUnmapLockedPages(self.mapping, self.as_raw());
+            }
+            if S::LOCKED {
+                MmUnlockPages(self.as_raw());
+            }
+            IoFreeMdl(self.as_raw());
+        }
+    }
+}
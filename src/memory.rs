@@ -0,0 +1,161 @@
+//! Typed access to memory that can't be dereferenced directly, such as another process's address
+//! space or physical memory.
+
+use alloc::vec::Vec;
+use core::mem::{size_of, size_of_val, MaybeUninit};
+use core::slice;
+use crate::basedef::ntstatus;
+use crate::ntstatus::NtStatus;
+use crate::pe::{ImageReader, PeError};
+
+/// Strings are read in chunks that never cross a page, so a string ending just before an
+/// unmapped page is read without error.
+const PAGE_SIZE: u64 = 0x1000;
+const STRING_CHUNK: usize = 256;
+
+/// Types that can be copied to and from raw memory: they have no padding and every bit pattern
+/// is a valid value.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($ty:ty),*) => {
+        $(unsafe impl Pod for $ty {})*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// A source of memory addressed by 64-bit addresses.
+///
+/// Failed copies report the status and how much was copied before the failure, e.g.
+/// `STATUS_PARTIAL_COPY` when a read runs into an unmapped page.
+pub trait MemoryReader {
+    /// Copies `buf.len()` bytes starting at `address` into `buf`. On failure, the returned count
+    /// is the number of bytes at the start of `buf` that were filled in.
+    unsafe fn read_memory(&self, address: u64, buf: &mut [u8]) -> Result<(), (NtStatus, usize)>;
+
+    unsafe fn read<T: Pod>(&self, address: u64) -> Result<T, NtStatus> {
+        let mut value = MaybeUninit::<T>::zeroed();
+        let bytes = slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, size_of::<T>());
+        self.read_memory(address, bytes).map_err(|(status, _)| status)?;
+        Ok(value.assume_init())
+    }
+
+    /// Fills `values` from consecutive `T`s at `address`. On failure, the returned count is the
+    /// number of complete values that were read.
+    unsafe fn read_slice<T: Pod>(&self, address: u64, values: &mut [T]) -> Result<(), (NtStatus, usize)> {
+        let bytes = slice::from_raw_parts_mut(values.as_mut_ptr() as *mut u8, size_of_val(values));
+        self.read_memory(address, bytes).map_err(|(status, read)| (status, read / size_of::<T>().max(1)))
+    }
+
+    /// Reads a null-terminated byte string of at most `max_len` bytes, not counting the
+    /// terminator. Longer strings fail with `STATUS_NAME_TOO_LONG`.
+    unsafe fn read_cstring(&self, address: u64, max_len: usize) -> Result<Vec<u8>, NtStatus> {
+        read_terminated(self, address, max_len)
+    }
+
+    /// Reads a null-terminated UTF-16 string of at most `max_len` code units, not counting the
+    /// terminator. Longer strings fail with `STATUS_NAME_TOO_LONG`.
+    unsafe fn read_wide_string(&self, address: u64, max_len: usize) -> Result<Vec<u16>, NtStatus> {
+        read_terminated(self, address, max_len)
+    }
+}
+
+/// A destination for memory writes, the counterpart of [`MemoryReader`].
+pub trait MemoryWriter {
+    /// Copies `buf` to `address`. On failure, the returned count is the number of bytes at the
+    /// start of `buf` that were written.
+    unsafe fn write_memory(&self, address: u64, buf: &[u8]) -> Result<(), (NtStatus, usize)>;
+
+    unsafe fn write<T: Pod>(&self, address: u64, value: &T) -> Result<(), NtStatus> {
+        let bytes = slice::from_raw_parts(value as *const T as *const u8, size_of::<T>());
+        self.write_memory(address, bytes).map_err(|(status, _)| status)
+    }
+
+    /// Writes `values` as consecutive `T`s at `address`. On failure, the returned count is the
+    /// number of complete values that were written.
+    unsafe fn write_slice<T: Pod>(&self, address: u64, values: &[T]) -> Result<(), (NtStatus, usize)> {
+        let bytes = slice::from_raw_parts(values.as_ptr() as *const u8, size_of_val(values));
+        self.write_memory(address, bytes).map_err(|(status, written)| (status, written / size_of::<T>().max(1)))
+    }
+}
+
+impl<R: MemoryReader + ?Sized> MemoryReader for &R {
+    unsafe fn read_memory(&self, address: u64, buf: &mut [u8]) -> Result<(), (NtStatus, usize)> {
+        (**self).read_memory(address, buf)
+    }
+}
+
+impl<W: MemoryWriter + ?Sized> MemoryWriter for &W {
+    unsafe fn write_memory(&self, address: u64, buf: &[u8]) -> Result<(), (NtStatus, usize)> {
+        (**self).write_memory(address, buf)
+    }
+}
+
+/// An image mapped at `base` in the memory of a reader, e.g. a module loaded in another process,
+/// for use with [`PeImage`](crate::pe::PeImage) in the mapped layout.
+pub struct MemoryImage<R> {
+    reader: R,
+    base: u64,
+    size: usize,
+}
+
+impl<R: MemoryReader> MemoryImage<R> {
+    /// # Safety
+    /// Reads through `reader` must be safe for the whole `size` bytes at `base`, for as long as
+    /// the image is used.
+    pub unsafe fn new(reader: R, base: u64, size: usize) -> Self {
+        Self { reader, base, size }
+    }
+}
+
+impl<R: MemoryReader> ImageReader for MemoryImage<R> {
+    fn read(&self, offset: usize, buf: &mut [u8]) -> Result<(), PeError> {
+        match offset.checked_add(buf.len()) {
+            Some(end) if end <= self.size => {}
+            _ => return Err(PeError::OutOfBounds { offset, len: buf.len() }),
+        }
+        unsafe { self.reader.read_memory(self.base.wrapping_add(offset as u64), buf) }
+            .map_err(|(status, _)| PeError::ReadFailed { offset, status })
+    }
+}
+
+/// Turns the status and byte count of a copy routine into a result. Copies that come up short
+/// fail even when the routine only returned a warning such as `STATUS_PARTIAL_COPY`.
+pub(crate) fn copy_result(status: NtStatus, copied: usize, len: usize) -> Result<(), (NtStatus, usize)> {
+    status.to_result().map_err(|status| (status, copied))?;
+    if copied < len {
+        let status = if status.0 == ntstatus::STATUS_SUCCESS { NtStatus(ntstatus::STATUS_PARTIAL_COPY) } else { status };
+        return Err((status, copied));
+    }
+    Ok(())
+}
+
+unsafe fn read_terminated<R, T>(reader: &R, address: u64, max_len: usize) -> Result<Vec<T>, NtStatus>
+where
+    R: MemoryReader + ?Sized,
+    T: Pod + Default + PartialEq,
+{
+    let mut string = Vec::new();
+    let mut chunk = [T::default(); STRING_CHUNK];
+    let mut next = address;
+
+    loop {
+        let to_page_end = ((PAGE_SIZE - next % PAGE_SIZE) as usize / size_of::<T>()).max(1);
+        let count = to_page_end.min(STRING_CHUNK).min(max_len.saturating_add(1) - string.len());
+        reader.read_slice(next, &mut chunk[..count]).map_err(|(status, _)| status)?;
+
+        if let Some(end) = chunk[..count].iter().position(|&c| c == T::default()) {
+            string.extend_from_slice(&chunk[..end]);
+            return Ok(string);
+        }
+        if string.len() + count > max_len {
+            return Err(NtStatus(ntstatus::STATUS_NAME_TOO_LONG));
+        }
+
+        string.extend_from_slice(&chunk[..count]);
+        next = next.wrapping_add((count * size_of::<T>()) as u64);
+    }
+}
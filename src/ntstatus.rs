@@ -1,9 +1,12 @@
+use core::fmt;
 use crate::basedef::NTSTATUS;
 
+/// An `NTSTATUS` value, used as the error type of every fallible API in this crate.
 #[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
 pub struct NtStatus(pub NTSTATUS);
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum NtStatusType {
     Success,
     Information,
@@ -12,13 +15,57 @@ pub enum NtStatusType {
 }
 
 impl NtStatus {
+    /// The severity, stored in the top two bits.
+    pub fn severity(&self) -> NtStatusType {
+        match (self.0 as u32) >> 30 {
+            0 => NtStatusType::Success,
+            1 => NtStatusType::Information,
+            2 => NtStatusType::Warning,
+            _ => NtStatusType::Error,
+        }
+    }
+
     pub fn get_type(&self) -> NtStatusType {
-        match self.0 {
-            s if nt_information(s) => NtStatusType::Information,
-            s if nt_success(s) => NtStatusType::Success,
-            s if nt_warning(s) => NtStatusType::Warning,
-            s if nt_error(s) => NtStatusType::Error,
-            _ => unreachable!()
+        self.severity()
+    }
+
+    /// Whether the customer bit is set, meaning the code was defined by a third party.
+    pub fn is_customer(&self) -> bool {
+        (self.0 as u32) & 0x2000_0000 != 0
+    }
+
+    /// The facility, e.g. `FACILITY_DEBUGGER` (1) or `FACILITY_NTWIN32` (7).
+    pub fn facility(&self) -> u16 {
+        (((self.0 as u32) >> 16) & 0xFFF) as u16
+    }
+
+    /// The facility's status code.
+    pub fn code(&self) -> u16 {
+        self.0 as u16
+    }
+
+    /// The symbolic name of the status, e.g. `STATUS_ACCESS_DENIED`, if it is a known code.
+    pub fn name(&self) -> Option<&'static str> {
+        status_name(self.0)
+    }
+
+    /// The Win32 error code, as returned by `RtlNtStatusToDosError`. Exception statuses map to the
+    /// matching `EXCEPTION_*` code, which has the same value, and statuses without a mapping
+    /// return `ERROR_MR_MID_NOT_FOUND`.
+    pub fn to_win32(&self) -> u32 {
+        if self.facility() == FACILITY_NTWIN32 {
+            return self.code() as u32;
+        }
+        status_to_win32(self.0).unwrap_or(win32::ERROR_MR_MID_NOT_FOUND)
+    }
+
+    /// The closest POSIX `errno` value. Unknown success and informational statuses return `0`,
+    /// everything else unknown returns `EIO`.
+    pub fn to_errno(&self) -> i32 {
+        match status_to_errno(self.0) {
+            Some(errno) => errno,
+            None if nt_success(self.0) => 0,
+            None => errno::EIO,
         }
     }
 
@@ -34,16 +81,16 @@ impl NtStatus {
         self.get_type() == NtStatusType::Error
     }
 
-    pub fn to_result(&self) -> Result<(), NTSTATUS> {
+    pub fn to_result(&self) -> Result<(), NtStatus> {
         match self.get_type() {
-            NtStatusType::Error => Err(self.0),
+            NtStatusType::Error => Err(*self),
             _ => Ok(())
         }
     }
 
-    pub fn to_result_with_value<T>(&self, value: T) -> Result<T, NTSTATUS> {
+    pub fn to_result_with_value<T>(&self, value: T) -> Result<T, NtStatus> {
         match self.get_type() {
-            NtStatusType::Error => Err(self.0),
+            NtStatusType::Error => Err(*self),
             _ => Ok(value)
         }
     }
@@ -55,6 +102,364 @@ impl From<NTSTATUS> for NtStatus {
     }
 }
 
+impl From<NtStatus> for NTSTATUS {
+    fn from(s: NtStatus) -> Self {
+        s.0
+    }
+}
+
+/// Formats as the symbolic name, falling back to the hex code.
+impl fmt::Display for NtStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{:#010X}", self.0 as u32),
+        }
+    }
+}
+
+impl fmt::Debug for NtStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{} ({:#010X})", name, self.0 as u32),
+            None => write!(f, "NtStatus({:#010X})", self.0 as u32),
+        }
+    }
+}
+
+/// Win32 error codes returned by [`NtStatus::to_win32`].
+pub mod win32 {
+    pub const ERROR_SUCCESS: u32 = 0;
+    pub const ERROR_INVALID_FUNCTION: u32 = 1;
+    pub const ERROR_FILE_NOT_FOUND: u32 = 2;
+    pub const ERROR_PATH_NOT_FOUND: u32 = 3;
+    pub const ERROR_TOO_MANY_OPEN_FILES: u32 = 4;
+    pub const ERROR_ACCESS_DENIED: u32 = 5;
+    pub const ERROR_INVALID_HANDLE: u32 = 6;
+    pub const ERROR_NOT_ENOUGH_MEMORY: u32 = 8;
+    pub const ERROR_NOT_SAME_DEVICE: u32 = 17;
+    pub const ERROR_NO_MORE_FILES: u32 = 18;
+    pub const ERROR_WRITE_PROTECT: u32 = 19;
+    pub const ERROR_NOT_READY: u32 = 21;
+    pub const ERROR_BAD_COMMAND: u32 = 22;
+    pub const ERROR_CRC: u32 = 23;
+    pub const ERROR_BAD_LENGTH: u32 = 24;
+    pub const ERROR_GEN_FAILURE: u32 = 31;
+    pub const ERROR_SHARING_VIOLATION: u32 = 32;
+    pub const ERROR_LOCK_VIOLATION: u32 = 33;
+    pub const ERROR_HANDLE_EOF: u32 = 38;
+    pub const ERROR_NOT_SUPPORTED: u32 = 50;
+    pub const ERROR_DUP_NAME: u32 = 52;
+    pub const ERROR_BAD_NETPATH: u32 = 53;
+    pub const ERROR_DEV_NOT_EXIST: u32 = 55;
+    pub const ERROR_UNEXP_NET_ERR: u32 = 59;
+    pub const ERROR_NETNAME_DELETED: u32 = 64;
+    pub const ERROR_NETWORK_ACCESS_DENIED: u32 = 65;
+    pub const ERROR_BAD_DEV_TYPE: u32 = 66;
+    pub const ERROR_BAD_NET_NAME: u32 = 67;
+    pub const ERROR_REQ_NOT_ACCEP: u32 = 71;
+    pub const ERROR_INVALID_PASSWORD: u32 = 86;
+    pub const ERROR_INVALID_PARAMETER: u32 = 87;
+    pub const ERROR_BROKEN_PIPE: u32 = 109;
+    pub const ERROR_DISK_FULL: u32 = 112;
+    pub const ERROR_SEM_TIMEOUT: u32 = 121;
+    pub const ERROR_INSUFFICIENT_BUFFER: u32 = 122;
+    pub const ERROR_INVALID_NAME: u32 = 123;
+    pub const ERROR_INVALID_LEVEL: u32 = 124;
+    pub const ERROR_MOD_NOT_FOUND: u32 = 126;
+    pub const ERROR_PROC_NOT_FOUND: u32 = 127;
+    pub const ERROR_DIR_NOT_EMPTY: u32 = 145;
+    pub const ERROR_SIGNAL_REFUSED: u32 = 156;
+    pub const ERROR_NOT_LOCKED: u32 = 158;
+    pub const ERROR_BAD_PATHNAME: u32 = 161;
+    pub const ERROR_BUSY: u32 = 170;
+    pub const ERROR_ALREADY_EXISTS: u32 = 183;
+    pub const ERROR_BAD_EXE_FORMAT: u32 = 193;
+    pub const ERROR_FILENAME_EXCED_RANGE: u32 = 206;
+    pub const ERROR_PIPE_BUSY: u32 = 231;
+    pub const ERROR_MORE_DATA: u32 = 234;
+    pub const WAIT_TIMEOUT: u32 = 258;
+    pub const ERROR_NO_MORE_ITEMS: u32 = 259;
+    pub const ERROR_DIRECTORY: u32 = 267;
+    pub const ERROR_NOT_OWNER: u32 = 288;
+    pub const ERROR_TOO_MANY_POSTS: u32 = 298;
+    pub const ERROR_PARTIAL_COPY: u32 = 299;
+    pub const ERROR_MR_MID_NOT_FOUND: u32 = 317;
+    pub const ERROR_INVALID_ADDRESS: u32 = 487;
+    pub const ERROR_ARITHMETIC_OVERFLOW: u32 = 534;
+    pub const ERROR_INVALID_IMAGE_HASH: u32 = 577;
+    pub const ERROR_NOINTERFACE: u32 = 632;
+    pub const ERROR_IMAGE_NOT_AT_BASE: u32 = 700;
+    pub const ERROR_REPARSE: u32 = 741;
+    pub const ERROR_OPERATION_ABORTED: u32 = 995;
+    pub const ERROR_IO_PENDING: u32 = 997;
+    pub const ERROR_NOACCESS: u32 = 998;
+    pub const ERROR_SWAPERROR: u32 = 999;
+    pub const ERROR_STACK_OVERFLOW: u32 = 1001;
+    pub const ERROR_FILE_INVALID: u32 = 1006;
+    pub const ERROR_NO_TOKEN: u32 = 1008;
+    pub const ERROR_SERVICE_ALREADY_RUNNING: u32 = 1056;
+    pub const ERROR_IO_DEVICE: u32 = 1117;
+    pub const ERROR_POSSIBLE_DEADLOCK: u32 = 1131;
+    pub const ERROR_TOO_MANY_LINKS: u32 = 1142;
+    pub const ERROR_NOT_FOUND: u32 = 1168;
+    pub const ERROR_CONNECTION_REFUSED: u32 = 1225;
+    pub const ERROR_NETWORK_UNREACHABLE: u32 = 1231;
+    pub const ERROR_HOST_UNREACHABLE: u32 = 1232;
+    pub const ERROR_CONNECTION_ABORTED: u32 = 1236;
+    pub const ERROR_RETRY: u32 = 1237;
+    pub const ERROR_NOT_ALL_ASSIGNED: u32 = 1300;
+    pub const ERROR_SOME_NOT_MAPPED: u32 = 1301;
+    pub const ERROR_NO_SUCH_PRIVILEGE: u32 = 1313;
+    pub const ERROR_PRIVILEGE_NOT_HELD: u32 = 1314;
+    pub const ERROR_LOGON_FAILURE: u32 = 1326;
+    pub const ERROR_INTERNAL_ERROR: u32 = 1359;
+    pub const ERROR_NO_SYSTEM_RESOURCES: u32 = 1450;
+    pub const ERROR_WORKING_SET_QUOTA: u32 = 1453;
+    pub const ERROR_DEVICE_REMOVED: u32 = 1617;
+    pub const ERROR_INVALID_USER_BUFFER: u32 = 1784;
+    pub const ERROR_NOT_ENOUGH_QUOTA: u32 = 1816;
+    pub const ERROR_CANT_ACCESS_FILE: u32 = 1920;
+    pub const ERROR_NOT_A_REPARSE_POINT: u32 = 4390;
+
+    pub const EXCEPTION_GUARD_PAGE: u32 = 0x80000001;
+    pub const EXCEPTION_BREAKPOINT: u32 = 0x80000003;
+    pub const EXCEPTION_SINGLE_STEP: u32 = 0x80000004;
+    pub const EXCEPTION_ILLEGAL_INSTRUCTION: u32 = 0xC000001D;
+    pub const EXCEPTION_NONCONTINUABLE_EXCEPTION: u32 = 0xC0000025;
+    pub const EXCEPTION_ARRAY_BOUNDS_EXCEEDED: u32 = 0xC000008C;
+    pub const EXCEPTION_INT_DIVIDE_BY_ZERO: u32 = 0xC0000094;
+    pub const EXCEPTION_PRIV_INSTRUCTION: u32 = 0xC0000096;
+}
+
+/// Linux `errno` values returned by [`NtStatus::to_errno`].
+pub mod errno {
+    pub const EPERM: i32 = 1;
+    pub const ENOENT: i32 = 2;
+    pub const ESRCH: i32 = 3;
+    pub const EINTR: i32 = 4;
+    pub const EIO: i32 = 5;
+    pub const ENOEXEC: i32 = 8;
+    pub const EBADF: i32 = 9;
+    pub const EAGAIN: i32 = 11;
+    pub const ENOMEM: i32 = 12;
+    pub const EACCES: i32 = 13;
+    pub const EFAULT: i32 = 14;
+    pub const EBUSY: i32 = 16;
+    pub const EEXIST: i32 = 17;
+    pub const EXDEV: i32 = 18;
+    pub const ENODEV: i32 = 19;
+    pub const ENOTDIR: i32 = 20;
+    pub const EISDIR: i32 = 21;
+    pub const EINVAL: i32 = 22;
+    pub const EMFILE: i32 = 24;
+    pub const ENOSPC: i32 = 28;
+    pub const EROFS: i32 = 30;
+    pub const EMLINK: i32 = 31;
+    pub const EPIPE: i32 = 32;
+    pub const EDEADLK: i32 = 35;
+    pub const ENAMETOOLONG: i32 = 36;
+    pub const ENOLCK: i32 = 37;
+    pub const ENOSYS: i32 = 38;
+    pub const ENOTEMPTY: i32 = 39;
+    pub const ENODATA: i32 = 61;
+    pub const EOVERFLOW: i32 = 75;
+    pub const EOPNOTSUPP: i32 = 95;
+    pub const EADDRINUSE: i32 = 98;
+    pub const ENETUNREACH: i32 = 101;
+    pub const ECONNABORTED: i32 = 103;
+    pub const ECONNRESET: i32 = 104;
+    pub const ENOBUFS: i32 = 105;
+    pub const ETIMEDOUT: i32 = 110;
+    pub const ECONNREFUSED: i32 = 111;
+    pub const EHOSTUNREACH: i32 = 113;
+    pub const EINPROGRESS: i32 = 115;
+    pub const EDQUOT: i32 = 122;
+    pub const ECANCELED: i32 = 125;
+    pub const EKEYREJECTED: i32 = 129;
+}
+
+/// Facility of statuses wrapping a Win32 error code in their low 16 bits.
+const FACILITY_NTWIN32: u16 = 7;
+
+/// Generates the name, Win32 error and errno lookups from one table, so the three can't drift
+/// apart. Each row is `NAME = code => win32 error, errno;`, using `0` where there is no errno.
+macro_rules! status_table {
+    ($($name:ident = $value:literal => $win32:ident, $errno:tt;)*) => {
+        fn status_name(status: NTSTATUS) -> Option<&'static str> {
+            match status as u32 {
+                $($value => Some(stringify!($name)),)*
+                _ => None,
+            }
+        }
+
+        fn status_to_win32(status: NTSTATUS) -> Option<u32> {
+            match status as u32 {
+                $($value => Some(win32::$win32),)*
+                _ => None,
+            }
+        }
+
+        fn status_to_errno(status: NTSTATUS) -> Option<i32> {
+            match status as u32 {
+                $($value => Some(status_table!(@errno $errno)),)*
+                _ => None,
+            }
+        }
+    };
+    (@errno 0) => { 0 };
+    (@errno $errno:ident) => { errno::$errno };
+}
+
+status_table! {
+    STATUS_SUCCESS = 0x00000000 => ERROR_SUCCESS, 0;
+    STATUS_ABANDONED = 0x00000080 => ERROR_MR_MID_NOT_FOUND, 0;
+    STATUS_USER_APC = 0x000000C0 => ERROR_MR_MID_NOT_FOUND, EINTR;
+    STATUS_ALERTED = 0x00000101 => ERROR_MR_MID_NOT_FOUND, EINTR;
+    STATUS_TIMEOUT = 0x00000102 => WAIT_TIMEOUT, ETIMEDOUT;
+    STATUS_PENDING = 0x00000103 => ERROR_IO_PENDING, EINPROGRESS;
+    STATUS_REPARSE = 0x00000104 => ERROR_REPARSE, 0;
+    STATUS_MORE_ENTRIES = 0x00000105 => ERROR_MORE_DATA, 0;
+    STATUS_NOT_ALL_ASSIGNED = 0x00000106 => ERROR_NOT_ALL_ASSIGNED, EPERM;
+    STATUS_SOME_NOT_MAPPED = 0x00000107 => ERROR_SOME_NOT_MAPPED, 0;
+    STATUS_OBJECT_NAME_EXISTS = 0x40000000 => ERROR_ALREADY_EXISTS, 0;
+    STATUS_IMAGE_NOT_AT_BASE = 0x40000003 => ERROR_IMAGE_NOT_AT_BASE, 0;
+    STATUS_GUARD_PAGE_VIOLATION = 0x80000001 => EXCEPTION_GUARD_PAGE, EFAULT;
+    STATUS_DATATYPE_MISALIGNMENT = 0x80000002 => ERROR_NOACCESS, EFAULT;
+    STATUS_BREAKPOINT = 0x80000003 => EXCEPTION_BREAKPOINT, EFAULT;
+    STATUS_SINGLE_STEP = 0x80000004 => EXCEPTION_SINGLE_STEP, EFAULT;
+    STATUS_BUFFER_OVERFLOW = 0x80000005 => ERROR_MORE_DATA, EOVERFLOW;
+    STATUS_NO_MORE_FILES = 0x80000006 => ERROR_NO_MORE_FILES, ENOENT;
+    STATUS_PARTIAL_COPY = 0x8000000D => ERROR_PARTIAL_COPY, EFAULT;
+    STATUS_DEVICE_BUSY = 0x80000011 => ERROR_BUSY, EBUSY;
+    STATUS_NO_MORE_ENTRIES = 0x8000001A => ERROR_NO_MORE_ITEMS, ENOENT;
+    STATUS_UNSUCCESSFUL = 0xC0000001 => ERROR_GEN_FAILURE, EIO;
+    STATUS_NOT_IMPLEMENTED = 0xC0000002 => ERROR_INVALID_FUNCTION, ENOSYS;
+    STATUS_INVALID_INFO_CLASS = 0xC0000003 => ERROR_INVALID_PARAMETER, EINVAL;
+    STATUS_INFO_LENGTH_MISMATCH = 0xC0000004 => ERROR_BAD_LENGTH, EINVAL;
+    STATUS_ACCESS_VIOLATION = 0xC0000005 => ERROR_NOACCESS, EFAULT;
+    STATUS_IN_PAGE_ERROR = 0xC0000006 => ERROR_SWAPERROR, EIO;
+    STATUS_INVALID_HANDLE = 0xC0000008 => ERROR_INVALID_HANDLE, EBADF;
+    STATUS_INVALID_CID = 0xC000000B => ERROR_INVALID_PARAMETER, ESRCH;
+    STATUS_INVALID_PARAMETER = 0xC000000D => ERROR_INVALID_PARAMETER, EINVAL;
+    STATUS_NO_SUCH_DEVICE = 0xC000000E => ERROR_FILE_NOT_FOUND, ENODEV;
+    STATUS_NO_SUCH_FILE = 0xC000000F => ERROR_FILE_NOT_FOUND, ENOENT;
+    STATUS_INVALID_DEVICE_REQUEST = 0xC0000010 => ERROR_INVALID_FUNCTION, EINVAL;
+    STATUS_END_OF_FILE = 0xC0000011 => ERROR_HANDLE_EOF, ENODATA;
+    STATUS_NO_MEMORY = 0xC0000017 => ERROR_NOT_ENOUGH_MEMORY, ENOMEM;
+    STATUS_CONFLICTING_ADDRESSES = 0xC0000018 => ERROR_INVALID_ADDRESS, EINVAL;
+    STATUS_NOT_MAPPED_VIEW = 0xC0000019 => ERROR_INVALID_ADDRESS, EINVAL;
+    STATUS_UNABLE_TO_FREE_VM = 0xC000001A => ERROR_INVALID_PARAMETER, EINVAL;
+    STATUS_INVALID_SYSTEM_SERVICE = 0xC000001C => ERROR_INVALID_FUNCTION, ENOSYS;
+    STATUS_ILLEGAL_INSTRUCTION = 0xC000001D => EXCEPTION_ILLEGAL_INSTRUCTION, EFAULT;
+    STATUS_INVALID_VIEW_SIZE = 0xC000001F => ERROR_ACCESS_DENIED, EINVAL;
+    STATUS_ALREADY_COMMITTED = 0xC0000021 => ERROR_ACCESS_DENIED, EACCES;
+    STATUS_ACCESS_DENIED = 0xC0000022 => ERROR_ACCESS_DENIED, EACCES;
+    STATUS_BUFFER_TOO_SMALL = 0xC0000023 => ERROR_INSUFFICIENT_BUFFER, ENOBUFS;
+    STATUS_OBJECT_TYPE_MISMATCH = 0xC0000024 => ERROR_INVALID_HANDLE, EBADF;
+    STATUS_NONCONTINUABLE_EXCEPTION = 0xC0000025 => EXCEPTION_NONCONTINUABLE_EXCEPTION, EFAULT;
+    STATUS_NOT_LOCKED = 0xC000002A => ERROR_NOT_LOCKED, ENOLCK;
+    STATUS_NOT_COMMITTED = 0xC000002D => ERROR_INVALID_ADDRESS, EFAULT;
+    STATUS_OBJECT_NAME_INVALID = 0xC0000033 => ERROR_INVALID_NAME, EINVAL;
+    STATUS_OBJECT_NAME_NOT_FOUND = 0xC0000034 => ERROR_FILE_NOT_FOUND, ENOENT;
+    STATUS_OBJECT_NAME_COLLISION = 0xC0000035 => ERROR_ALREADY_EXISTS, EEXIST;
+    STATUS_PORT_DISCONNECTED = 0xC0000037 => ERROR_INVALID_HANDLE, EPIPE;
+    STATUS_OBJECT_PATH_INVALID = 0xC0000039 => ERROR_BAD_PATHNAME, ENOTDIR;
+    STATUS_OBJECT_PATH_NOT_FOUND = 0xC000003A => ERROR_PATH_NOT_FOUND, ENOENT;
+    STATUS_OBJECT_PATH_SYNTAX_BAD = 0xC000003B => ERROR_BAD_PATHNAME, EINVAL;
+    STATUS_DATA_ERROR = 0xC000003E => ERROR_CRC, EIO;
+    STATUS_CRC_ERROR = 0xC000003F => ERROR_CRC, EIO;
+    STATUS_SECTION_TOO_BIG = 0xC0000040 => ERROR_NOT_ENOUGH_MEMORY, ENOMEM;
+    STATUS_SHARING_VIOLATION = 0xC0000043 => ERROR_SHARING_VIOLATION, EBUSY;
+    STATUS_QUOTA_EXCEEDED = 0xC0000044 => ERROR_NOT_ENOUGH_QUOTA, EDQUOT;
+    STATUS_INVALID_PAGE_PROTECTION = 0xC0000045 => ERROR_INVALID_PARAMETER, EINVAL;
+    STATUS_MUTANT_NOT_OWNED = 0xC0000046 => ERROR_NOT_OWNER, EPERM;
+    STATUS_SEMAPHORE_LIMIT_EXCEEDED = 0xC0000047 => ERROR_TOO_MANY_POSTS, EOVERFLOW;
+    STATUS_SECTION_NOT_IMAGE = 0xC0000049 => ERROR_INVALID_PARAMETER, ENOEXEC;
+    STATUS_SUSPEND_COUNT_EXCEEDED = 0xC000004A => ERROR_SIGNAL_REFUSED, EAGAIN;
+    STATUS_THREAD_IS_TERMINATING = 0xC000004B => ERROR_ACCESS_DENIED, ESRCH;
+    STATUS_FILE_LOCK_CONFLICT = 0xC0000054 => ERROR_LOCK_VIOLATION, EAGAIN;
+    STATUS_LOCK_NOT_GRANTED = 0xC0000055 => ERROR_LOCK_VIOLATION, EAGAIN;
+    STATUS_DELETE_PENDING = 0xC0000056 => ERROR_ACCESS_DENIED, ENOENT;
+    STATUS_NO_SUCH_PRIVILEGE = 0xC0000060 => ERROR_NO_SUCH_PRIVILEGE, EPERM;
+    STATUS_PRIVILEGE_NOT_HELD = 0xC0000061 => ERROR_PRIVILEGE_NOT_HELD, EPERM;
+    STATUS_WRONG_PASSWORD = 0xC000006A => ERROR_INVALID_PASSWORD, EACCES;
+    STATUS_LOGON_FAILURE = 0xC000006D => ERROR_LOGON_FAILURE, EACCES;
+    STATUS_PROCEDURE_NOT_FOUND = 0xC000007A => ERROR_PROC_NOT_FOUND, ENOENT;
+    STATUS_INVALID_IMAGE_FORMAT = 0xC000007B => ERROR_BAD_EXE_FORMAT, ENOEXEC;
+    STATUS_NO_TOKEN = 0xC000007C => ERROR_NO_TOKEN, EPERM;
+    STATUS_RANGE_NOT_LOCKED = 0xC000007E => ERROR_NOT_LOCKED, ENOLCK;
+    STATUS_DISK_FULL = 0xC000007F => ERROR_DISK_FULL, ENOSPC;
+    STATUS_ARRAY_BOUNDS_EXCEEDED = 0xC000008C => EXCEPTION_ARRAY_BOUNDS_EXCEEDED, EFAULT;
+    STATUS_INTEGER_DIVIDE_BY_ZERO = 0xC0000094 => EXCEPTION_INT_DIVIDE_BY_ZERO, EFAULT;
+    STATUS_INTEGER_OVERFLOW = 0xC0000095 => ERROR_ARITHMETIC_OVERFLOW, EOVERFLOW;
+    STATUS_PRIVILEGED_INSTRUCTION = 0xC0000096 => EXCEPTION_PRIV_INSTRUCTION, EFAULT;
+    STATUS_FILE_INVALID = 0xC0000098 => ERROR_FILE_INVALID, EIO;
+    STATUS_INSUFFICIENT_RESOURCES = 0xC000009A => ERROR_NO_SYSTEM_RESOURCES, ENOMEM;
+    STATUS_MEMORY_NOT_ALLOCATED = 0xC00000A0 => ERROR_INVALID_ADDRESS, EFAULT;
+    STATUS_WORKING_SET_QUOTA = 0xC00000A1 => ERROR_WORKING_SET_QUOTA, ENOMEM;
+    STATUS_MEDIA_WRITE_PROTECTED = 0xC00000A2 => ERROR_WRITE_PROTECT, EROFS;
+    STATUS_DEVICE_NOT_READY = 0xC00000A3 => ERROR_NOT_READY, EAGAIN;
+    STATUS_INSTANCE_NOT_AVAILABLE = 0xC00000AB => ERROR_PIPE_BUSY, EBUSY;
+    STATUS_PIPE_BUSY = 0xC00000AE => ERROR_PIPE_BUSY, EBUSY;
+    STATUS_ILLEGAL_FUNCTION = 0xC00000AF => ERROR_INVALID_FUNCTION, EINVAL;
+    STATUS_IO_TIMEOUT = 0xC00000B5 => ERROR_SEM_TIMEOUT, ETIMEDOUT;
+    STATUS_FILE_IS_A_DIRECTORY = 0xC00000BA => ERROR_ACCESS_DENIED, EISDIR;
+    STATUS_NOT_SUPPORTED = 0xC00000BB => ERROR_NOT_SUPPORTED, EOPNOTSUPP;
+    STATUS_BAD_NETWORK_PATH = 0xC00000BE => ERROR_BAD_NETPATH, ENOENT;
+    STATUS_DEVICE_DOES_NOT_EXIST = 0xC00000C0 => ERROR_DEV_NOT_EXIST, ENODEV;
+    STATUS_NETWORK_ACCESS_DENIED = 0xC00000CA => ERROR_NETWORK_ACCESS_DENIED, EACCES;
+    STATUS_BAD_DEVICE_TYPE = 0xC00000CB => ERROR_BAD_DEV_TYPE, ENODEV;
+    STATUS_BAD_NETWORK_NAME = 0xC00000CC => ERROR_BAD_NET_NAME, ENOENT;
+    STATUS_REQUEST_NOT_ACCEPTED = 0xC00000D0 => ERROR_REQ_NOT_ACCEP, EAGAIN;
+    STATUS_NOT_SAME_DEVICE = 0xC00000D4 => ERROR_NOT_SAME_DEVICE, EXDEV;
+    STATUS_INTERNAL_ERROR = 0xC00000E5 => ERROR_INTERNAL_ERROR, EIO;
+    STATUS_INVALID_USER_BUFFER = 0xC00000E8 => ERROR_INVALID_USER_BUFFER, EFAULT;
+    STATUS_UNEXPECTED_IO_ERROR = 0xC00000E9 => ERROR_UNEXP_NET_ERR, EIO;
+    STATUS_INVALID_PARAMETER_1 = 0xC00000EF => ERROR_INVALID_PARAMETER, EINVAL;
+    STATUS_INVALID_PARAMETER_2 = 0xC00000F0 => ERROR_INVALID_PARAMETER, EINVAL;
+    STATUS_INVALID_PARAMETER_3 = 0xC00000F1 => ERROR_INVALID_PARAMETER, EINVAL;
+    STATUS_STACK_OVERFLOW = 0xC00000FD => ERROR_STACK_OVERFLOW, EFAULT;
+    STATUS_DIRECTORY_NOT_EMPTY = 0xC0000101 => ERROR_DIR_NOT_EMPTY, ENOTEMPTY;
+    STATUS_NOT_A_DIRECTORY = 0xC0000103 => ERROR_DIRECTORY, ENOTDIR;
+    STATUS_NAME_TOO_LONG = 0xC0000106 => ERROR_FILENAME_EXCED_RANGE, ENAMETOOLONG;
+    STATUS_PROCESS_IS_TERMINATING = 0xC000010A => ERROR_ACCESS_DENIED, ESRCH;
+    STATUS_IMAGE_ALREADY_LOADED = 0xC000010E => ERROR_SERVICE_ALREADY_RUNNING, EEXIST;
+    STATUS_TOO_MANY_OPENED_FILES = 0xC000011F => ERROR_TOO_MANY_OPEN_FILES, EMFILE;
+    STATUS_CANCELLED = 0xC0000120 => ERROR_OPERATION_ABORTED, ECANCELED;
+    STATUS_CANNOT_DELETE = 0xC0000121 => ERROR_ACCESS_DENIED, EACCES;
+    STATUS_FILE_DELETED = 0xC0000123 => ERROR_ACCESS_DENIED, ENOENT;
+    STATUS_FILE_CLOSED = 0xC0000128 => ERROR_INVALID_HANDLE, EBADF;
+    STATUS_SYNCHRONIZATION_REQUIRED = 0xC0000134 => ERROR_MR_MID_NOT_FOUND, EINVAL;
+    STATUS_DLL_NOT_FOUND = 0xC0000135 => ERROR_MOD_NOT_FOUND, ENOENT;
+    STATUS_ENTRYPOINT_NOT_FOUND = 0xC0000139 => ERROR_PROC_NOT_FOUND, ENOENT;
+    STATUS_INVALID_ADDRESS = 0xC0000141 => ERROR_UNEXP_NET_ERR, EFAULT;
+    STATUS_INVALID_LEVEL = 0xC0000148 => ERROR_INVALID_LEVEL, EINVAL;
+    STATUS_PIPE_BROKEN = 0xC000014B => ERROR_BROKEN_PIPE, EPIPE;
+    STATUS_INVALID_DEVICE_STATE = 0xC0000184 => ERROR_BAD_COMMAND, EIO;
+    STATUS_IO_DEVICE_ERROR = 0xC0000185 => ERROR_IO_DEVICE, EIO;
+    STATUS_MUTANT_LIMIT_EXCEEDED = 0xC0000191 => ERROR_MR_MID_NOT_FOUND, EOVERFLOW;
+    STATUS_POSSIBLE_DEADLOCK = 0xC0000194 => ERROR_POSSIBLE_DEADLOCK, EDEADLK;
+    STATUS_INVALID_BUFFER_SIZE = 0xC0000206 => ERROR_INVALID_USER_BUFFER, EINVAL;
+    STATUS_ADDRESS_ALREADY_EXISTS = 0xC000020A => ERROR_DUP_NAME, EADDRINUSE;
+    STATUS_CONNECTION_RESET = 0xC000020D => ERROR_NETNAME_DELETED, ECONNRESET;
+    STATUS_NOT_FOUND = 0xC0000225 => ERROR_NOT_FOUND, ENOENT;
+    STATUS_RETRY = 0xC000022D => ERROR_RETRY, EAGAIN;
+    STATUS_HANDLE_NOT_CLOSABLE = 0xC0000235 => ERROR_INVALID_HANDLE, EBADF;
+    STATUS_CONNECTION_REFUSED = 0xC0000236 => ERROR_CONNECTION_REFUSED, ECONNREFUSED;
+    STATUS_NETWORK_UNREACHABLE = 0xC000023C => ERROR_NETWORK_UNREACHABLE, ENETUNREACH;
+    STATUS_HOST_UNREACHABLE = 0xC000023D => ERROR_HOST_UNREACHABLE, EHOSTUNREACH;
+    STATUS_CONNECTION_ABORTED = 0xC0000241 => ERROR_CONNECTION_ABORTED, ECONNABORTED;
+    STATUS_TOO_MANY_LINKS = 0xC0000265 => ERROR_TOO_MANY_LINKS, EMLINK;
+    STATUS_DRIVER_UNABLE_TO_LOAD = 0xC000026C => ERROR_MR_MID_NOT_FOUND, ENOEXEC;
+    STATUS_NOT_A_REPARSE_POINT = 0xC0000275 => ERROR_NOT_A_REPARSE_POINT, EINVAL;
+    STATUS_IO_REPARSE_TAG_NOT_HANDLED = 0xC0000279 => ERROR_CANT_ACCESS_FILE, EIO;
+    STATUS_DEVICE_REMOVED = 0xC00002B6 => ERROR_DEVICE_REMOVED, ENODEV;
+    STATUS_NOINTERFACE = 0xC00002B9 => ERROR_NOINTERFACE, EOPNOTSUPP;
+    STATUS_INVALID_IMAGE_HASH = 0xC0000428 => ERROR_INVALID_IMAGE_HASH, EKEYREJECTED;
+    STATUS_PROCESS_IS_PROTECTED = 0xC0000712 => ERROR_MR_MID_NOT_FOUND, EACCES;
+}
+
 /// Evaluates to TRUE if the return value specified by Status is a success type (0 − 0x3FFFFFFF) or an informational type (0x40000000 − 0x7FFFFFFF).
 pub fn nt_success(status: NTSTATUS) -> bool {
     (0..=0x7FFFFFFF).contains(&(status as u32))
@@ -73,4 +478,4 @@ pub fn nt_warning(status: NTSTATUS) -> bool {
 /// Evaluates to TRUE if the return value specified by Status is an error type (0xC0000000 - 0xFFFFFFFF).
 pub fn nt_error(status: NTSTATUS) -> bool {
     (0xC0000000..=0xFFFFFFFF).contains(&(status as u32))
-}
\ No newline at end of file
+}
@@ -0,0 +1,376 @@
+//! NT path parsing and normalization.
+//!
+//! Paths come back from the kernel in several namespaces: `\Device\HarddiskVolume3\...` from
+//! `ObQueryNameString`, `\SystemRoot\...` in the loaded module list, `\??\C:\...` from anything
+//! that went through the Win32 layer. [`NtPath`] splits such a path into its root and components,
+//! and [`DriveMap`] converts between the DOS and device forms. Nothing here touches the object
+//! manager, so the drive letters have to be supplied by the caller.
+
+use alloc::{string::String, vec::Vec};
+use crate::basedef::ntstatus;
+use crate::ntstatus::NtStatus;
+
+/// The separator between path components.
+pub const SEPARATOR: char = '\\';
+
+/// The namespace a path lives in, decided by its prefix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Namespace {
+    /// `\Device\HarddiskVolume3\...`.
+    Device,
+    /// `\??\C:\...` or `\DosDevices\C:\...`. The Win32 `\\?\` and `\\.\` prefixes are classified
+    /// here too, since they map straight onto `\??\`.
+    DosDevices,
+    /// `\GLOBAL??\C:\...`.
+    GlobalDosDevices,
+    /// `\SystemRoot\...`.
+    SystemRoot,
+    /// `\Registry\Machine\...`.
+    Registry,
+    /// `\\server\share\...`.
+    Unc,
+    /// `C:\...`.
+    Drive,
+    /// Any other rooted object manager path, e.g. `\BaseNamedObjects\...`.
+    Object,
+    /// A path without a root, including drive-relative paths like `C:foo`.
+    Relative,
+}
+
+/// A borrowed path split into its root and the components below it.
+///
+/// The root is the part that `..` can't climb out of: `\Device\HarddiskVolume3`, `\??\C:`,
+/// `\\server\share`, `C:` and so on. Prefixes are matched case-insensitively, like the object
+/// manager does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NtPath<'a> {
+    namespace: Namespace,
+    root: &'a str,
+    rest: &'a str,
+    /// The symbolic link below `\??\`, e.g. `C:` or `UNC\server\share`.
+    link: &'a str,
+}
+
+impl<'a> NtPath<'a> {
+    pub fn parse(path: &'a str) -> Self {
+        let mut link = "";
+        let (namespace, root_len) = if let Some(rest) = strip_namespace(path, "\\Device") {
+            (Namespace::Device, path.len() - rest.len() + components_len(rest, 1))
+        } else if let Some(rest) = strip_namespace(path, "\\GLOBAL??") {
+            link = dos_link(rest);
+            (Namespace::GlobalDosDevices, path.len() - rest.len() + link_len(link))
+        } else if let Some(rest) = ["\\??", "\\DosDevices", "\\\\?", "\\\\."].iter().find_map(|name| strip_namespace(path, name)) {
+            link = dos_link(rest);
+            (Namespace::DosDevices, path.len() - rest.len() + link_len(link))
+        } else if let Some(rest) = strip_namespace(path, "\\SystemRoot") {
+            (Namespace::SystemRoot, path.len() - rest.len())
+        } else if let Some(rest) = strip_namespace(path, "\\Registry") {
+            (Namespace::Registry, path.len() - rest.len())
+        } else if path.starts_with("\\\\") {
+            (Namespace::Unc, 1 + components_len(&path[1..], 2))
+        } else if path.starts_with(SEPARATOR) {
+            (Namespace::Object, 0)
+        } else if path.get(..2).and_then(drive_letter).is_some() && path.as_bytes().get(2) == Some(&(SEPARATOR as u8)) {
+            (Namespace::Drive, 2)
+        } else {
+            (Namespace::Relative, 0)
+        };
+
+        Self { namespace, root: &path[..root_len], rest: &path[root_len..], link }
+    }
+
+    pub fn namespace(&self) -> Namespace {
+        self.namespace
+    }
+
+    pub fn is_absolute(&self) -> bool {
+        self.namespace != Namespace::Relative
+    }
+
+    /// The root of the path as written, empty for [`Namespace::Object`] and
+    /// [`Namespace::Relative`] paths.
+    pub fn root(&self) -> &'a str {
+        self.root
+    }
+
+    /// The non-empty components below the root, as written.
+    pub fn components(&self) -> impl DoubleEndedIterator<Item = &'a str> + 'a {
+        self.rest.split(SEPARATOR).filter(|component| !component.is_empty())
+    }
+
+    /// The last component, e.g. `ntoskrnl.exe` for `\SystemRoot\system32\ntoskrnl.exe`.
+    pub fn file_name(&self) -> Option<&'a str> {
+        self.components().next_back()
+    }
+
+    /// The path with `.` components, `..` components and repeated or trailing separators
+    /// resolved. `..` never climbs above the root; relative paths keep their leading `..`.
+    pub fn normalize(&self) -> String {
+        join(self.root, &self.normalized_components(), self.is_rooted())
+    }
+
+    fn normalized_components(&self) -> Vec<&'a str> {
+        let mut components = Vec::new();
+        for component in self.components() {
+            match component {
+                "." => {}
+                ".." => match components.last() {
+                    Some(&last) if last != ".." => {
+                        components.pop();
+                    }
+                    _ if self.namespace == Namespace::Relative => components.push(component),
+                    _ => {}
+                },
+                _ => components.push(component),
+            }
+        }
+        components
+    }
+
+    /// Whether a separator follows the root, which matters for the device namespace:
+    /// `\Device\HarddiskVolume3` is the volume, `\Device\HarddiskVolume3\` its root directory.
+    fn is_rooted(&self) -> bool {
+        self.namespace != Namespace::Relative && !self.rest.is_empty()
+    }
+}
+
+/// Maps drive letters to the devices behind them, e.g. `C:` to `\Device\HarddiskVolume3`.
+#[derive(Clone, Debug, Default)]
+pub struct DriveMap {
+    drives: [Option<String>; 26],
+    system_root: Option<String>,
+}
+
+impl DriveMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps `letter` to `device`, which must be a `\Device\...` path.
+    pub fn insert(&mut self, letter: char, device: &str) -> Result<(), NtStatus> {
+        if !letter.is_ascii_alphabetic() {
+            return Err(NtStatus(ntstatus::STATUS_OBJECT_NAME_INVALID));
+        }
+        if NtPath::parse(device).namespace() != Namespace::Device {
+            return Err(NtStatus(ntstatus::STATUS_OBJECT_PATH_SYNTAX_BAD));
+        }
+
+        self.drives[drive_index(letter as u8)] = Some(String::from(device.trim_end_matches(SEPARATOR)));
+        Ok(())
+    }
+
+    /// The device `letter` is mapped to.
+    pub fn device(&self, letter: char) -> Option<&str> {
+        if !letter.is_ascii_alphabetic() {
+            return None;
+        }
+        self.drives[drive_index(letter as u8)].as_deref()
+    }
+
+    /// Sets the DOS path `\SystemRoot` resolves to, e.g. `C:\Windows`.
+    pub fn set_system_root(&mut self, path: &str) -> Result<(), NtStatus> {
+        let parsed = NtPath::parse(path);
+        if parsed.namespace() != Namespace::Drive {
+            return Err(NtStatus(ntstatus::STATUS_OBJECT_PATH_SYNTAX_BAD));
+        }
+
+        let mut system_root = parsed.normalize();
+        system_root.truncate(system_root.trim_end_matches(SEPARATOR).len());
+        self.system_root = Some(system_root);
+        Ok(())
+    }
+
+    /// Converts a DOS path to its normalized device form, e.g. `C:\Windows` to
+    /// `\Device\HarddiskVolume3\Windows` and `\\server\share` to `\Device\Mup\server\share`.
+    ///
+    /// Forward slashes are accepted as separators, as the Win32 layer does. Paths that are
+    /// already in the NT namespace are only normalized. Drive letters without a mapping fail with
+    /// `STATUS_OBJECT_PATH_NOT_FOUND` and relative paths with `STATUS_OBJECT_PATH_SYNTAX_BAD`.
+    pub fn to_nt(&self, path: &str) -> Result<String, NtStatus> {
+        let converted;
+        let path = if is_win32_path(path) && path.contains('/') {
+            converted = path.replace('/', "\\");
+            converted.as_str()
+        } else {
+            path
+        };
+
+        let parsed = NtPath::parse(path);
+        let components = parsed.normalized_components();
+        let rooted = parsed.is_rooted();
+
+        match parsed.namespace() {
+            Namespace::Drive => Ok(join(self.drive_device(parsed.root)?, &components, true)),
+            Namespace::Unc => Ok(join(&mup_path(&parsed.root[1..]), &components, rooted)),
+            Namespace::DosDevices | Namespace::GlobalDosDevices => {
+                if drive_letter(parsed.link).is_some() {
+                    Ok(join(self.drive_device(parsed.link)?, &components, rooted))
+                } else if let Some(unc) = strip_namespace(parsed.link, "UNC") {
+                    Ok(join(&mup_path(unc), &components, rooted))
+                } else {
+                    let mut root = String::from(if parsed.namespace() == Namespace::DosDevices { "\\??\\" } else { "\\GLOBAL??\\" });
+                    root.push_str(parsed.link);
+                    Ok(join(&root, &components, rooted))
+                }
+            }
+            Namespace::Device | Namespace::SystemRoot | Namespace::Registry | Namespace::Object => Ok(parsed.normalize()),
+            Namespace::Relative => Err(NtStatus(ntstatus::STATUS_OBJECT_PATH_SYNTAX_BAD)),
+        }
+    }
+
+    /// Converts an NT path to its normalized DOS form, the reverse of [`DriveMap::to_nt`].
+    ///
+    /// Devices without a drive letter and `\SystemRoot` without [`DriveMap::set_system_root`]
+    /// fail with `STATUS_OBJECT_PATH_NOT_FOUND`. Registry, other object manager and relative paths
+    /// have no DOS form and fail with `STATUS_OBJECT_PATH_SYNTAX_BAD`.
+    pub fn to_dos(&self, path: &str) -> Result<String, NtStatus> {
+        let parsed = NtPath::parse(path);
+        let components = parsed.normalized_components();
+        let rooted = parsed.is_rooted();
+
+        match parsed.namespace() {
+            Namespace::Drive | Namespace::Unc => Ok(parsed.normalize()),
+            Namespace::Device => {
+                let device = &parsed.root["\\Device\\".len().min(parsed.root.len())..];
+                if device.eq_ignore_ascii_case("Mup") {
+                    return Ok(join("\\", &components, rooted));
+                }
+
+                let index = self.drives.iter().position(|mapped| match mapped {
+                    Some(mapped) => mapped.eq_ignore_ascii_case(parsed.root),
+                    None => false,
+                });
+                match index {
+                    Some(index) => Ok(join(&drive_root(index), &components, true)),
+                    None => Err(NtStatus(ntstatus::STATUS_OBJECT_PATH_NOT_FOUND)),
+                }
+            }
+            Namespace::DosDevices | Namespace::GlobalDosDevices => {
+                if let Some(letter) = drive_letter(parsed.link) {
+                    Ok(join(&drive_root(drive_index(letter)), &components, true))
+                } else if let Some(unc) = strip_namespace(parsed.link, "UNC") {
+                    let mut root = String::from("\\");
+                    root.push_str(unc);
+                    Ok(join(&root, &components, rooted))
+                } else {
+                    let mut root = String::from("\\\\.\\");
+                    root.push_str(parsed.link);
+                    Ok(join(&root, &components, rooted))
+                }
+            }
+            Namespace::SystemRoot => match &self.system_root {
+                Some(system_root) => Ok(join(system_root, &components, true)),
+                None => Err(NtStatus(ntstatus::STATUS_OBJECT_PATH_NOT_FOUND)),
+            },
+            Namespace::Registry | Namespace::Object | Namespace::Relative => Err(NtStatus(ntstatus::STATUS_OBJECT_PATH_SYNTAX_BAD)),
+        }
+    }
+
+    fn drive_device(&self, drive: &str) -> Result<&str, NtStatus> {
+        drive_letter(drive)
+            .and_then(|letter| self.drives[drive_index(letter)].as_deref())
+            .ok_or(NtStatus(ntstatus::STATUS_OBJECT_PATH_NOT_FOUND))
+    }
+}
+
+/// Strips `name` from the front of `path` if it is followed by a separator or the end.
+fn strip_namespace<'a>(path: &'a str, name: &str) -> Option<&'a str> {
+    let head = path.get(..name.len())?;
+    let rest = &path[name.len()..];
+    if head.eq_ignore_ascii_case(name) && (rest.is_empty() || rest.starts_with(SEPARATOR)) {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+/// The length of the first `count` components of `s`, which starts with a separator.
+fn components_len(s: &str, count: usize) -> usize {
+    let mut end = 0;
+    for _ in 0..count {
+        if end >= s.len() {
+            break;
+        }
+        end = s[end + 1..].find(SEPARATOR).map_or(s.len(), |i| end + 1 + i);
+    }
+    end
+}
+
+/// The symbolic link at the start of `rest`, which follows a `\??` prefix.
+fn dos_link(rest: &str) -> &str {
+    let first = components_len(rest, 1);
+    if first == 0 {
+        return "";
+    }
+
+    let len = if rest[1..first].eq_ignore_ascii_case("UNC") { components_len(rest, 3) } else { first };
+    &rest[1..len]
+}
+
+/// The length of a link returned by [`dos_link`] including the separator before it.
+fn link_len(link: &str) -> usize {
+    if link.is_empty() {
+        0
+    } else {
+        link.len() + 1
+    }
+}
+
+/// The uppercase letter of a `C:` drive.
+fn drive_letter(s: &str) -> Option<u8> {
+    match s.as_bytes() {
+        [letter, b':'] if letter.is_ascii_alphabetic() => Some(letter.to_ascii_uppercase()),
+        _ => None,
+    }
+}
+
+fn drive_index(letter: u8) -> usize {
+    (letter.to_ascii_uppercase() - b'A') as usize
+}
+
+fn drive_root(index: usize) -> String {
+    let mut root = String::with_capacity(2);
+    root.push((b'A' + index as u8) as char);
+    root.push(':');
+    root
+}
+
+/// `\Device\Mup` followed by `unc`, which starts with `\server`.
+fn mup_path(unc: &str) -> String {
+    let mut path = String::from("\\Device\\Mup");
+    path.push_str(unc);
+    path
+}
+
+/// Whether `path` is a Win32 path, in which forward slashes are separators. NT paths start with
+/// a single backslash, and `\\?\` turns the Win32 rewriting off.
+fn is_win32_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    match bytes {
+        [b'\\', b'\\', b'?', b'\\', ..] => false,
+        [b'\\', b'\\', ..] => true,
+        [b'\\', ..] => false,
+        _ => true,
+    }
+}
+
+fn join(root: &str, components: &[&str], rooted: bool) -> String {
+    let mut path = String::with_capacity(root.len() + components.iter().map(|component| component.len() + 1).sum::<usize>() + 1);
+    path.push_str(root);
+
+    for (i, component) in components.iter().enumerate() {
+        if i > 0 || rooted || !root.is_empty() {
+            path.push(SEPARATOR);
+        }
+        path.push_str(component);
+    }
+
+    if components.is_empty() {
+        if rooted {
+            path.push(SEPARATOR);
+        } else if root.is_empty() {
+            path.push('.');
+        }
+    }
+    path
+}
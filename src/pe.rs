@@ -0,0 +1,595 @@
+//! A PE image parser that works on any [`ImageReader`], e.g. a file read into memory, a module
+//! mapped in kernel space or a module in another process.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use crate::basedef::ntstatus;
+use crate::ntstatus::NtStatus;
+
+const IMAGE_DOS_SIGNATURE: u16 = 0x5A4D;
+const IMAGE_NT_SIGNATURE: u32 = 0x0000_4550;
+const IMAGE_NT_OPTIONAL_HDR32_MAGIC: u16 = 0x10B;
+const IMAGE_NT_OPTIONAL_HDR64_MAGIC: u16 = 0x20B;
+const IMAGE_ORDINAL_FLAG32: u64 = 0x8000_0000;
+const IMAGE_ORDINAL_FLAG64: u64 = 0x8000_0000_0000_0000;
+const SECTION_HEADER_SIZE: usize = 40;
+const IMPORT_DESCRIPTOR_SIZE: u32 = 20;
+/// Longest name read from an image, including export, import and forwarder names.
+const MAX_NAME_LEN: usize = 512;
+
+pub const IMAGE_DIRECTORY_ENTRY_EXPORT: usize = 0;
+pub const IMAGE_DIRECTORY_ENTRY_IMPORT: usize = 1;
+pub const IMAGE_DIRECTORY_ENTRY_BASERELOC: usize = 5;
+
+/// The relocation types found in base relocation blocks.
+pub const IMAGE_REL_BASED_ABSOLUTE: u8 = 0;
+pub const IMAGE_REL_BASED_HIGHLOW: u8 = 3;
+pub const IMAGE_REL_BASED_DIR64: u8 = 10;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum PeError {
+    /// A read went past the end of the image.
+    OutOfBounds { offset: usize, len: usize },
+    /// The reader failed to copy the bytes, e.g. because the page is not present in the
+    /// target process.
+    ReadFailed { offset: usize, status: NtStatus },
+    BadDosSignature,
+    BadNtSignature,
+    BadOptionalHeaderMagic(u16),
+    /// The RVA is not backed by the headers or any section.
+    BadRva(u32),
+    /// A name was not null terminated within `MAX_NAME_LEN` bytes or was not valid UTF-8.
+    BadName(u32),
+    MissingDirectory(usize),
+}
+
+impl fmt::Display for PeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeError::OutOfBounds { offset, len } => write!(f, "read of {:#x} bytes at {:#x} is out of bounds", len, offset),
+            PeError::ReadFailed { offset, status } => write!(f, "read at {:#x} failed with {}", offset, status),
+            PeError::BadDosSignature => write!(f, "bad DOS signature"),
+            PeError::BadNtSignature => write!(f, "bad NT signature"),
+            PeError::BadOptionalHeaderMagic(magic) => write!(f, "bad optional header magic {:#x}", magic),
+            PeError::BadRva(rva) => write!(f, "RVA {:#x} is not mapped by the image", rva),
+            PeError::BadName(rva) => write!(f, "invalid name at RVA {:#x}", rva),
+            PeError::MissingDirectory(index) => write!(f, "data directory {} is not present", index),
+        }
+    }
+}
+
+impl From<PeError> for NtStatus {
+    fn from(e: PeError) -> Self {
+        match e {
+            PeError::MissingDirectory(_) => NtStatus(ntstatus::STATUS_NOT_FOUND),
+            PeError::ReadFailed { status, .. } => status,
+            _ => NtStatus(ntstatus::STATUS_INVALID_IMAGE_FORMAT),
+        }
+    }
+}
+
+/// A source of image bytes, addressed by offset from the start of the image.
+pub trait ImageReader {
+    fn read(&self, offset: usize, buf: &mut [u8]) -> Result<(), PeError>;
+}
+
+impl ImageReader for [u8] {
+    fn read(&self, offset: usize, buf: &mut [u8]) -> Result<(), PeError> {
+        let src = offset
+            .checked_add(buf.len())
+            .and_then(|end| self.get(offset..end))
+            .ok_or(PeError::OutOfBounds { offset, len: buf.len() })?;
+        buf.copy_from_slice(src);
+        Ok(())
+    }
+}
+
+impl<R: ImageReader + ?Sized> ImageReader for &R {
+    fn read(&self, offset: usize, buf: &mut [u8]) -> Result<(), PeError> {
+        (**self).read(offset, buf)
+    }
+}
+
+/// Whether the bytes are laid out as on disk or as mapped by the loader.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ImageLayout {
+    /// Sections are at their `PointerToRawData` file offsets.
+    File,
+    /// Sections are at their `VirtualAddress`, so offsets are RVAs.
+    Mapped,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct DataDirectory {
+    pub virtual_address: u32,
+    pub size: u32,
+}
+
+impl DataDirectory {
+    pub fn contains(&self, rva: u32) -> bool {
+        rva >= self.virtual_address && rva - self.virtual_address < self.size
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SectionHeader {
+    pub name: [u8; 8],
+    pub virtual_size: u32,
+    pub virtual_address: u32,
+    pub size_of_raw_data: u32,
+    pub pointer_to_raw_data: u32,
+    pub characteristics: u32,
+}
+
+impl SectionHeader {
+    /// The section name with trailing nulls removed.
+    pub fn name(&self) -> &str {
+        let len = self.name.iter().position(|&c| c == 0).unwrap_or(self.name.len());
+        core::str::from_utf8(&self.name[..len]).unwrap_or("")
+    }
+
+    pub fn contains_rva(&self, rva: u32) -> bool {
+        let size = self.virtual_size.max(self.size_of_raw_data);
+        rva >= self.virtual_address && rva - self.virtual_address < size
+    }
+}
+
+/// The fields of the file and optional headers, normalized across PE32 and PE32+.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct NtHeaders {
+    pub machine: u16,
+    pub time_date_stamp: u32,
+    pub characteristics: u16,
+    pub is_64bit: bool,
+    pub address_of_entry_point: u32,
+    pub image_base: u64,
+    pub section_alignment: u32,
+    pub file_alignment: u32,
+    pub size_of_image: u32,
+    pub size_of_headers: u32,
+    pub checksum: u32,
+    pub subsystem: u16,
+    pub dll_characteristics: u16,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum ExportTarget {
+    /// The RVA of the exported code or data.
+    Rva(u32),
+    /// The export is forwarded to another module, e.g. `NTDLL.RtlAllocateHeap`.
+    Forwarder(String),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Export {
+    pub name: Option<String>,
+    /// The biased ordinal, as used with `GetProcAddress`.
+    pub ordinal: u32,
+    pub target: ExportTarget,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum ImportName {
+    Name { hint: u16, name: String },
+    Ordinal(u16),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Import {
+    pub name: ImportName,
+    /// The RVA of the import address table slot the loader fills in for this import.
+    pub iat_rva: u32,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ImportModule {
+    pub name: String,
+    pub imports: Vec<Import>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Relocation {
+    pub rva: u32,
+    /// One of the `IMAGE_REL_BASED_*` types.
+    pub kind: u8,
+}
+
+/// A parsed PE image.
+pub struct PeImage<R> {
+    reader: R,
+    layout: ImageLayout,
+    headers: NtHeaders,
+    data_directories: Vec<DataDirectory>,
+    sections: Vec<SectionHeader>,
+}
+
+impl<'a> PeImage<&'a [u8]> {
+    /// Parses an image as read from disk.
+    pub fn from_file_bytes(bytes: &'a [u8]) -> Result<Self, PeError> {
+        Self::parse(bytes, ImageLayout::File)
+    }
+
+    /// Parses an image as mapped by the loader.
+    pub fn from_mapped_bytes(bytes: &'a [u8]) -> Result<Self, PeError> {
+        Self::parse(bytes, ImageLayout::Mapped)
+    }
+}
+
+impl<R: ImageReader> PeImage<R> {
+    pub fn parse(reader: R, layout: ImageLayout) -> Result<Self, PeError> {
+        if read_u16(&reader, 0)? != IMAGE_DOS_SIGNATURE {
+            return Err(PeError::BadDosSignature);
+        }
+
+        let nt = read_u32(&reader, 0x3C)? as usize;
+        if read_u32(&reader, nt)? != IMAGE_NT_SIGNATURE {
+            return Err(PeError::BadNtSignature);
+        }
+
+        let file_header = nt + 4;
+        let number_of_sections = read_u16(&reader, file_header + 2)?;
+        let size_of_optional_header = read_u16(&reader, file_header + 16)? as usize;
+
+        let optional = file_header + 20;
+        let magic = read_u16(&reader, optional)?;
+        let (is_64bit, image_base, directories) = match magic {
+            IMAGE_NT_OPTIONAL_HDR32_MAGIC => (false, read_u32(&reader, optional + 28)? as u64, optional + 92),
+            IMAGE_NT_OPTIONAL_HDR64_MAGIC => (true, read_u64(&reader, optional + 24)?, optional + 108),
+            magic => return Err(PeError::BadOptionalHeaderMagic(magic)),
+        };
+
+        let headers = NtHeaders {
+            machine: read_u16(&reader, file_header)?,
+            time_date_stamp: read_u32(&reader, file_header + 4)?,
+            characteristics: read_u16(&reader, file_header + 18)?,
+            is_64bit,
+            address_of_entry_point: read_u32(&reader, optional + 16)?,
+            image_base,
+            section_alignment: read_u32(&reader, optional + 32)?,
+            file_alignment: read_u32(&reader, optional + 36)?,
+            size_of_image: read_u32(&reader, optional + 56)?,
+            size_of_headers: read_u32(&reader, optional + 60)?,
+            checksum: read_u32(&reader, optional + 64)?,
+            subsystem: read_u16(&reader, optional + 68)?,
+            dll_characteristics: read_u16(&reader, optional + 70)?,
+        };
+
+        // Never trust NumberOfRvaAndSizes beyond what fits in the optional header.
+        let max_directories = size_of_optional_header.saturating_sub(directories + 4 - optional) / 8;
+        let number_of_directories = (read_u32(&reader, directories)? as usize).min(max_directories).min(16);
+        let data_directories = (0..number_of_directories)
+            .map(|i| {
+                let offset = directories + 4 + i * 8;
+                Ok(DataDirectory {
+                    virtual_address: read_u32(&reader, offset)?,
+                    size: read_u32(&reader, offset + 4)?,
+                })
+            })
+            .collect::<Result<Vec<_>, PeError>>()?;
+
+        let section_table = optional + size_of_optional_header;
+        let sections = (0..number_of_sections as usize)
+            .map(|i| {
+                let offset = section_table + i * SECTION_HEADER_SIZE;
+                let mut name = [0u8; 8];
+                reader.read(offset, &mut name)?;
+                Ok(SectionHeader {
+                    name,
+                    virtual_size: read_u32(&reader, offset + 8)?,
+                    virtual_address: read_u32(&reader, offset + 12)?,
+                    size_of_raw_data: read_u32(&reader, offset + 16)?,
+                    pointer_to_raw_data: read_u32(&reader, offset + 20)?,
+                    characteristics: read_u32(&reader, offset + 36)?,
+                })
+            })
+            .collect::<Result<Vec<_>, PeError>>()?;
+
+        Ok(Self { reader, layout, headers, data_directories, sections })
+    }
+
+    pub fn headers(&self) -> &NtHeaders {
+        &self.headers
+    }
+
+    pub fn sections(&self) -> &[SectionHeader] {
+        &self.sections
+    }
+
+    pub fn section_by_name(&self, name: &str) -> Option<&SectionHeader> {
+        self.sections.iter().find(|s| s.name() == name)
+    }
+
+    pub fn section_containing(&self, rva: u32) -> Option<&SectionHeader> {
+        self.sections.iter().find(|s| s.contains_rva(rva))
+    }
+
+    /// Returns the data directory at `index` if it is present and non-empty.
+    pub fn data_directory(&self, index: usize) -> Option<DataDirectory> {
+        self.data_directories
+            .get(index)
+            .copied()
+            .filter(|d| d.virtual_address != 0 && d.size != 0)
+    }
+
+    pub fn data_directories(&self) -> &[DataDirectory] {
+        &self.data_directories
+    }
+
+    /// Translates an RVA to an offset into the underlying reader.
+    pub fn rva_to_offset(&self, rva: u32) -> Result<usize, PeError> {
+        match self.layout {
+            ImageLayout::Mapped => Ok(rva as usize),
+            ImageLayout::File if rva < self.headers.size_of_headers => Ok(rva as usize),
+            ImageLayout::File => {
+                let section = self.section_containing(rva).ok_or(PeError::BadRva(rva))?;
+                let delta = rva - section.virtual_address;
+                if delta >= section.size_of_raw_data {
+                    // Uninitialized data only exists once the image is mapped.
+                    return Err(PeError::BadRva(rva));
+                }
+                Ok(section.pointer_to_raw_data as usize + delta as usize)
+            }
+        }
+    }
+
+    /// Reads `buf.len()` bytes starting at `rva`.
+    pub fn read_rva(&self, rva: u32, buf: &mut [u8]) -> Result<(), PeError> {
+        self.reader.read(self.rva_to_offset(rva)?, buf)
+    }
+
+    pub fn read_u16_rva(&self, rva: u32) -> Result<u16, PeError> {
+        let mut buf = [0u8; 2];
+        self.read_rva(rva, &mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    pub fn read_u32_rva(&self, rva: u32) -> Result<u32, PeError> {
+        let mut buf = [0u8; 4];
+        self.read_rva(rva, &mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    pub fn read_u64_rva(&self, rva: u32) -> Result<u64, PeError> {
+        let mut buf = [0u8; 8];
+        self.read_rva(rva, &mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    /// Reads a null terminated ASCII string starting at `rva`.
+    pub fn read_cstr_rva(&self, rva: u32) -> Result<String, PeError> {
+        let mut name = Vec::new();
+        let mut chunk = [0u8; 32];
+        while name.len() < MAX_NAME_LEN {
+            let at = rva.checked_add(name.len() as u32).ok_or(PeError::BadRva(rva))?;
+            // Fall back to single bytes near the end of a section or image.
+            let chunk = match self.read_rva(at, &mut chunk) {
+                Ok(()) => &chunk[..],
+                Err(_) => {
+                    self.read_rva(at, &mut chunk[..1])?;
+                    &chunk[..1]
+                }
+            };
+
+            match chunk.iter().position(|&c| c == 0) {
+                Some(end) => {
+                    name.extend_from_slice(&chunk[..end]);
+                    return String::from_utf8(name).map_err(|_| PeError::BadName(rva));
+                }
+                None => name.extend_from_slice(chunk),
+            }
+        }
+
+        Err(PeError::BadName(rva))
+    }
+
+    fn export_directory(&self) -> Result<ExportDirectory, PeError> {
+        let directory = self
+            .data_directory(IMAGE_DIRECTORY_ENTRY_EXPORT)
+            .ok_or(PeError::MissingDirectory(IMAGE_DIRECTORY_ENTRY_EXPORT))?;
+        let rva = directory.virtual_address;
+
+        Ok(ExportDirectory {
+            directory,
+            name: self.read_u32_rva(rva.wrapping_add(12))?,
+            base: self.read_u32_rva(rva.wrapping_add(16))?,
+            number_of_functions: self.read_u32_rva(rva.wrapping_add(20))?,
+            number_of_names: self.read_u32_rva(rva.wrapping_add(24))?,
+            address_of_functions: self.read_u32_rva(rva.wrapping_add(28))?,
+            address_of_names: self.read_u32_rva(rva.wrapping_add(32))?,
+            address_of_name_ordinals: self.read_u32_rva(rva.wrapping_add(36))?,
+        })
+    }
+
+    fn export_target(&self, exports: &ExportDirectory, index: u32) -> Result<Option<ExportTarget>, PeError> {
+        if index >= exports.number_of_functions {
+            return Ok(None);
+        }
+
+        let rva = self.read_u32_rva(exports.address_of_functions.wrapping_add(index.wrapping_mul(4)))?;
+        if rva == 0 {
+            return Ok(None);
+        }
+
+        // Forwarders point back into the export directory at a "MODULE.Function" string.
+        if exports.directory.contains(rva) {
+            return Ok(Some(ExportTarget::Forwarder(self.read_cstr_rva(rva)?)));
+        }
+
+        Ok(Some(ExportTarget::Rva(rva)))
+    }
+
+    /// The name the image was linked with, from the export directory.
+    pub fn export_name(&self) -> Result<String, PeError> {
+        let exports = self.export_directory()?;
+        self.read_cstr_rva(exports.name)
+    }
+
+    /// Returns every export, named or not.
+    pub fn exports(&self) -> Result<Vec<Export>, PeError> {
+        let exports = self.export_directory()?;
+
+        // Function index to name. An index with several names keeps the first one.
+        let mut names = BTreeMap::new();
+        for i in 0..exports.number_of_names {
+            let index = self.read_u16_rva(exports.address_of_name_ordinals.wrapping_add(i.wrapping_mul(2)))? as u32;
+            let name = self.read_cstr_rva(self.read_u32_rva(exports.address_of_names.wrapping_add(i.wrapping_mul(4)))?)?;
+            names.entry(index).or_insert(name);
+        }
+
+        let mut result = Vec::new();
+        for index in 0..exports.number_of_functions {
+            if let Some(target) = self.export_target(&exports, index)? {
+                let name = names.remove(&index);
+                result.push(Export { name, ordinal: exports.base.wrapping_add(index), target });
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Finds an export by name, using a binary search over the sorted name table.
+    pub fn export_by_name(&self, name: &str) -> Result<Option<ExportTarget>, PeError> {
+        let exports = self.export_directory()?;
+
+        let (mut low, mut high) = (0, exports.number_of_names);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let mid_name = self.read_cstr_rva(self.read_u32_rva(exports.address_of_names.wrapping_add(mid.wrapping_mul(4)))?)?;
+            match mid_name.as_str().cmp(name) {
+                core::cmp::Ordering::Less => low = mid + 1,
+                core::cmp::Ordering::Greater => high = mid,
+                core::cmp::Ordering::Equal => {
+                    let index = self.read_u16_rva(exports.address_of_name_ordinals.wrapping_add(mid.wrapping_mul(2)))? as u32;
+                    return self.export_target(&exports, index);
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Finds an export by its biased ordinal.
+    pub fn export_by_ordinal(&self, ordinal: u32) -> Result<Option<ExportTarget>, PeError> {
+        let exports = self.export_directory()?;
+        match ordinal.checked_sub(exports.base) {
+            Some(index) => self.export_target(&exports, index),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the imports of every module in the import directory.
+    pub fn imports(&self) -> Result<Vec<ImportModule>, PeError> {
+        let directory = match self.data_directory(IMAGE_DIRECTORY_ENTRY_IMPORT) {
+            Some(directory) => directory,
+            None => return Ok(Vec::new()),
+        };
+
+        let (thunk_size, ordinal_flag) = match self.headers.is_64bit {
+            true => (8, IMAGE_ORDINAL_FLAG64),
+            false => (4, IMAGE_ORDINAL_FLAG32),
+        };
+
+        // The directory ends with a null descriptor, but never walk past its declared size.
+        let mut modules = Vec::new();
+        for index in 0..directory.size / IMPORT_DESCRIPTOR_SIZE {
+            let descriptor = directory.virtual_address.wrapping_add(index * IMPORT_DESCRIPTOR_SIZE);
+            let original_first_thunk = self.read_u32_rva(descriptor)?;
+            let name = self.read_u32_rva(descriptor.wrapping_add(12))?;
+            let first_thunk = self.read_u32_rva(descriptor.wrapping_add(16))?;
+            if name == 0 && first_thunk == 0 {
+                break;
+            }
+
+            // Bound images may only have the IAT, which then still holds the lookup entries on disk.
+            let lookup = if original_first_thunk != 0 { original_first_thunk } else { first_thunk };
+
+            let mut imports = Vec::new();
+            for i in 0.. {
+                let thunk = match self.headers.is_64bit {
+                    true => self.read_u64_rva(lookup.wrapping_add(i * thunk_size))?,
+                    false => self.read_u32_rva(lookup.wrapping_add(i * thunk_size))? as u64,
+                };
+                if thunk == 0 {
+                    break;
+                }
+
+                let name = if thunk & ordinal_flag != 0 {
+                    ImportName::Ordinal(thunk as u16)
+                } else {
+                    let hint_name = thunk as u32;
+                    ImportName::Name {
+                        hint: self.read_u16_rva(hint_name)?,
+                        name: self.read_cstr_rva(hint_name.wrapping_add(2))?,
+                    }
+                };
+                imports.push(Import { name, iat_rva: first_thunk.wrapping_add(i * thunk_size) });
+            }
+
+            modules.push(ImportModule { name: self.read_cstr_rva(name)?, imports });
+        }
+
+        Ok(modules)
+    }
+
+    /// Returns every base relocation, skipping `IMAGE_REL_BASED_ABSOLUTE` padding entries.
+    pub fn relocations(&self) -> Result<Vec<Relocation>, PeError> {
+        let directory = match self.data_directory(IMAGE_DIRECTORY_ENTRY_BASERELOC) {
+            Some(directory) => directory,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut relocations = Vec::new();
+        let mut offset = 0u32;
+        while offset.checked_add(8).is_some_and(|end| end <= directory.size) {
+            let block = directory.virtual_address.wrapping_add(offset);
+            let page = self.read_u32_rva(block)?;
+            let size = self.read_u32_rva(block.wrapping_add(4))?;
+            if size < 8 || size > directory.size - offset {
+                break;
+            }
+
+            for i in 0..(size - 8) / 2 {
+                let entry = self.read_u16_rva(block.wrapping_add(8 + i * 2))?;
+                let kind = (entry >> 12) as u8;
+                if kind != IMAGE_REL_BASED_ABSOLUTE {
+                    relocations.push(Relocation { rva: page.wrapping_add((entry & 0xFFF) as u32), kind });
+                }
+            }
+
+            offset += size;
+        }
+
+        Ok(relocations)
+    }
+}
+
+struct ExportDirectory {
+    directory: DataDirectory,
+    name: u32,
+    base: u32,
+    number_of_functions: u32,
+    number_of_names: u32,
+    address_of_functions: u32,
+    address_of_names: u32,
+    address_of_name_ordinals: u32,
+}
+
+fn read_u16<R: ImageReader + ?Sized>(reader: &R, offset: usize) -> Result<u16, PeError> {
+    let mut buf = [0u8; 2];
+    reader.read(offset, &mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32<R: ImageReader + ?Sized>(reader: &R, offset: usize) -> Result<u32, PeError> {
+    let mut buf = [0u8; 4];
+    reader.read(offset, &mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: ImageReader + ?Sized>(reader: &R, offset: usize) -> Result<u64, PeError> {
+    let mut buf = [0u8; 8];
+    reader.read(offset, &mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
@@ -1,18 +1,111 @@
+#[cfg(not(feature = "host-sim"))]
 use core::ffi::c_void;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
 use crate::basedef::*;
-use core::{mem, ptr};
+use core::marker::PhantomData;
+use core::mem::{self, ManuallyDrop};
+use core::ops::Deref;
+use crate::memory::{copy_result, MemoryImage, MemoryReader, MemoryWriter, Pod};
 use crate::ntstatus::NtStatus;
-use ntapi::ntpebteb::PPEB;
-use cstr_core::CStr;
+use crate::pe::{ExportTarget, ImageLayout, PeImage};
+use crate::basedef::ntapi::ntpebteb::PPEB;
+use crate::string::AnsiString;
 
+#[cfg(not(feature = "host-sim"))]
 extern "system" {
     pub fn PsLookupProcessByProcessId(process_id: HANDLE, process: *mut PeProcess) -> NtStatus;
     pub fn PsGetProcessPeb(process: PeProcess) -> PPEB;
     pub fn IoGetCurrentProcess() -> PeProcess;
     pub fn PsGetProcessImageFileName(process: PeProcess) -> *const u8;
     pub fn MmCopyVirtualMemory(from_process: PeProcess, from_address: *mut c_void, to_process: PeProcess, to_address: *mut c_void, size: usize, previous_mode: KProcessorMode, bytes_copied: &mut usize) -> NtStatus;
+    pub fn ObfReferenceObject(object: PVOID) -> isize;
+    pub fn ObfDereferenceObject(object: PVOID) -> isize;
+    pub fn KeStackAttachProcess(process: PeProcess, apc_state: *mut KAPC_STATE);
+    pub fn KeUnstackDetachProcess(apc_state: *mut KAPC_STATE);
 }
 
+#[cfg(feature = "host-sim")]
+pub use crate::sim::ntoskrnl::{PsLookupProcessByProcessId, PsGetProcessPeb, IoGetCurrentProcess, PsGetProcessImageFileName, MmCopyVirtualMemory, ObfReferenceObject, ObfDereferenceObject, KeStackAttachProcess, KeUnstackDetachProcess};
+
+/// Size of `EPROCESS.ImageFileName`, including the terminator.
+const IMAGE_FILE_NAME_LEN: usize = 15;
+
+/// Offset of `PEB.Ldr` and `PEB_LDR_DATA.InLoadOrderModuleList` in 64-bit processes.
+const PEB_LDR_OFFSET: u64 = 0x18;
+const LDR_IN_LOAD_ORDER_OFFSET: u64 = 0x10;
+/// Upper bound on loader entries, so a corrupt or cyclic list can't keep the walk going forever.
+const MAX_MODULES: usize = 4096;
+/// How many forwarders are followed when resolving an export.
+const MAX_FORWARDS: usize = 8;
+
+/// `UNICODE_STRING` as laid out in a 64-bit process.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct RemoteUnicodeString {
+    length: u16,
+    maximum_length: u16,
+    _padding: u32,
+    buffer: u64,
+}
+
+unsafe impl Pod for RemoteUnicodeString {}
+
+/// The leading fields of `LDR_DATA_TABLE_ENTRY` in a 64-bit process.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct RemoteLdrEntry {
+    in_load_order_links: [u64; 2],
+    in_memory_order_links: [u64; 2],
+    in_initialization_order_links: [u64; 2],
+    dll_base: u64,
+    entry_point: u64,
+    size_of_image: u32,
+    _padding: u32,
+    full_dll_name: RemoteUnicodeString,
+    base_dll_name: RemoteUnicodeString,
+}
+
+unsafe impl Pod for RemoteLdrEntry {}
+
+/// A module from the loader list of a process.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct UserModule {
+    pub base: u64,
+    pub size: u32,
+    /// The path the module was loaded from, e.g. `C:\Windows\System32\ntdll.dll`.
+    pub full_name: String,
+    /// The file name part of `full_name`, e.g. `ntdll.dll`.
+    pub base_name: String,
+}
+
+impl UserModule {
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.base && address - self.base < self.size as u64
+    }
+}
+
+/// `KAPC_STATE` (x64 layout), where `KeStackAttachProcess` saves the APC state of the thread.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+pub struct KAPC_STATE {
+    pub apc_list_head: [LIST_ENTRY; 2],
+    pub process: PEPROCESS,
+    pub in_progress_flags: u8,
+    pub kernel_apc_pending: u8,
+    pub user_apc_pending_all: u8,
+}
+
+impl Default for KAPC_STATE {
+    fn default() -> Self {
+        unsafe { mem::zeroed() }
+    }
+}
+
+/// A borrowed `EPROCESS`. It holds no reference, so it is only valid while someone else keeps
+/// the process object alive; see [`ProcessRef`] for the owned form.
 #[repr(transparent)]
 #[derive(Copy, Clone, Debug)]
 pub struct PeProcess(PEPROCESS);
@@ -22,43 +115,229 @@ impl PeProcess {
         Self(proc)
     }
 
+    pub fn as_ptr(&self) -> PEPROCESS {
+        self.0
+    }
+
     pub unsafe fn current() -> Self {
         IoGetCurrentProcess()
     }
 
-    pub unsafe fn file_name(&self) -> &str {
+    /// The image file name stored in the `EPROCESS`, which the kernel truncates to 14 bytes. It
+    /// is in the system code page, so check it with [`AnsiString::to_str`] rather than assuming
+    /// UTF-8. [`UnicodeStringBuf::from_latin1`](crate::string::UnicodeStringBuf::from_latin1) is
+    /// exact for ASCII names.
+    pub unsafe fn file_name(&self) -> AnsiString {
         let buf = PsGetProcessImageFileName(*self);
-        CStr::from_ptr(buf as _).to_str().unwrap()
+        if buf.is_null() {
+            return AnsiString::empty();
+        }
+        AnsiString::from_ptr_bounded(buf, IMAGE_FILE_NAME_LEN)
     }
 
-    pub unsafe fn by_pid(pid: u64) -> Option<Self> {
+    /// Looks up a process by pid. `PsLookupProcessByProcessId` references the process, and the
+    /// returned [`ProcessRef`] releases that reference when dropped.
+    pub unsafe fn by_pid(pid: u64) -> Option<ProcessRef> {
         let mut proc: PeProcess = mem::zeroed();
         PsLookupProcessByProcessId(pid as HANDLE, &mut proc)
             .to_result_with_value(proc)
             .ok()
+            .map(|proc| ProcessRef::from_raw(proc))
+    }
+
+    /// Takes a new reference on the process with `ObReferenceObject`.
+    pub unsafe fn reference(&self) -> ProcessRef {
+        ObfReferenceObject(self.0 as _);
+        ProcessRef::from_raw(*self)
     }
 
     pub unsafe fn peb(&self) -> PPEB {
         PsGetProcessPeb(*self)
     }
 
-    pub unsafe fn read_memory(&self, address: u64, buf: &mut [u8]) -> Result<(), NTSTATUS> {
-        let mut bytes_copied = 0;
-        MmCopyVirtualMemory(*self, address as _, Self::current(), buf.as_mut_ptr() as _, buf.len(), KProcessorMode::KernelMode, &mut bytes_copied);
-        if bytes_copied < buf.len() {
-            return Err(ntstatus::STATUS_UNSUCCESSFUL);
+    /// Walks `PEB->Ldr->InLoadOrderModuleList` through the process memory, so the main image
+    /// comes first. Processes without a PEB or whose loader hasn't initialized yet have no
+    /// modules. Only the 64-bit loader structures are read, so the 32-bit modules of a WOW64
+    /// process are not included.
+    pub unsafe fn modules(&self) -> Result<Vec<UserModule>, NtStatus> {
+        let peb = self.peb() as u64;
+        if peb == 0 {
+            return Ok(Vec::new());
+        }
+        let ldr = self.read::<u64>(peb + PEB_LDR_OFFSET)?;
+        if ldr == 0 {
+            return Ok(Vec::new());
+        }
+
+        let head = ldr + LDR_IN_LOAD_ORDER_OFFSET;
+        let mut next = self.read::<u64>(head)?;
+        let mut modules = Vec::new();
+        while next != head {
+            if modules.len() == MAX_MODULES {
+                return Err(NtStatus(ntstatus::STATUS_DATA_ERROR));
+            }
+
+            // InLoadOrderLinks is the first field, so the link is the entry address.
+            let entry = self.read::<RemoteLdrEntry>(next)?;
+            modules.push(UserModule {
+                base: entry.dll_base,
+                size: entry.size_of_image,
+                full_name: self.read_unicode_string(&entry.full_dll_name)?,
+                base_name: self.read_unicode_string(&entry.base_dll_name)?,
+            });
+            next = entry.in_load_order_links[0];
         }
 
-        Ok(())
+        Ok(modules)
     }
 
-    pub unsafe fn write_memory(&self, address: u64, buf: &[u8]) -> Result<(), NTSTATUS> {
-        let mut bytes_copied = 0;
-        MmCopyVirtualMemory(Self::current(), buf.as_ptr() as _, *self, address as _, buf.len(), KProcessorMode::KernelMode, &mut bytes_copied);
-        if bytes_copied < buf.len() {
-            return Err(ntstatus::STATUS_UNSUCCESSFUL);
+    /// Finds a loaded module by file name, ignoring case and any leading path.
+    pub unsafe fn find_module(&self, name: &str) -> Result<Option<UserModule>, NtStatus> {
+        let name = name.rsplit(['\\', '/']).next().unwrap_or(name);
+        Ok(self.modules()?.into_iter().find(|m| m.base_name.eq_ignore_ascii_case(name)))
+    }
+
+    /// Resolves an export of a module of this process to its address, following forwarders to
+    /// other loaded modules. `name` may also be an ordinal written as `#123`.
+    ///
+    /// Forwarders to API sets can't be resolved and fail with `STATUS_DLL_NOT_FOUND`, as do
+    /// forwarders to modules that aren't loaded.
+    pub unsafe fn module_export(&self, module: &UserModule, name: &str) -> Result<Option<u64>, NtStatus> {
+        self.resolve_export(module, name, MAX_FORWARDS)
+    }
+
+    unsafe fn resolve_export(&self, module: &UserModule, name: &str, forwards_left: usize) -> Result<Option<u64>, NtStatus> {
+        let image = PeImage::parse(MemoryImage::new(*self, module.base, module.size as usize), ImageLayout::Mapped)?;
+        let target = match name.strip_prefix('#') {
+            Some(ordinal) => {
+                let ordinal = ordinal.parse().map_err(|_| NtStatus(ntstatus::STATUS_INVALID_PARAMETER))?;
+                image.export_by_ordinal(ordinal)?
+            }
+            None => image.export_by_name(name)?,
+        };
+
+        match target {
+            None => Ok(None),
+            Some(ExportTarget::Rva(rva)) => Ok(Some(module.base + rva as u64)),
+            Some(ExportTarget::Forwarder(forwarder)) => {
+                let (dll, name) = match forwarder.rsplit_once('.') {
+                    Some(split) if forwards_left > 0 => split,
+                    _ => return Err(NtStatus(ntstatus::STATUS_INVALID_IMAGE_FORMAT)),
+                };
+                let module = self
+                    .find_module(&format!("{}.dll", dll))?
+                    .ok_or(NtStatus(ntstatus::STATUS_DLL_NOT_FOUND))?;
+                self.resolve_export(&module, name, forwards_left - 1)
+            }
+        }
+    }
+
+    unsafe fn read_unicode_string(&self, string: &RemoteUnicodeString) -> Result<String, NtStatus> {
+        let mut buf = vec![0u16; string.length as usize / 2];
+        if !buf.is_empty() {
+            self.read_slice(string.buffer, &mut buf).map_err(|(status, _)| status)?;
         }
+        Ok(String::from_utf16_lossy(&buf))
+    }
+}
+
+impl MemoryReader for PeProcess {
+    unsafe fn read_memory(&self, address: u64, buf: &mut [u8]) -> Result<(), (NtStatus, usize)> {
+        let mut bytes_copied = 0;
+        let status = MmCopyVirtualMemory(*self, address as _, Self::current(), buf.as_mut_ptr() as _, buf.len(), KProcessorMode::KernelMode, &mut bytes_copied);
+        copy_result(status, bytes_copied, buf.len())
+    }
+}
 
-        Ok(())
+impl MemoryWriter for PeProcess {
+    unsafe fn write_memory(&self, address: u64, buf: &[u8]) -> Result<(), (NtStatus, usize)> {
+        let mut bytes_copied = 0;
+        let status = MmCopyVirtualMemory(Self::current(), buf.as_ptr() as _, *self, address as _, buf.len(), KProcessorMode::KernelMode, &mut bytes_copied);
+        copy_result(status, bytes_copied, buf.len())
     }
-}
\ No newline at end of file
+}
+
+/// Keeps the current thread attached to a process for the duration of
+/// [`ProcessRef::with_attached`]; detaches with `KeUnstackDetachProcess` on drop. Attachment
+/// belongs to the thread, so the guard is neither `Send` nor `Sync`.
+pub struct AttachGuard<'a> {
+    process: PeProcess,
+    apc_state: &'a mut KAPC_STATE,
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl AttachGuard<'_> {
+    /// The process the thread is attached to.
+    pub fn process(&self) -> PeProcess {
+        self.process
+    }
+}
+
+impl Drop for AttachGuard<'_> {
+    fn drop(&mut self) {
+        unsafe { KeUnstackDetachProcess(self.apc_state) };
+    }
+}
+
+/// An owned reference to an `EPROCESS`, released with `ObDereferenceObject` on drop.
+///
+/// Cloning takes another reference. It derefs to the borrowed [`PeProcess`], which must not be
+/// used after the last `ProcessRef` is gone.
+#[repr(transparent)]
+#[derive(Debug)]
+pub struct ProcessRef(PeProcess);
+
+// Object references are not tied to the thread that took them.
+unsafe impl Send for ProcessRef {}
+unsafe impl Sync for ProcessRef {}
+
+impl ProcessRef {
+    /// Takes ownership of a reference the caller already holds, e.g. one returned by
+    /// `PsLookupProcessByProcessId`.
+    pub unsafe fn from_raw(process: PeProcess) -> Self {
+        Self(process)
+    }
+
+    /// Gives up ownership of the reference without releasing it.
+    pub fn into_raw(this: Self) -> PeProcess {
+        ManuallyDrop::new(this).0
+    }
+
+    /// Runs `f` with the current thread attached to the address space of the process, so its
+    /// user-mode memory can be accessed directly, and detaches afterwards, also on unwind.
+    ///
+    /// The `KAPC_STATE` the kernel links the thread's APC lists into lives in this call's frame,
+    /// and `f` only borrows the guard, so it can't be leaked or outlive the attach. Nested calls
+    /// therefore always detach in the reverse order they attached. The reference held by `self`
+    /// keeps the process alive until the detach.
+    ///
+    /// ```ignore
+    /// let peb = process.with_attached(|_| unsafe { (*process.peb()).BeingDebugged });
+    /// ```
+    pub fn with_attached<R>(&self, f: impl FnOnce(&AttachGuard) -> R) -> R {
+        let mut apc_state = KAPC_STATE::default();
+        unsafe { KeStackAttachProcess(self.0, &mut apc_state) };
+        let guard = AttachGuard { process: self.0, apc_state: &mut apc_state, _not_send: PhantomData };
+        f(&guard)
+    }
+}
+
+impl Deref for ProcessRef {
+    type Target = PeProcess;
+
+    fn deref(&self) -> &PeProcess {
+        &self.0
+    }
+}
+
+impl Clone for ProcessRef {
+    fn clone(&self) -> Self {
+        unsafe { self.0.reference() }
+    }
+}
+
+impl Drop for ProcessRef {
+    fn drop(&mut self) {
+        unsafe { ObfDereferenceObject(self.0.as_ptr() as _) };
+    }
+}
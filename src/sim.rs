@@ -0,0 +1,1373 @@
+//! Host-side simulation of the kernel APIs used by this crate.
+//!
+//! With the `host-sim` feature enabled every `ntoskrnl`/`hal` import is replaced by a function in
+//! [`ntoskrnl`] with the same name and signature. Those functions forward to the installed
+//! [`KernelBackend`], which is a [`SimKernel`] unless one is installed with [`set_backend`].
+
+#[cfg(not(windows))]
+pub mod ffi;
+
+use std::alloc::{alloc, dealloc, Layout};
+use std::boxed::Box;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ffi::{CStr, VaList};
+use std::mem;
+use std::ptr::{self, null_mut};
+use std::string::String;
+use std::hint;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, ThreadId};
+use std::time::Instant;
+use std::vec::Vec;
+
+use core::ffi::c_void;
+
+use crate::basedef::ntapi::ntexapi::{SYSTEM_INFORMATION_CLASS, SYSTEM_THREAD_INFORMATION, SystemModuleInformation, SystemProcessInformation};
+use crate::basedef::ntapi::ntexapi::{SystemExtendedHandleInformation, SYSTEM_HANDLE_INFORMATION_EX, SYSTEM_HANDLE_TABLE_ENTRY_INFO_EX};
+use crate::basedef::ntapi::ntexapi::{SystemBigPoolInformation, SYSTEM_BIGPOOL_INFORMATION, SYSTEM_BIGPOOL_ENTRY};
+use crate::basedef::ntapi::ntexapi::{SystemCodeIntegrityInformation, SYSTEM_CODEINTEGRITY_INFORMATION, SystemKernelDebuggerInformation, SYSTEM_KERNEL_DEBUGGER_INFORMATION};
+use crate::basedef::ntapi::ntexapi::{SystemBootEnvironmentInformation, SYSTEM_BOOT_ENVIRONMENT_INFORMATION};
+use crate::basedef::ntapi::ntkeapi::{KTHREAD_STATE, KWAIT_REASON};
+use crate::basedef::ntapi::ntldr::{RTL_PROCESS_MODULES, RTL_PROCESS_MODULE_INFORMATION};
+use crate::basedef::ntapi::ntpebteb::PPEB;
+use crate::basedef::winapi::shared::guiddef::GUID;
+use crate::basedef::winapi::um::winnt::{FirmwareTypeUefi, FIRMWARE_TYPE};
+
+use crate::allocator::PoolType;
+use crate::lookaside::{LOOKASIDE_LIST_EX, PALLOCATE_FUNCTION_EX, PFREE_FUNCTION_EX, SLIST_ENTRY, SLIST_HEADER};
+use crate::basedef::*;
+use crate::memory::{copy_result, MemoryReader, MemoryWriter};
+use crate::ntstatus::NtStatus;
+use crate::process::KAPC_STATE;
+use crate::kernel::{ProcessModuleInformation, SystemProcessInformation as ProcessInfo};
+use crate::string::UnicodeString;
+
+/// The set of kernel services the crate depends on.
+///
+/// Each method corresponds to one (or a small group of) `ntoskrnl`/`hal` exports and takes the
+/// same arguments, with out-pointers replaced by references where that is unambiguous.
+pub trait KernelBackend: Send + Sync {
+    unsafe fn allocate_pool(&self, pool_type: PoolType, size: usize, tag: u32) -> *mut c_void;
+    unsafe fn free_pool(&self, pool: *mut c_void, tag: u32);
+
+    fn debug_print(&self, text: &str);
+
+    fn lookup_process(&self, pid: u64) -> Result<PEPROCESS, NtStatus>;
+    fn current_process(&self) -> PEPROCESS;
+    fn process_peb(&self, process: PEPROCESS) -> PPEB;
+    fn process_image_file_name(&self, process: PEPROCESS) -> *const u8;
+    fn reference_object(&self, object: PVOID) -> isize;
+    fn dereference_object(&self, object: PVOID) -> isize;
+    unsafe fn stack_attach_process(&self, process: PEPROCESS, apc_state: *mut KAPC_STATE);
+    unsafe fn unstack_detach_process(&self, apc_state: *mut KAPC_STATE);
+    unsafe fn copy_virtual_memory(
+        &self,
+        from_process: PEPROCESS,
+        from_address: *mut c_void,
+        to_process: PEPROCESS,
+        to_address: *mut c_void,
+        size: usize,
+        bytes_copied: &mut usize,
+    ) -> NTSTATUS;
+
+    unsafe fn query_system_information(
+        &self,
+        class: SYSTEM_INFORMATION_CLASS,
+        buf: PVOID,
+        len: ULONG,
+        return_length: &mut ULONG,
+    ) -> NTSTATUS;
+    unsafe fn find_exported_routine(&self, module_base: PVOID, name: &CStr) -> PVOID;
+    unsafe fn query_object_name(&self, object: PVOID, buf: PVOID, len: ULONG, return_length: &mut ULONG) -> NTSTATUS;
+
+    unsafe fn allocate_mdl(&self, virtual_address: PVOID, length: u32) -> PMDL;
+    unsafe fn probe_and_lock_pages(&self, mdl: PMDL);
+    unsafe fn map_locked_pages(&self, mdl: PMDL) -> PVOID;
+    unsafe fn protect_mdl_system_address(&self, mdl: PMDL, new_protect: ULONG) -> NTSTATUS;
+    unsafe fn unmap_locked_pages(&self, base_address: PVOID, mdl: PMDL);
+    unsafe fn unlock_pages(&self, mdl: PMDL);
+    unsafe fn free_mdl(&self, mdl: PMDL);
+
+    fn register_callback(&self, func: *mut c_void, context: *mut c_void, cookie: &mut u64) -> NTSTATUS;
+    fn unregister_callback(&self, cookie: u64) -> NTSTATUS;
+
+    unsafe fn copy_physical_memory(&self, target: *mut u8, physical_address: u64, size: usize, bytes_transferred: &mut usize) -> NTSTATUS;
+    unsafe fn map_io_space(&self, physical_address: u64, len: usize) -> *mut c_void;
+    unsafe fn unmap_io_space(&self, base_address: *mut c_void, len: usize);
+
+    fn query_performance_counter(&self, frequency: Option<&mut i64>) -> u64;
+    fn is_address_valid(&self, address: usize) -> bool;
+
+    unsafe fn acquire_spin_lock(&self, spin_lock: *mut KSPIN_LOCK) -> KIRQL;
+    unsafe fn release_spin_lock(&self, spin_lock: *mut KSPIN_LOCK, new_irql: KIRQL);
+
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn initialize_lookaside(
+        &self,
+        lookaside: *mut LOOKASIDE_LIST_EX,
+        allocate: PALLOCATE_FUNCTION_EX,
+        free: PFREE_FUNCTION_EX,
+        pool_type: PoolType,
+        size: usize,
+        tag: u32,
+        depth: u16,
+    ) -> NTSTATUS;
+    unsafe fn delete_lookaside(&self, lookaside: *mut LOOKASIDE_LIST_EX);
+    unsafe fn pop_entry_slist(&self, list_head: *mut SLIST_HEADER) -> *mut SLIST_ENTRY;
+    unsafe fn push_entry_slist(&self, list_head: *mut SLIST_HEADER, list_entry: *mut SLIST_ENTRY) -> *mut SLIST_ENTRY;
+}
+
+static BACKEND: RwLock<Option<Arc<dyn KernelBackend>>> = RwLock::new(None);
+
+/// Installs `backend` as the target of every simulated kernel call and returns a handle to it,
+/// so it can still be configured afterwards.
+pub fn set_backend<B: KernelBackend + 'static>(backend: B) -> Arc<B> {
+    let backend = Arc::new(backend);
+    *BACKEND.write().unwrap() = Some(backend.clone());
+    backend
+}
+
+/// Returns the installed backend, installing a default [`SimKernel`] if there is none.
+pub fn backend() -> Arc<dyn KernelBackend> {
+    if let Some(backend) = BACKEND.read().unwrap().as_ref() {
+        return backend.clone();
+    }
+
+    BACKEND
+        .write()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(SimKernel::new()))
+        .clone()
+}
+
+const PAGE_SIZE: u64 = 0x1000;
+
+/// The IRQL the simulated kernel always runs at.
+const PASSIVE_LEVEL: KIRQL = 0;
+
+/// The byte fresh simulated pool allocations are filled with.
+pub const POOL_FILL: u8 = 0xA5;
+
+/// Pid of the simulated `System` process, whose address space is the host process.
+pub const SYSTEM_PID: u64 = 4;
+
+struct SimPool {
+    layout: Layout,
+    pool_type: PoolType,
+    tag: u32,
+}
+
+/// A simulated process. Memory of every process other than `System` lives in private regions.
+pub struct SimProcess {
+    pub pid: u64,
+    pub image_file_name: [u8; 16],
+    pub peb: usize,
+    pub threads: Vec<SimThread>,
+    regions: BTreeMap<u64, Vec<u8>>,
+}
+
+/// A simulated handle table entry, reported through `SystemExtendedHandleInformation`.
+#[derive(Copy, Clone, Debug)]
+pub struct SimHandle {
+    pub process_id: u64,
+    pub handle: usize,
+    pub object: usize,
+    pub granted_access: u32,
+    pub object_type_index: u16,
+    pub attributes: u32,
+}
+
+/// The fixed size system information classes. By default code integrity is enabled, no kernel
+/// debugger is attached and the machine booted from UEFI.
+#[derive(Copy, Clone, Debug)]
+pub struct SimSystemInfo {
+    pub code_integrity_options: u32,
+    pub kernel_debugger_enabled: bool,
+    pub kernel_debugger_present: bool,
+    pub boot_identifier: GUID,
+    pub firmware_type: FIRMWARE_TYPE,
+    pub boot_flags: u64,
+}
+
+impl Default for SimSystemInfo {
+    fn default() -> Self {
+        Self {
+            code_integrity_options: 0x01,
+            kernel_debugger_enabled: false,
+            kernel_debugger_present: false,
+            boot_identifier: GUID { Data1: 0, Data2: 0, Data3: 0, Data4: [0; 8] },
+            firmware_type: FirmwareTypeUefi,
+            boot_flags: 0,
+        }
+    }
+}
+
+/// A simulated thread, reported through `SystemProcessInformation`.
+#[derive(Copy, Clone, Debug)]
+pub struct SimThread {
+    pub tid: u64,
+    pub start_address: usize,
+    pub state: KTHREAD_STATE,
+    pub wait_reason: KWAIT_REASON,
+    pub priority: i32,
+}
+
+impl SimProcess {
+    fn new(pid: u64, name: &str) -> Self {
+        let mut image_file_name = [0u8; 16];
+        let len = name.len().min(image_file_name.len() - 1);
+        image_file_name[..len].copy_from_slice(&name.as_bytes()[..len]);
+
+        Self { pid, image_file_name, peb: 0, threads: Vec::new(), regions: BTreeMap::new() }
+    }
+
+    fn region(&self, address: u64) -> Option<(u64, &Vec<u8>)> {
+        let (&base, region) = self.regions.range(..=address).next_back()?;
+        if address < base + region.len() as u64 {
+            Some((base, region))
+        } else {
+            None
+        }
+    }
+
+    fn region_mut(&mut self, address: u64) -> Option<(u64, &mut Vec<u8>)> {
+        let (&base, region) = self.regions.range_mut(..=address).next_back()?;
+        if address < base + region.len() as u64 {
+            Some((base, region))
+        } else {
+            None
+        }
+    }
+
+    fn read(&self, address: u64, buf: &mut [u8]) -> usize {
+        let mut done = 0;
+        while done < buf.len() {
+            let Some((base, region)) = self.region(address + done as u64) else { break };
+            let start = (address + done as u64 - base) as usize;
+            let n = (region.len() - start).min(buf.len() - done);
+            buf[done..done + n].copy_from_slice(&region[start..start + n]);
+            done += n;
+        }
+        done
+    }
+
+    fn write(&mut self, address: u64, buf: &[u8]) -> usize {
+        let mut done = 0;
+        while done < buf.len() {
+            let Some((base, region)) = self.region_mut(address + done as u64) else { break };
+            let start = (address + done as u64 - base) as usize;
+            let n = (region.len() - start).min(buf.len() - done);
+            region[start..start + n].copy_from_slice(&buf[done..done + n]);
+            done += n;
+        }
+        done
+    }
+}
+
+/// A simulated kernel module.
+pub struct SimModule {
+    pub full_path: String,
+    pub base: usize,
+    pub size: u32,
+    pub exports: BTreeMap<String, usize>,
+}
+
+struct SimMdl {
+    virtual_address: PVOID,
+    length: u32,
+    locked: bool,
+    mapped: bool,
+}
+
+struct SimIoMapping {
+    physical_address: u64,
+    data: Box<[u8]>,
+}
+
+#[derive(Default)]
+struct SimState {
+    pools: BTreeMap<usize, SimPool>,
+    debug_output: Vec<String>,
+    // Boxed so the `EPROCESS` handles handed out stay valid as processes are added.
+    #[allow(clippy::vec_box)]
+    processes: Vec<Box<SimProcess>>,
+    modules: Vec<SimModule>,
+    object_names: BTreeMap<usize, Vec<u16>>,
+    object_references: BTreeMap<usize, usize>,
+    /// Per thread, the processes it is attached to with the `KAPC_STATE` of each attach.
+    attachments: HashMap<ThreadId, Vec<(PEPROCESS, usize)>>,
+    mdls: Vec<*mut SimMdl>,
+    callbacks: BTreeMap<u64, (usize, usize)>,
+    next_cookie: u64,
+    physical_pages: BTreeMap<u64, Box<[u8; PAGE_SIZE as usize]>>,
+    io_mappings: BTreeMap<usize, SimIoMapping>,
+    handles: Vec<SimHandle>,
+    system_info: SimSystemInfo,
+    /// Per information class, the number of queries so far and the number of upcoming queries
+    /// that fail as if the information grew since the caller sized its buffer.
+    query_counts: BTreeMap<SYSTEM_INFORMATION_CLASS, usize>,
+    forced_mismatches: BTreeMap<SYSTEM_INFORMATION_CLASS, usize>,
+    /// Overrides `NumberOfModules` in `SystemModuleInformation` results.
+    reported_module_count: Option<ULONG>,
+    /// Kernel pages `MmIsAddressValid` reports as not present.
+    invalid_pages: BTreeSet<usize>,
+}
+
+// SAFETY: the raw pointers in the state are only handles owned by the state itself, and all
+// access goes through the mutex in `SimKernel`.
+unsafe impl Send for SimState {}
+
+/// An in-process fake kernel with simulated pools, processes, modules and physical memory.
+pub struct SimKernel {
+    state: Mutex<SimState>,
+    started: Instant,
+}
+
+impl Default for SimKernel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimKernel {
+    /// Creates a kernel containing only the `System` process.
+    pub fn new() -> Self {
+        let kernel = Self { state: Mutex::new(SimState::default()), started: Instant::now() };
+        kernel.add_process(SYSTEM_PID, "System");
+        kernel
+    }
+
+    /// Adds a process and returns its `EPROCESS` handle.
+    pub fn add_process(&self, pid: u64, image_file_name: &str) -> PEPROCESS {
+        let mut state = self.state.lock().unwrap();
+        let mut process = Box::new(SimProcess::new(pid, image_file_name));
+        let handle = &mut *process as *mut SimProcess as PEPROCESS;
+        state.processes.push(process);
+        handle
+    }
+
+    /// Sets the PEB address reported for the process with the given pid.
+    pub fn set_process_peb(&self, pid: u64, peb: usize) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(process) = state.processes.iter_mut().find(|p| p.pid == pid) {
+            process.peb = peb;
+        }
+    }
+
+    /// Adds a thread to the process with the given pid.
+    pub fn add_thread(&self, pid: u64, thread: SimThread) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(process) = state.processes.iter_mut().find(|p| p.pid == pid) {
+            process.threads.push(thread);
+        }
+    }
+
+    /// Maps a zeroed region of `size` bytes at `address` in the process with the given pid.
+    pub fn map_process_memory(&self, pid: u64, address: u64, size: usize) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(process) = state.processes.iter_mut().find(|p| p.pid == pid) {
+            process.regions.insert(address, std::vec![0u8; size]);
+        }
+    }
+
+    /// Writes into previously mapped memory of a process, returning the number of bytes written.
+    pub fn write_process_memory(&self, pid: u64, address: u64, data: &[u8]) -> usize {
+        let mut state = self.state.lock().unwrap();
+        match state.processes.iter_mut().find(|p| p.pid == pid) {
+            Some(process) => process.write(address, data),
+            None => 0,
+        }
+    }
+
+    /// Adds a kernel module to the list returned by `SystemModuleInformation`.
+    pub fn add_module(&self, module: SimModule) {
+        self.state.lock().unwrap().modules.push(module);
+    }
+
+    /// Sets the name `ObQueryNameString` reports for `object`.
+    pub fn set_object_name(&self, object: PVOID, name: &str) {
+        self.state.lock().unwrap().object_names.insert(object as usize, name.encode_utf16().collect());
+    }
+
+    /// Writes `data` to simulated physical memory, creating pages as needed.
+    pub fn write_physical(&self, physical_address: u64, data: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        for (i, byte) in data.iter().enumerate() {
+            let address = physical_address + i as u64;
+            let page = state
+                .physical_pages
+                .entry(address & !(PAGE_SIZE - 1))
+                .or_insert_with(|| Box::new([0u8; PAGE_SIZE as usize]));
+            page[(address & (PAGE_SIZE - 1)) as usize] = *byte;
+        }
+    }
+
+    /// Returns the outstanding pool allocations as `(address, size, pool type, tag)`.
+    pub fn outstanding_pools(&self) -> Vec<(usize, usize, PoolType, u32)> {
+        self.state
+            .lock()
+            .unwrap()
+            .pools
+            .iter()
+            .map(|(&address, pool)| (address, pool.layout.size(), pool.pool_type, pool.tag))
+            .collect()
+    }
+
+    /// Returns the MDLs that have not been freed as `(virtual address, length, locked, mapped)`.
+    pub fn outstanding_mdls(&self) -> Vec<(usize, u32, bool, bool)> {
+        self.state
+            .lock()
+            .unwrap()
+            .mdls
+            .iter()
+            .map(|&mdl| unsafe { ((*mdl).virtual_address as usize, (*mdl).length, (*mdl).locked, (*mdl).mapped) })
+            .collect()
+    }
+
+    /// Adds an entry to the system handle table.
+    pub fn add_handle(&self, handle: SimHandle) {
+        self.state.lock().unwrap().handles.push(handle);
+    }
+
+    /// Replaces the information reported for the fixed size system information classes.
+    pub fn set_system_info(&self, info: SimSystemInfo) {
+        self.state.lock().unwrap().system_info = info;
+    }
+
+    /// Makes the next `count` queries of `class` fail with `STATUS_INFO_LENGTH_MISMATCH`, as if
+    /// the information grew between sizing the buffer and filling it.
+    pub fn force_length_mismatch(&self, class: SYSTEM_INFORMATION_CLASS, count: usize) {
+        self.state.lock().unwrap().forced_mismatches.insert(class, count);
+    }
+
+    /// Makes `MmIsAddressValid` report the page containing `address` as not present, like a
+    /// discarded `INIT` section or a paged out page, or as present again.
+    pub fn set_address_valid(&self, address: usize, valid: bool) {
+        let page = address & !(PAGE_SIZE as usize - 1);
+        let mut state = self.state.lock().unwrap();
+        if valid {
+            state.invalid_pages.remove(&page);
+        } else {
+            state.invalid_pages.insert(page);
+        }
+    }
+
+    /// Makes `SystemModuleInformation` report `count` modules regardless of how many were
+    /// written, to simulate a corrupt result. `None` restores the real count.
+    pub fn set_reported_module_count(&self, count: Option<ULONG>) {
+        self.state.lock().unwrap().reported_module_count = count;
+    }
+
+    /// The number of times `class` has been queried with `ZwQuerySystemInformation`.
+    pub fn query_count(&self, class: SYSTEM_INFORMATION_CLASS) -> usize {
+        self.state.lock().unwrap().query_counts.get(&class).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of registered registry callbacks.
+    pub fn callback_count(&self) -> usize {
+        self.state.lock().unwrap().callbacks.len()
+    }
+
+    /// Drains everything printed through `DbgPrintEx` so far.
+    pub fn take_debug_output(&self) -> Vec<String> {
+        mem::take(&mut self.state.lock().unwrap().debug_output)
+    }
+
+    /// The number of references taken on `object` through `PsLookupProcessByProcessId` or
+    /// `ObReferenceObject` that have not been released yet.
+    pub fn object_references(&self, object: PVOID) -> usize {
+        self.state.lock().unwrap().object_references.get(&(object as usize)).copied().unwrap_or(0)
+    }
+
+    /// The memory of the process with the given pid, read and written directly rather than
+    /// through `MmCopyVirtualMemory`. Only private regions are reachable, so this is `None` for
+    /// `System`.
+    pub fn process_memory(&self, pid: u64) -> Option<SimProcessMemory<'_>> {
+        match self.find_process(pid) {
+            Some(process) if pid != SYSTEM_PID => Some(SimProcessMemory { kernel: self, process }),
+            _ => None,
+        }
+    }
+
+    /// The process the calling thread is attached to with `KeStackAttachProcess`, if any.
+    pub fn attached_process(&self) -> Option<PEPROCESS> {
+        let state = self.state.lock().unwrap();
+        state.attachments.get(&thread::current().id()).and_then(|stack| stack.last()).map(|&(process, _)| process)
+    }
+
+    fn find_process(&self, pid: u64) -> Option<PEPROCESS> {
+        self.state
+            .lock()
+            .unwrap()
+            .processes
+            .iter_mut()
+            .find(|p| p.pid == pid)
+            .map(|p| &mut **p as *mut SimProcess as PEPROCESS)
+    }
+
+    fn with_process<R>(&self, process: PEPROCESS, f: impl FnOnce(&mut SimProcess) -> R) -> Option<R> {
+        let mut state = self.state.lock().unwrap();
+        state
+            .processes
+            .iter_mut()
+            .find(|p| &***p as *const SimProcess as PEPROCESS == process)
+            .map(|p| f(p))
+    }
+
+    fn is_system(&self, process: PEPROCESS) -> bool {
+        self.with_process(process, |p| p.pid == SYSTEM_PID).unwrap_or(false)
+    }
+
+    unsafe fn write_system_information(state: &SimState, class: SYSTEM_INFORMATION_CLASS, buf: PVOID, len: ULONG, return_length: &mut ULONG) -> NTSTATUS {
+        let info = &state.system_info;
+        #[allow(non_upper_case_globals)]
+        match class {
+            SystemModuleInformation => Self::write_module_information(state, buf, len, return_length),
+            SystemProcessInformation => Self::write_process_information(state, buf, len, return_length),
+            SystemExtendedHandleInformation => Self::write_handle_information(state, buf, len, return_length),
+            SystemBigPoolInformation => Self::write_big_pool_information(state, buf, len, return_length),
+            SystemCodeIntegrityInformation => {
+                let info = SYSTEM_CODEINTEGRITY_INFORMATION {
+                    Length: mem::size_of::<SYSTEM_CODEINTEGRITY_INFORMATION>() as _,
+                    CodeIntegrityOptions: info.code_integrity_options,
+                };
+                Self::write_fixed_information(info, buf, len, return_length)
+            }
+            SystemKernelDebuggerInformation => {
+                let info = SYSTEM_KERNEL_DEBUGGER_INFORMATION {
+                    KernelDebuggerEnabled: info.kernel_debugger_enabled as _,
+                    KernelDebuggerNotPresent: !info.kernel_debugger_present as _,
+                };
+                Self::write_fixed_information(info, buf, len, return_length)
+            }
+            SystemBootEnvironmentInformation => {
+                let mut boot: SYSTEM_BOOT_ENVIRONMENT_INFORMATION = mem::zeroed();
+                boot.BootIdentifier = info.boot_identifier;
+                boot.FirmwareType = info.firmware_type;
+                boot.BootFlags = info.boot_flags;
+                Self::write_fixed_information(boot, buf, len, return_length)
+            }
+            _ => ntstatus::STATUS_INVALID_INFO_CLASS,
+        }
+    }
+
+    /// Fixed size classes must be queried with a buffer of exactly their size.
+    unsafe fn write_fixed_information<T>(info: T, buf: PVOID, len: ULONG, return_length: &mut ULONG) -> NTSTATUS {
+        *return_length = mem::size_of::<T>() as _;
+        if len as usize != mem::size_of::<T>() || buf.is_null() {
+            return ntstatus::STATUS_INFO_LENGTH_MISMATCH;
+        }
+
+        ptr::write_unaligned(buf as *mut T, info);
+        ntstatus::STATUS_SUCCESS
+    }
+
+    unsafe fn write_handle_information(state: &SimState, buf: PVOID, len: ULONG, return_length: &mut ULONG) -> NTSTATUS {
+        let header = mem::size_of::<SYSTEM_HANDLE_INFORMATION_EX>() - mem::size_of::<SYSTEM_HANDLE_TABLE_ENTRY_INFO_EX>();
+        let required = header + state.handles.len().max(1) * mem::size_of::<SYSTEM_HANDLE_TABLE_ENTRY_INFO_EX>();
+        *return_length = required as _;
+        if (len as usize) < required || buf.is_null() {
+            return ntstatus::STATUS_INFO_LENGTH_MISMATCH;
+        }
+
+        ptr::write_bytes(buf as *mut u8, 0, required);
+        (*(buf as *mut SYSTEM_HANDLE_INFORMATION_EX)).NumberOfHandles = state.handles.len();
+        let entries = (buf as *mut u8).add(header) as *mut SYSTEM_HANDLE_TABLE_ENTRY_INFO_EX;
+        for (i, handle) in state.handles.iter().enumerate() {
+            let entry = &mut *entries.add(i);
+            entry.Object = handle.object as _;
+            entry.UniqueProcessId = handle.process_id as _;
+            entry.HandleValue = handle.handle;
+            entry.GrantedAccess = handle.granted_access;
+            entry.ObjectTypeIndex = handle.object_type_index;
+            entry.HandleAttributes = handle.attributes;
+        }
+
+        ntstatus::STATUS_SUCCESS
+    }
+
+    /// Like the real big pool table, this covers every allocation of at least a page.
+    unsafe fn write_big_pool_information(state: &SimState, buf: PVOID, len: ULONG, return_length: &mut ULONG) -> NTSTATUS {
+        let pools: Vec<_> = state.pools.iter().filter(|(_, pool)| pool.layout.size() >= PAGE_SIZE as usize).collect();
+        let header = mem::size_of::<SYSTEM_BIGPOOL_INFORMATION>() - mem::size_of::<SYSTEM_BIGPOOL_ENTRY>();
+        let required = header + pools.len().max(1) * mem::size_of::<SYSTEM_BIGPOOL_ENTRY>();
+        *return_length = required as _;
+        if (len as usize) < required || buf.is_null() {
+            return ntstatus::STATUS_INFO_LENGTH_MISMATCH;
+        }
+
+        ptr::write_bytes(buf as *mut u8, 0, required);
+        (*(buf as *mut SYSTEM_BIGPOOL_INFORMATION)).Count = pools.len() as _;
+        let entries = (buf as *mut u8).add(header) as *mut SYSTEM_BIGPOOL_ENTRY;
+        for (i, (&address, pool)) in pools.into_iter().enumerate() {
+            let entry = &mut *entries.add(i);
+            // The low bit of the address is the non-paged flag.
+            entry.u1.Bitfields = address | !pool.pool_type.is_paged() as usize;
+            entry.SizeInBytes = pool.layout.size();
+            entry.u2.TagUlong = pool.tag;
+        }
+
+        ntstatus::STATUS_SUCCESS
+    }
+
+    unsafe fn write_module_information(state: &SimState, buf: PVOID, len: ULONG, return_length: &mut ULONG) -> NTSTATUS {
+        let header = mem::size_of::<RTL_PROCESS_MODULES>() - mem::size_of::<RTL_PROCESS_MODULE_INFORMATION>();
+        let required = header + state.modules.len().max(1) * mem::size_of::<ProcessModuleInformation>();
+        *return_length = required as _;
+        if (len as usize) < required || buf.is_null() {
+            return ntstatus::STATUS_INFO_LENGTH_MISMATCH;
+        }
+
+        ptr::write_bytes(buf as *mut u8, 0, required);
+        *(buf as *mut ULONG) = state.reported_module_count.unwrap_or(state.modules.len() as _);
+        let entries = (buf as *mut u8).add(header) as *mut ProcessModuleInformation;
+        for (i, module) in state.modules.iter().enumerate() {
+            let entry = &mut *entries.add(i);
+            entry.image_base = module.base;
+            entry.image_size = module.size;
+            entry.load_order_index = i as _;
+            entry.init_order_index = i as _;
+            entry.load_count = 1;
+            let path = module.full_path.as_bytes();
+            let path_len = path.len().min(entry.full_path_name.len() - 1);
+            entry.full_path_name[..path_len].copy_from_slice(&path[..path_len]);
+            entry.offset_to_file_name = path[..path_len]
+                .iter()
+                .rposition(|&c| c == b'\\')
+                .map_or(0, |i| i + 1) as _;
+        }
+
+        ntstatus::STATUS_SUCCESS
+    }
+
+    unsafe fn write_process_information(state: &SimState, buf: PVOID, len: ULONG, return_length: &mut ULONG) -> NTSTATUS {
+        let align = |n: usize| (n + 7) & !7;
+        let threads_offset = mem::size_of::<ProcessInfo>() - mem::size_of::<SYSTEM_THREAD_INFORMATION>();
+        let names_offset = |p: &SimProcess| threads_offset + p.threads.len().max(1) * mem::size_of::<SYSTEM_THREAD_INFORMATION>();
+        let entry_size = |p: &SimProcess| {
+            let name_len = p.image_file_name.iter().position(|&c| c == 0).unwrap_or(p.image_file_name.len());
+            (align(names_offset(p) + name_len * 2), name_len)
+        };
+
+        let required: usize = state.processes.iter().map(|p| entry_size(p).0).sum();
+        *return_length = required as _;
+        if (len as usize) < required || buf.is_null() {
+            return ntstatus::STATUS_INFO_LENGTH_MISMATCH;
+        }
+
+        ptr::write_bytes(buf as *mut u8, 0, required);
+        let mut offset = 0;
+        for (i, process) in state.processes.iter().enumerate() {
+            let (size, name_len) = entry_size(process);
+            let entry = (buf as *mut u8).add(offset) as *mut ProcessInfo;
+            let name = (entry as *mut u8).add(names_offset(process)) as *mut u16;
+            for (j, &c) in process.image_file_name[..name_len].iter().enumerate() {
+                *name.add(j) = c as u16;
+            }
+
+            (*entry).next_entry_offset = if i + 1 == state.processes.len() { 0 } else { size as _ };
+            (*entry).image_name = UnicodeString {
+                length: (name_len * 2) as _,
+                maximum_length: (name_len * 2) as _,
+                buffer: name,
+            };
+            (*entry).unique_process_id = process.pid as _;
+            (*entry).number_of_threads = process.threads.len() as _;
+
+            let threads = (entry as *mut u8).add(threads_offset) as *mut SYSTEM_THREAD_INFORMATION;
+            for (j, thread) in process.threads.iter().enumerate() {
+                let info = &mut *threads.add(j);
+                info.ClientId.UniqueProcess = process.pid as _;
+                info.ClientId.UniqueThread = thread.tid as _;
+                info.StartAddress = thread.start_address as _;
+                info.ThreadState = thread.state;
+                info.WaitReason = thread.wait_reason;
+                info.Priority = thread.priority;
+            }
+            offset += size;
+        }
+
+        ntstatus::STATUS_SUCCESS
+    }
+}
+
+/// Direct access to the memory of a simulated process, see [`SimKernel::process_memory`].
+pub struct SimProcessMemory<'a> {
+    kernel: &'a SimKernel,
+    process: PEPROCESS,
+}
+
+impl MemoryReader for SimProcessMemory<'_> {
+    unsafe fn read_memory(&self, address: u64, buf: &mut [u8]) -> Result<(), (NtStatus, usize)> {
+        let read = self.kernel.with_process(self.process, |p| p.read(address, buf)).unwrap_or(0);
+        copy_result(NtStatus(ntstatus::STATUS_SUCCESS), read, buf.len())
+    }
+}
+
+impl MemoryWriter for SimProcessMemory<'_> {
+    unsafe fn write_memory(&self, address: u64, buf: &[u8]) -> Result<(), (NtStatus, usize)> {
+        let written = self.kernel.with_process(self.process, |p| p.write(address, buf)).unwrap_or(0);
+        copy_result(NtStatus(ntstatus::STATUS_SUCCESS), written, buf.len())
+    }
+}
+
+impl KernelBackend for SimKernel {
+    unsafe fn allocate_pool(&self, pool_type: PoolType, size: usize, tag: u32) -> *mut c_void {
+        let layout = match Layout::from_size_align(size.max(1), 16) {
+            Ok(layout) => layout,
+            Err(_) => return null_mut(),
+        };
+        // Real pool memory is not zeroed, so fill it with junk to catch code that assumes it is.
+        let pool = alloc(layout);
+        if !pool.is_null() {
+            ptr::write_bytes(pool, POOL_FILL, layout.size());
+            self.state.lock().unwrap().pools.insert(pool as usize, SimPool { layout, pool_type, tag });
+        }
+        pool as _
+    }
+
+    unsafe fn free_pool(&self, pool: *mut c_void, tag: u32) {
+        let entry = self.state.lock().unwrap().pools.remove(&(pool as usize));
+        match entry {
+            Some(entry) if entry.tag == tag => dealloc(pool as _, entry.layout),
+            Some(_) => panic!("BAD_POOL_CALLER: {:p} freed with the wrong tag", pool),
+            None => panic!("BAD_POOL_CALLER: {:p} is not a pool allocation", pool),
+        }
+    }
+
+    fn debug_print(&self, text: &str) {
+        std::eprint!("{}", text);
+        self.state.lock().unwrap().debug_output.push(text.into());
+    }
+
+    fn lookup_process(&self, pid: u64) -> Result<PEPROCESS, NtStatus> {
+        let process = self.find_process(pid).ok_or(NtStatus(ntstatus::STATUS_INVALID_CID))?;
+        self.reference_object(process as PVOID);
+        Ok(process)
+    }
+
+    fn current_process(&self) -> PEPROCESS {
+        self.find_process(SYSTEM_PID).unwrap()
+    }
+
+    fn process_peb(&self, process: PEPROCESS) -> PPEB {
+        self.with_process(process, |p| p.peb as PPEB).unwrap_or(null_mut())
+    }
+
+    fn process_image_file_name(&self, process: PEPROCESS) -> *const u8 {
+        self.with_process(process, |p| p.image_file_name.as_ptr()).unwrap_or(ptr::null())
+    }
+
+    fn reference_object(&self, object: PVOID) -> isize {
+        let mut state = self.state.lock().unwrap();
+        let references = state.object_references.entry(object as usize).or_insert(0);
+        *references += 1;
+        *references as isize
+    }
+
+    fn dereference_object(&self, object: PVOID) -> isize {
+        let mut state = self.state.lock().unwrap();
+        let references = match state.object_references.get_mut(&(object as usize)) {
+            Some(references) => references,
+            None => panic!("REFERENCE_BY_POINTER: {:p} dereferenced without a reference", object),
+        };
+        *references -= 1;
+        let remaining = *references;
+        if remaining == 0 {
+            state.object_references.remove(&(object as usize));
+        }
+        remaining as isize
+    }
+
+    unsafe fn stack_attach_process(&self, process: PEPROCESS, apc_state: *mut KAPC_STATE) {
+        let system = self.find_process(SYSTEM_PID).unwrap();
+        let mut state = self.state.lock().unwrap();
+        let stack = state.attachments.entry(thread::current().id()).or_default();
+        (*apc_state).process = stack.last().map_or(system, |&(previous, _)| previous);
+        stack.push((process, apc_state as usize));
+    }
+
+    unsafe fn unstack_detach_process(&self, apc_state: *mut KAPC_STATE) {
+        let mut state = self.state.lock().unwrap();
+        let id = thread::current().id();
+        let stack = state.attachments.entry(id).or_default();
+        match stack.last() {
+            Some(&(_, saved)) if saved == apc_state as usize => {
+                stack.pop();
+            }
+            _ => panic!("INVALID_PROCESS_DETACH_ATTEMPT: {:p} is not the innermost attach of this thread", apc_state),
+        }
+        if stack.is_empty() {
+            state.attachments.remove(&id);
+        }
+    }
+
+    unsafe fn copy_virtual_memory(
+        &self,
+        from_process: PEPROCESS,
+        from_address: *mut c_void,
+        to_process: PEPROCESS,
+        to_address: *mut c_void,
+        size: usize,
+        bytes_copied: &mut usize,
+    ) -> NTSTATUS {
+        *bytes_copied = 0;
+
+        let mut buf = std::vec![0u8; size];
+        let read = if self.is_system(from_process) {
+            ptr::copy_nonoverlapping(from_address as *const u8, buf.as_mut_ptr(), size);
+            size
+        } else {
+            match self.with_process(from_process, |p| p.read(from_address as u64, &mut buf)) {
+                Some(read) => read,
+                None => return ntstatus::STATUS_INVALID_PARAMETER,
+            }
+        };
+
+        let written = if self.is_system(to_process) {
+            ptr::copy_nonoverlapping(buf.as_ptr(), to_address as *mut u8, read);
+            read
+        } else {
+            match self.with_process(to_process, |p| p.write(to_address as u64, &buf[..read])) {
+                Some(written) => written,
+                None => return ntstatus::STATUS_INVALID_PARAMETER,
+            }
+        };
+
+        *bytes_copied = written;
+        if written == size {
+            ntstatus::STATUS_SUCCESS
+        } else {
+            ntstatus::STATUS_PARTIAL_COPY
+        }
+    }
+
+    unsafe fn query_system_information(
+        &self,
+        class: SYSTEM_INFORMATION_CLASS,
+        buf: PVOID,
+        len: ULONG,
+        return_length: &mut ULONG,
+    ) -> NTSTATUS {
+        let mut state = self.state.lock().unwrap();
+        *state.query_counts.entry(class).or_default() += 1;
+        if let Some(remaining) = state.forced_mismatches.get_mut(&class).filter(|remaining| **remaining > 0) {
+            *remaining -= 1;
+            Self::write_system_information(&state, class, null_mut(), 0, return_length);
+            return ntstatus::STATUS_INFO_LENGTH_MISMATCH;
+        }
+
+        Self::write_system_information(&state, class, buf, len, return_length)
+    }
+
+    unsafe fn find_exported_routine(&self, module_base: PVOID, name: &CStr) -> PVOID {
+        let state = self.state.lock().unwrap();
+        let name = name.to_string_lossy();
+        state
+            .modules
+            .iter()
+            .find(|m| m.base == module_base as usize)
+            .and_then(|m| m.exports.get(&*name))
+            .map_or(null_mut(), |&address| address as _)
+    }
+
+    unsafe fn query_object_name(&self, object: PVOID, buf: PVOID, len: ULONG, return_length: &mut ULONG) -> NTSTATUS {
+        let state = self.state.lock().unwrap();
+        let name = match state.object_names.get(&(object as usize)) {
+            Some(name) => name,
+            None => return ntstatus::STATUS_OBJECT_NAME_NOT_FOUND,
+        };
+
+        let header = mem::size_of::<UNICODE_STRING>();
+        let required = header + (name.len() + 1) * 2;
+        *return_length = required as _;
+        if (len as usize) < required || buf.is_null() {
+            return ntstatus::STATUS_INFO_LENGTH_MISMATCH;
+        }
+
+        let chars = (buf as *mut u8).add(header) as *mut u16;
+        ptr::copy_nonoverlapping(name.as_ptr(), chars, name.len());
+        *chars.add(name.len()) = 0;
+        *(buf as *mut UNICODE_STRING) = UNICODE_STRING {
+            Length: (name.len() * 2) as _,
+            MaximumLength: ((name.len() + 1) * 2) as _,
+            Buffer: chars,
+        };
+
+        ntstatus::STATUS_SUCCESS
+    }
+
+    unsafe fn allocate_mdl(&self, virtual_address: PVOID, length: u32) -> PMDL {
+        let mdl = Box::into_raw(Box::new(SimMdl { virtual_address, length, locked: false, mapped: false }));
+        self.state.lock().unwrap().mdls.push(mdl);
+        mdl as _
+    }
+
+    unsafe fn probe_and_lock_pages(&self, mdl: PMDL) {
+        let mdl = &mut *(mdl as *mut SimMdl);
+        assert!(!mdl.locked, "MmProbeAndLockPages called on a locked MDL");
+        mdl.locked = true;
+    }
+
+    unsafe fn map_locked_pages(&self, mdl: PMDL) -> PVOID {
+        let mdl = &mut *(mdl as *mut SimMdl);
+        assert!(mdl.locked, "MmMapLockedPagesSpecifyCache called on an unlocked MDL");
+        mdl.mapped = true;
+        mdl.virtual_address
+    }
+
+    unsafe fn protect_mdl_system_address(&self, mdl: PMDL, _new_protect: ULONG) -> NTSTATUS {
+        let mdl = &*(mdl as *mut SimMdl);
+        if mdl.mapped {
+            ntstatus::STATUS_SUCCESS
+        } else {
+            ntstatus::STATUS_NOT_MAPPED_VIEW
+        }
+    }
+
+    unsafe fn unmap_locked_pages(&self, base_address: PVOID, mdl: PMDL) {
+        let mdl = &mut *(mdl as *mut SimMdl);
+        assert!(mdl.mapped && base_address == mdl.virtual_address, "MmUnmapLockedPages called on an unmapped MDL");
+        mdl.mapped = false;
+    }
+
+    unsafe fn unlock_pages(&self, mdl: PMDL) {
+        let mdl = &mut *(mdl as *mut SimMdl);
+        assert!(mdl.locked && !mdl.mapped, "MmUnlockPages called on an unlocked or mapped MDL");
+        mdl.locked = false;
+    }
+
+    unsafe fn free_mdl(&self, mdl: PMDL) {
+        let mdl = mdl as *mut SimMdl;
+        self.state.lock().unwrap().mdls.retain(|&m| m != mdl);
+        let mdl = Box::from_raw(mdl);
+        assert!(!mdl.locked, "IoFreeMdl called on a locked MDL of {} bytes", mdl.length);
+    }
+
+    fn register_callback(&self, func: *mut c_void, context: *mut c_void, cookie: &mut u64) -> NTSTATUS {
+        let mut state = self.state.lock().unwrap();
+        state.next_cookie += 1;
+        *cookie = state.next_cookie;
+        state.callbacks.insert(*cookie, (func as usize, context as usize));
+        ntstatus::STATUS_SUCCESS
+    }
+
+    fn unregister_callback(&self, cookie: u64) -> NTSTATUS {
+        match self.state.lock().unwrap().callbacks.remove(&cookie) {
+            Some(_) => ntstatus::STATUS_SUCCESS,
+            None => ntstatus::STATUS_INVALID_PARAMETER,
+        }
+    }
+
+    unsafe fn copy_physical_memory(&self, target: *mut u8, physical_address: u64, size: usize, bytes_transferred: &mut usize) -> NTSTATUS {
+        let state = self.state.lock().unwrap();
+        *bytes_transferred = 0;
+        while *bytes_transferred < size {
+            let address = physical_address + *bytes_transferred as u64;
+            let page = match state.physical_pages.get(&(address & !(PAGE_SIZE - 1))) {
+                Some(page) => page,
+                None => return ntstatus::STATUS_PARTIAL_COPY,
+            };
+            let start = (address & (PAGE_SIZE - 1)) as usize;
+            let n = (PAGE_SIZE as usize - start).min(size - *bytes_transferred);
+            ptr::copy_nonoverlapping(page[start..].as_ptr(), target.add(*bytes_transferred), n);
+            *bytes_transferred += n;
+        }
+        ntstatus::STATUS_SUCCESS
+    }
+
+    unsafe fn map_io_space(&self, physical_address: u64, len: usize) -> *mut c_void {
+        let mut data = std::vec![0u8; len].into_boxed_slice();
+        let mut transferred = 0;
+        self.copy_physical_memory(data.as_mut_ptr(), physical_address, len, &mut transferred);
+
+        let address = data.as_mut_ptr();
+        self.state.lock().unwrap().io_mappings.insert(address as usize, SimIoMapping { physical_address, data });
+        address as _
+    }
+
+    unsafe fn unmap_io_space(&self, base_address: *mut c_void, _len: usize) {
+        let mapping = self.state.lock().unwrap().io_mappings.remove(&(base_address as usize));
+        if let Some(mapping) = mapping {
+            self.write_physical(mapping.physical_address, &mapping.data);
+        }
+    }
+
+    fn query_performance_counter(&self, frequency: Option<&mut i64>) -> u64 {
+        if let Some(frequency) = frequency {
+            *frequency = 1_000_000_000;
+        }
+        self.started.elapsed().as_nanos() as u64
+    }
+
+    fn is_address_valid(&self, address: usize) -> bool {
+        let page = address & !(PAGE_SIZE as usize - 1);
+        address != 0 && !self.state.lock().unwrap().invalid_pages.contains(&page)
+    }
+
+    unsafe fn acquire_spin_lock(&self, spin_lock: *mut KSPIN_LOCK) -> KIRQL {
+        let lock = &*(spin_lock as *const AtomicUsize);
+        while lock.compare_exchange_weak(0, 1, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            hint::spin_loop();
+        }
+        PASSIVE_LEVEL
+    }
+
+    unsafe fn release_spin_lock(&self, spin_lock: *mut KSPIN_LOCK, _new_irql: KIRQL) {
+        (*(spin_lock as *const AtomicUsize)).store(0, Ordering::Release);
+    }
+
+    unsafe fn initialize_lookaside(
+        &self,
+        lookaside: *mut LOOKASIDE_LIST_EX,
+        allocate: PALLOCATE_FUNCTION_EX,
+        free: PFREE_FUNCTION_EX,
+        pool_type: PoolType,
+        size: usize,
+        tag: u32,
+        _depth: u16,
+    ) -> NTSTATUS {
+        if size > u32::MAX as usize {
+            return ntstatus::STATUS_INVALID_PARAMETER;
+        }
+
+        // The depth argument is reserved; the kernel starts every list at the minimum depth.
+        ptr::write_bytes(lookaside, 0, 1);
+        let lookaside = &mut *lookaside;
+        lookaside.depth = LOOKASIDE_MINIMUM_DEPTH;
+        lookaside.maximum_depth = LOOKASIDE_MAXIMUM_DEPTH;
+        lookaside.pool_type = pool_type;
+        lookaside.tag = tag;
+        lookaside.size = size as u32;
+        lookaside.allocate_ex = allocate.or(Some(lookaside_allocate));
+        lookaside.free_ex = free.or(Some(lookaside_free));
+        ntstatus::STATUS_SUCCESS
+    }
+
+    unsafe fn delete_lookaside(&self, lookaside: *mut LOOKASIDE_LIST_EX) {
+        loop {
+            let entry = self.pop_entry_slist(&mut (*lookaside).list_head);
+            if entry.is_null() {
+                break;
+            }
+            if let Some(free) = (*lookaside).free_ex {
+                free(entry as _, lookaside);
+            }
+        }
+    }
+
+    // The state lock stands in for the interlocked compare-exchange. The depth in `alignment` is
+    // read without the lock by `ExQueryDepthSList`, so it is updated atomically.
+    unsafe fn pop_entry_slist(&self, list_head: *mut SLIST_HEADER) -> *mut SLIST_ENTRY {
+        let _state = self.state.lock().unwrap();
+        let entry = (*list_head).region as *mut SLIST_ENTRY;
+        if !entry.is_null() {
+            (*list_head).region = (*entry).next as u64;
+            AtomicU64::from_ptr(ptr::addr_of_mut!((*list_head).alignment)).fetch_sub(1, Ordering::Relaxed);
+        }
+        entry
+    }
+
+    unsafe fn push_entry_slist(&self, list_head: *mut SLIST_HEADER, list_entry: *mut SLIST_ENTRY) -> *mut SLIST_ENTRY {
+        let _state = self.state.lock().unwrap();
+        let previous = (*list_head).region as *mut SLIST_ENTRY;
+        (*list_entry).next = previous;
+        (*list_head).region = list_entry as u64;
+        AtomicU64::from_ptr(ptr::addr_of_mut!((*list_head).alignment)).fetch_add(1, Ordering::Relaxed);
+        previous
+    }
+}
+
+const LOOKASIDE_MINIMUM_DEPTH: u16 = 4;
+const LOOKASIDE_MAXIMUM_DEPTH: u16 = 256;
+
+unsafe extern "system" fn lookaside_allocate(pool_type: PoolType, size: usize, tag: u32, _lookaside: *mut LOOKASIDE_LIST_EX) -> *mut c_void {
+    backend().allocate_pool(pool_type, size, tag)
+}
+
+unsafe extern "system" fn lookaside_free(buffer: *mut c_void, lookaside: *mut LOOKASIDE_LIST_EX) {
+    backend().free_pool(buffer, (*lookaside).tag)
+}
+
+/// Expands a `DbgPrintEx` format string. Supports the `%c`, `%s`, `%ws`/`%S`, `%d`/`%i`, `%u`,
+/// `%x`/`%X`, `%o` and `%p` conversions with flags, width, precision and the `h`, `l`, `ll`,
+/// `I64`, `I` and `z` size prefixes, and `%%`. Anything else is copied to the output as is.
+unsafe fn format_debug_print(fmt: &[u8], args: &mut VaList<'_>) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+    while i < fmt.len() {
+        if fmt[i] != b'%' {
+            let end = fmt[i..].iter().position(|&c| c == b'%').map_or(fmt.len(), |n| i + n);
+            out.push_str(&String::from_utf8_lossy(&fmt[i..end]));
+            i = end;
+            continue;
+        }
+
+        let start = i;
+        i += 1;
+        let (mut left, mut zero) = (false, false);
+        while let Some(&flag) = fmt.get(i).filter(|c| b"-+ 0#".contains(c)) {
+            left |= flag == b'-';
+            zero |= flag == b'0';
+            i += 1;
+        }
+        let number = |i: &mut usize| {
+            let digits = fmt[*i..].iter().take_while(|c| c.is_ascii_digit()).count();
+            let value = core::str::from_utf8(&fmt[*i..*i + digits]).unwrap().parse::<usize>().ok();
+            *i += digits;
+            value
+        };
+        let width = number(&mut i).unwrap_or(0);
+        let precision = match fmt.get(i) {
+            Some(b'.') => {
+                i += 1;
+                Some(number(&mut i).unwrap_or(0))
+            }
+            _ => None,
+        };
+
+        // `l` is 32 bits on Windows, but makes `%ls` a wide string like `w` does.
+        let prefix = [&b"ll"[..], b"I64", b"I32", b"h", b"l", b"w", b"z", b"I"].into_iter().find(|p| fmt[i..].starts_with(p));
+        let quad = matches!(prefix, Some(b"ll" | b"I64" | b"z" | b"I"));
+        let wide = matches!(prefix, Some(b"l" | b"w"));
+        i += prefix.map_or(0, <[u8]>::len);
+
+        let text = match fmt.get(i) {
+            Some(b'%') => "%".into(),
+            Some(b'c') => char::from(args.next_arg::<i32>() as u8).into(),
+            Some(&conversion @ (b's' | b'S')) => {
+                let string = args.next_arg::<*const u8>();
+                let text = if string.is_null() {
+                    "(null)".into()
+                } else if conversion == b'S' || wide {
+                    let string = string as *const u16;
+                    let len = (0..).take_while(|&n| *string.add(n) != 0).count();
+                    String::from_utf16_lossy(core::slice::from_raw_parts(string, len))
+                } else {
+                    CStr::from_ptr(string as _).to_string_lossy().into_owned()
+                };
+                match precision {
+                    Some(max) => text.chars().take(max).collect(),
+                    None => text,
+                }
+            }
+            Some(b'd' | b'i') if quad => args.next_arg::<i64>().to_string(),
+            Some(b'd' | b'i') => args.next_arg::<i32>().to_string(),
+            Some(&conversion @ (b'u' | b'x' | b'X' | b'o' | b'p')) => {
+                let value = match conversion {
+                    b'p' => args.next_arg::<usize>() as u64,
+                    _ if quad => args.next_arg::<u64>(),
+                    _ => args.next_arg::<u32>() as u64,
+                };
+                match conversion {
+                    b'u' => value.to_string(),
+                    b'x' => std::format!("{:x}", value),
+                    b'X' => std::format!("{:X}", value),
+                    b'o' => std::format!("{:o}", value),
+                    _ => std::format!("{:016X}", value),
+                }
+            }
+            _ => {
+                // Unknown conversions are printed verbatim and consume no argument.
+                i = i.min(fmt.len() - 1);
+                String::from_utf8_lossy(&fmt[start..=i]).into_owned()
+            }
+        };
+        i += 1;
+
+        let pad = width.saturating_sub(text.chars().count());
+        if left {
+            out.push_str(&text);
+            out.extend(std::iter::repeat_n(' ', pad));
+        } else {
+            let fill = if zero && !matches!(fmt[i - 1], b's' | b'S' | b'c') { '0' } else { ' ' };
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(digits) if fill == '0' => ("-", digits),
+                _ => ("", text.as_str()),
+            };
+            out.push_str(sign);
+            out.extend(std::iter::repeat_n(fill, pad));
+            out.push_str(digits);
+        }
+    }
+    out
+}
+
+/// Drop-in replacements for the `ntoskrnl`/`hal` imports, forwarding to [`backend`].
+#[allow(non_snake_case)]
+pub mod ntoskrnl {
+    use super::*;
+    use crate::mdl::{LOCK_OPERATION, MEMORY_CACHING_TYPE};
+    use crate::process::PeProcess;
+    use crate::basedef::ntapi::ntobapi::POBJECT_NAME_INFORMATION;
+
+    pub unsafe fn ExAllocatePoolWithTag(pool_type: PoolType, number_of_bytes: usize, tag: u32) -> *mut c_void {
+        backend().allocate_pool(pool_type, number_of_bytes, tag)
+    }
+
+    pub unsafe fn ExFreePoolWithTag(pool: *mut c_void, tag: u32) {
+        backend().free_pool(pool, tag)
+    }
+
+    pub unsafe extern "C" fn DbgPrintEx(_component_id: u32, _level: u32, fmt: *const u8, mut args: ...) -> i32 {
+        let text = format_debug_print(CStr::from_ptr(fmt as _).to_bytes(), &mut args);
+        backend().debug_print(&text);
+        text.len() as _
+    }
+
+    pub unsafe fn PsLookupProcessByProcessId(process_id: HANDLE, process: *mut PeProcess) -> NtStatus {
+        match backend().lookup_process(process_id as u64) {
+            Ok(p) => {
+                *process = PeProcess::from_peprocess(p);
+                NtStatus(ntstatus::STATUS_SUCCESS)
+            }
+            Err(status) => status,
+        }
+    }
+
+    pub unsafe fn PsGetProcessPeb(process: PeProcess) -> PPEB {
+        backend().process_peb(process.as_ptr())
+    }
+
+    pub unsafe fn IoGetCurrentProcess() -> PeProcess {
+        PeProcess::from_peprocess(backend().current_process())
+    }
+
+    pub unsafe fn PsGetProcessImageFileName(process: PeProcess) -> *const u8 {
+        backend().process_image_file_name(process.as_ptr())
+    }
+
+    pub unsafe fn MmCopyVirtualMemory(from_process: PeProcess, from_address: *mut c_void, to_process: PeProcess, to_address: *mut c_void, size: usize, _previous_mode: KProcessorMode, bytes_copied: &mut usize) -> NtStatus {
+        NtStatus(backend().copy_virtual_memory(from_process.as_ptr(), from_address, to_process.as_ptr(), to_address, size, bytes_copied))
+    }
+
+    pub unsafe fn ObfReferenceObject(object: PVOID) -> isize {
+        backend().reference_object(object)
+    }
+
+    pub unsafe fn ObfDereferenceObject(object: PVOID) -> isize {
+        backend().dereference_object(object)
+    }
+
+    pub unsafe fn KeStackAttachProcess(process: PeProcess, apc_state: *mut KAPC_STATE) {
+        backend().stack_attach_process(process.as_ptr(), apc_state)
+    }
+
+    pub unsafe fn KeUnstackDetachProcess(apc_state: *mut KAPC_STATE) {
+        backend().unstack_detach_process(apc_state)
+    }
+
+    pub unsafe fn IoAllocateMdl(virtual_address: *mut c_void, length: u32, _secondary_buffer: u8, _charge_quota: u8, _irp: *mut c_void) -> PMDL {
+        backend().allocate_mdl(virtual_address as _, length)
+    }
+
+    pub unsafe fn MmProbeAndLockPages(memory_descriptor_list: PMDL, _access_mode: KProcessorMode, _operation: LOCK_OPERATION) {
+        backend().probe_and_lock_pages(memory_descriptor_list)
+    }
+
+    pub unsafe fn MmMapLockedPagesSpecifyCache(memory_descriptor_list: PMDL, _access_mode: KProcessorMode, _cache_type: MEMORY_CACHING_TYPE, _requested_address: PVOID, _bug_check_on_failure: ULONG, _priority: ULONG) -> PVOID {
+        backend().map_locked_pages(memory_descriptor_list)
+    }
+
+    pub unsafe fn MmProtectMdlSystemAddress(memory_descriptor_list: PMDL, new_protect: ULONG) -> NtStatus {
+        NtStatus(backend().protect_mdl_system_address(memory_descriptor_list, new_protect))
+    }
+
+    pub unsafe fn MmUnmapLockedPages(base_address: PVOID, memory_descriptor_list: PMDL) {
+        backend().unmap_locked_pages(base_address, memory_descriptor_list)
+    }
+
+    pub unsafe fn MmUnlockPages(memory_descriptor_list: PMDL) {
+        backend().unlock_pages(memory_descriptor_list)
+    }
+
+    pub unsafe fn IoFreeMdl(mdl: PMDL) {
+        backend().free_mdl(mdl)
+    }
+
+    pub unsafe fn ZwQuerySystemInformation(class: SYSTEM_INFORMATION_CLASS, buf: PVOID, len: ULONG, return_length: PULONG) -> NTSTATUS {
+        let mut needed = 0;
+        let status = backend().query_system_information(class, buf, len, &mut needed);
+        if !return_length.is_null() {
+            *return_length = needed;
+        }
+        status
+    }
+
+    pub unsafe fn RtlFindExportedRoutineByName(module_base: PVOID, routine_name: PSTR) -> PVOID {
+        backend().find_exported_routine(module_base, CStr::from_ptr(routine_name))
+    }
+
+    pub unsafe fn CmRegisterCallback(func: *mut c_void, context: *mut c_void, cookie: *mut u64) -> NtStatus {
+        NtStatus(backend().register_callback(func, context, &mut *cookie))
+    }
+
+    pub unsafe fn CmUnRegisterCallback(cookie: u64) -> NtStatus {
+        NtStatus(backend().unregister_callback(cookie))
+    }
+
+    pub unsafe fn ObQueryNameString(object: PVOID, object_name_info: POBJECT_NAME_INFORMATION, length: ULONG, return_length: PULONG) -> NtStatus {
+        NtStatus(backend().query_object_name(object, object_name_info as _, length, &mut *return_length))
+    }
+
+    pub unsafe fn MmCopyMemory(target: *mut u8, copy_address: i64, size: usize, flags: u32, bytes_transferred: *mut usize) -> NtStatus {
+        if flags & 0x2 != 0 {
+            // MM_COPY_MEMORY_VIRTUAL: kernel virtual addresses are host addresses.
+            ptr::copy_nonoverlapping(copy_address as *const u8, target, size);
+            *bytes_transferred = size;
+            return NtStatus(ntstatus::STATUS_SUCCESS);
+        }
+        NtStatus(backend().copy_physical_memory(target, copy_address as _, size, &mut *bytes_transferred))
+    }
+
+    pub unsafe fn MmMapIoSpaceEx(physical_address: i64, len: usize, _protect: u32) -> *mut c_void {
+        backend().map_io_space(physical_address as _, len)
+    }
+
+    pub unsafe fn MmUnmapIoSpace(base_address: *mut c_void, len: usize) {
+        backend().unmap_io_space(base_address, len)
+    }
+
+    pub unsafe fn KeQueryPerformanceCounter(performance_frequency: *mut i64) -> u64 {
+        backend().query_performance_counter(performance_frequency.as_mut())
+    }
+
+    pub unsafe fn KeAcquireSpinLockRaiseToDpc(spin_lock: *mut KSPIN_LOCK) -> KIRQL {
+        backend().acquire_spin_lock(spin_lock)
+    }
+
+    pub unsafe fn KeReleaseSpinLock(spin_lock: *mut KSPIN_LOCK, new_irql: KIRQL) {
+        backend().release_spin_lock(spin_lock, new_irql)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn ExInitializeLookasideListEx(
+        lookaside: *mut LOOKASIDE_LIST_EX,
+        allocate: PALLOCATE_FUNCTION_EX,
+        free: PFREE_FUNCTION_EX,
+        pool_type: PoolType,
+        _flags: ULONG,
+        size: usize,
+        tag: ULONG,
+        depth: USHORT,
+    ) -> NtStatus {
+        NtStatus(backend().initialize_lookaside(lookaside, allocate, free, pool_type, size, tag, depth))
+    }
+
+    pub unsafe fn ExDeleteLookasideListEx(lookaside: *mut LOOKASIDE_LIST_EX) {
+        backend().delete_lookaside(lookaside)
+    }
+
+    pub unsafe fn ExpInterlockedPopEntrySList(list_head: *mut SLIST_HEADER) -> *mut SLIST_ENTRY {
+        backend().pop_entry_slist(list_head)
+    }
+
+    pub unsafe fn ExpInterlockedPushEntrySList(list_head: *mut SLIST_HEADER, list_entry: *mut SLIST_ENTRY) -> *mut SLIST_ENTRY {
+        backend().push_entry_slist(list_head, list_entry)
+    }
+
+    pub unsafe fn MmIsAddressValid(virtual_address: *mut c_void) -> BOOLEAN {
+        backend().is_address_valid(virtual_address as usize) as _
+    }
+}
@@ -0,0 +1,533 @@
+//! The parts of `winapi` and `ntapi` this crate uses, for hosts other than Windows.
+//!
+//! Both crates are `#![cfg(windows)]`, so on Linux they compile to nothing. The definitions here
+//! mirror theirs, down to union accessors and enums being plain integers, so code that builds
+//! against them also builds against the real crates. Add items here as the crate starts using
+//! them.
+#![allow(non_camel_case_types, non_snake_case, non_upper_case_globals, overflowing_literals, clippy::upper_case_acronyms)]
+
+pub mod winapi {
+    pub mod ctypes {
+        #[repr(u8)]
+        pub enum c_void {
+            #[doc(hidden)]
+            __variant1,
+            #[doc(hidden)]
+            __variant2,
+        }
+    }
+
+    pub mod shared {
+        pub mod basetsd {
+            pub type ULONG_PTR = usize;
+            pub type SIZE_T = ULONG_PTR;
+            pub type PSIZE_T = *mut SIZE_T;
+        }
+
+        pub mod guiddef {
+            #[repr(C)]
+            #[derive(Copy, Clone, Debug)]
+            pub struct GUID {
+                pub Data1: u32,
+                pub Data2: u16,
+                pub Data3: u16,
+                pub Data4: [u8; 8],
+            }
+        }
+
+        pub mod ntdef {
+            use super::super::ctypes::c_void;
+
+            pub type CHAR = i8;
+            pub type UCHAR = u8;
+            pub type USHORT = u16;
+            pub type LONG = i32;
+            pub type ULONG = u32;
+            pub type PULONG = *mut ULONG;
+            pub type LONGLONG = i64;
+            pub type ULONGLONG = u64;
+            pub type WCHAR = u16;
+            pub type PWCH = *mut WCHAR;
+            pub type PCHAR = *mut CHAR;
+            pub type PSTR = *mut CHAR;
+            pub type PVOID = *mut c_void;
+            pub type HANDLE = *mut c_void;
+            pub type NTSTATUS = LONG;
+            pub type BOOLEAN = UCHAR;
+            pub const FALSE: BOOLEAN = 0;
+            pub const TRUE: BOOLEAN = 1;
+
+            #[repr(C)]
+            #[derive(Copy, Clone, Debug)]
+            pub struct LIST_ENTRY {
+                pub Flink: *mut LIST_ENTRY,
+                pub Blink: *mut LIST_ENTRY,
+            }
+            pub type PLIST_ENTRY = *mut LIST_ENTRY;
+
+            #[repr(C)]
+            #[derive(Copy, Clone, Debug)]
+            pub struct UNICODE_STRING {
+                pub Length: USHORT,
+                pub MaximumLength: USHORT,
+                pub Buffer: PWCH,
+            }
+            pub type PUNICODE_STRING = *mut UNICODE_STRING;
+
+            #[repr(C)]
+            #[derive(Copy, Clone, Debug)]
+            pub struct STRING {
+                pub Length: USHORT,
+                pub MaximumLength: USHORT,
+                pub Buffer: PCHAR,
+            }
+            pub type ANSI_STRING = STRING;
+            pub type PANSI_STRING = *mut STRING;
+
+            /// A union in `winapi`, which only exposes its members through accessors.
+            #[repr(C)]
+            #[derive(Copy, Clone)]
+            pub struct LARGE_INTEGER([i64; 1]);
+
+            impl LARGE_INTEGER {
+                pub unsafe fn QuadPart(&self) -> &LONGLONG {
+                    &self.0[0]
+                }
+
+                pub unsafe fn QuadPart_mut(&mut self) -> &mut LONGLONG {
+                    &mut self.0[0]
+                }
+            }
+        }
+
+        pub mod ntstatus {
+            use super::ntdef::NTSTATUS;
+
+            pub const STATUS_ABANDONED: NTSTATUS = 0x00000080;
+            pub const STATUS_ACCESS_DENIED: NTSTATUS = 0xC0000022;
+            pub const STATUS_ACCESS_VIOLATION: NTSTATUS = 0xC0000005;
+            pub const STATUS_ADDRESS_ALREADY_EXISTS: NTSTATUS = 0xC000020A;
+            pub const STATUS_ALERTED: NTSTATUS = 0x00000101;
+            pub const STATUS_ALREADY_COMMITTED: NTSTATUS = 0xC0000021;
+            pub const STATUS_ARRAY_BOUNDS_EXCEEDED: NTSTATUS = 0xC000008C;
+            pub const STATUS_BAD_DEVICE_TYPE: NTSTATUS = 0xC00000CB;
+            pub const STATUS_BAD_NETWORK_NAME: NTSTATUS = 0xC00000CC;
+            pub const STATUS_BAD_NETWORK_PATH: NTSTATUS = 0xC00000BE;
+            pub const STATUS_BREAKPOINT: NTSTATUS = 0x80000003;
+            pub const STATUS_BUFFER_OVERFLOW: NTSTATUS = 0x80000005;
+            pub const STATUS_BUFFER_TOO_SMALL: NTSTATUS = 0xC0000023;
+            pub const STATUS_CANCELLED: NTSTATUS = 0xC0000120;
+            pub const STATUS_CANNOT_DELETE: NTSTATUS = 0xC0000121;
+            pub const STATUS_CONFLICTING_ADDRESSES: NTSTATUS = 0xC0000018;
+            pub const STATUS_CONNECTION_ABORTED: NTSTATUS = 0xC0000241;
+            pub const STATUS_CONNECTION_REFUSED: NTSTATUS = 0xC0000236;
+            pub const STATUS_CONNECTION_RESET: NTSTATUS = 0xC000020D;
+            pub const STATUS_CRC_ERROR: NTSTATUS = 0xC000003F;
+            pub const STATUS_DATATYPE_MISALIGNMENT: NTSTATUS = 0x80000002;
+            pub const STATUS_DATA_ERROR: NTSTATUS = 0xC000003E;
+            pub const STATUS_DELETE_PENDING: NTSTATUS = 0xC0000056;
+            pub const STATUS_DEVICE_BUSY: NTSTATUS = 0x80000011;
+            pub const STATUS_DEVICE_DOES_NOT_EXIST: NTSTATUS = 0xC00000C0;
+            pub const STATUS_DEVICE_NOT_READY: NTSTATUS = 0xC00000A3;
+            pub const STATUS_DEVICE_REMOVED: NTSTATUS = 0xC00002B6;
+            pub const STATUS_DIRECTORY_NOT_EMPTY: NTSTATUS = 0xC0000101;
+            pub const STATUS_DISK_FULL: NTSTATUS = 0xC000007F;
+            pub const STATUS_DLL_NOT_FOUND: NTSTATUS = 0xC0000135;
+            pub const STATUS_DRIVER_UNABLE_TO_LOAD: NTSTATUS = 0xC000026C;
+            pub const STATUS_END_OF_FILE: NTSTATUS = 0xC0000011;
+            pub const STATUS_ENTRYPOINT_NOT_FOUND: NTSTATUS = 0xC0000139;
+            pub const STATUS_FILE_CLOSED: NTSTATUS = 0xC0000128;
+            pub const STATUS_FILE_DELETED: NTSTATUS = 0xC0000123;
+            pub const STATUS_FILE_INVALID: NTSTATUS = 0xC0000098;
+            pub const STATUS_FILE_IS_A_DIRECTORY: NTSTATUS = 0xC00000BA;
+            pub const STATUS_FILE_LOCK_CONFLICT: NTSTATUS = 0xC0000054;
+            pub const STATUS_GUARD_PAGE_VIOLATION: NTSTATUS = 0x80000001;
+            pub const STATUS_HANDLE_NOT_CLOSABLE: NTSTATUS = 0xC0000235;
+            pub const STATUS_HOST_UNREACHABLE: NTSTATUS = 0xC000023D;
+            pub const STATUS_ILLEGAL_FUNCTION: NTSTATUS = 0xC00000AF;
+            pub const STATUS_ILLEGAL_INSTRUCTION: NTSTATUS = 0xC000001D;
+            pub const STATUS_IMAGE_ALREADY_LOADED: NTSTATUS = 0xC000010E;
+            pub const STATUS_IMAGE_NOT_AT_BASE: NTSTATUS = 0x40000003;
+            pub const STATUS_INFO_LENGTH_MISMATCH: NTSTATUS = 0xC0000004;
+            pub const STATUS_INSTANCE_NOT_AVAILABLE: NTSTATUS = 0xC00000AB;
+            pub const STATUS_INSUFFICIENT_RESOURCES: NTSTATUS = 0xC000009A;
+            pub const STATUS_INTEGER_DIVIDE_BY_ZERO: NTSTATUS = 0xC0000094;
+            pub const STATUS_INTEGER_OVERFLOW: NTSTATUS = 0xC0000095;
+            pub const STATUS_INTERNAL_ERROR: NTSTATUS = 0xC00000E5;
+            pub const STATUS_INVALID_ADDRESS: NTSTATUS = 0xC0000141;
+            pub const STATUS_INVALID_BUFFER_SIZE: NTSTATUS = 0xC0000206;
+            pub const STATUS_INVALID_CID: NTSTATUS = 0xC000000B;
+            pub const STATUS_INVALID_DEVICE_REQUEST: NTSTATUS = 0xC0000010;
+            pub const STATUS_INVALID_DEVICE_STATE: NTSTATUS = 0xC0000184;
+            pub const STATUS_INVALID_HANDLE: NTSTATUS = 0xC0000008;
+            pub const STATUS_INVALID_IMAGE_FORMAT: NTSTATUS = 0xC000007B;
+            pub const STATUS_INVALID_IMAGE_HASH: NTSTATUS = 0xC0000428;
+            pub const STATUS_INVALID_INFO_CLASS: NTSTATUS = 0xC0000003;
+            pub const STATUS_INVALID_LEVEL: NTSTATUS = 0xC0000148;
+            pub const STATUS_INVALID_PAGE_PROTECTION: NTSTATUS = 0xC0000045;
+            pub const STATUS_INVALID_PARAMETER: NTSTATUS = 0xC000000D;
+            pub const STATUS_INVALID_PARAMETER_1: NTSTATUS = 0xC00000EF;
+            pub const STATUS_INVALID_PARAMETER_2: NTSTATUS = 0xC00000F0;
+            pub const STATUS_INVALID_PARAMETER_3: NTSTATUS = 0xC00000F1;
+            pub const STATUS_INVALID_SYSTEM_SERVICE: NTSTATUS = 0xC000001C;
+            pub const STATUS_INVALID_USER_BUFFER: NTSTATUS = 0xC00000E8;
+            pub const STATUS_INVALID_VIEW_SIZE: NTSTATUS = 0xC000001F;
+            pub const STATUS_IN_PAGE_ERROR: NTSTATUS = 0xC0000006;
+            pub const STATUS_IO_DEVICE_ERROR: NTSTATUS = 0xC0000185;
+            pub const STATUS_IO_REPARSE_TAG_NOT_HANDLED: NTSTATUS = 0xC0000279;
+            pub const STATUS_IO_TIMEOUT: NTSTATUS = 0xC00000B5;
+            pub const STATUS_LOCK_NOT_GRANTED: NTSTATUS = 0xC0000055;
+            pub const STATUS_LOGON_FAILURE: NTSTATUS = 0xC000006D;
+            pub const STATUS_MEDIA_WRITE_PROTECTED: NTSTATUS = 0xC00000A2;
+            pub const STATUS_MEMORY_NOT_ALLOCATED: NTSTATUS = 0xC00000A0;
+            pub const STATUS_MORE_ENTRIES: NTSTATUS = 0x00000105;
+            pub const STATUS_MUTANT_LIMIT_EXCEEDED: NTSTATUS = 0xC0000191;
+            pub const STATUS_MUTANT_NOT_OWNED: NTSTATUS = 0xC0000046;
+            pub const STATUS_NAME_TOO_LONG: NTSTATUS = 0xC0000106;
+            pub const STATUS_NETWORK_ACCESS_DENIED: NTSTATUS = 0xC00000CA;
+            pub const STATUS_NETWORK_UNREACHABLE: NTSTATUS = 0xC000023C;
+            pub const STATUS_NOINTERFACE: NTSTATUS = 0xC00002B9;
+            pub const STATUS_NONCONTINUABLE_EXCEPTION: NTSTATUS = 0xC0000025;
+            pub const STATUS_NOT_ALL_ASSIGNED: NTSTATUS = 0x00000106;
+            pub const STATUS_NOT_A_DIRECTORY: NTSTATUS = 0xC0000103;
+            pub const STATUS_NOT_A_REPARSE_POINT: NTSTATUS = 0xC0000275;
+            pub const STATUS_NOT_COMMITTED: NTSTATUS = 0xC000002D;
+            pub const STATUS_NOT_FOUND: NTSTATUS = 0xC0000225;
+            pub const STATUS_NOT_IMPLEMENTED: NTSTATUS = 0xC0000002;
+            pub const STATUS_NOT_LOCKED: NTSTATUS = 0xC000002A;
+            pub const STATUS_NOT_MAPPED_VIEW: NTSTATUS = 0xC0000019;
+            pub const STATUS_NOT_SAME_DEVICE: NTSTATUS = 0xC00000D4;
+            pub const STATUS_NOT_SUPPORTED: NTSTATUS = 0xC00000BB;
+            pub const STATUS_NO_MEMORY: NTSTATUS = 0xC0000017;
+            pub const STATUS_NO_MORE_ENTRIES: NTSTATUS = 0x8000001A;
+            pub const STATUS_NO_MORE_FILES: NTSTATUS = 0x80000006;
+            pub const STATUS_NO_SUCH_DEVICE: NTSTATUS = 0xC000000E;
+            pub const STATUS_NO_SUCH_FILE: NTSTATUS = 0xC000000F;
+            pub const STATUS_NO_SUCH_PRIVILEGE: NTSTATUS = 0xC0000060;
+            pub const STATUS_NO_TOKEN: NTSTATUS = 0xC000007C;
+            pub const STATUS_OBJECT_NAME_COLLISION: NTSTATUS = 0xC0000035;
+            pub const STATUS_OBJECT_NAME_EXISTS: NTSTATUS = 0x40000000;
+            pub const STATUS_OBJECT_NAME_INVALID: NTSTATUS = 0xC0000033;
+            pub const STATUS_OBJECT_NAME_NOT_FOUND: NTSTATUS = 0xC0000034;
+            pub const STATUS_OBJECT_PATH_INVALID: NTSTATUS = 0xC0000039;
+            pub const STATUS_OBJECT_PATH_NOT_FOUND: NTSTATUS = 0xC000003A;
+            pub const STATUS_OBJECT_PATH_SYNTAX_BAD: NTSTATUS = 0xC000003B;
+            pub const STATUS_OBJECT_TYPE_MISMATCH: NTSTATUS = 0xC0000024;
+            pub const STATUS_PARTIAL_COPY: NTSTATUS = 0x8000000D;
+            pub const STATUS_PENDING: NTSTATUS = 0x00000103;
+            pub const STATUS_PIPE_BROKEN: NTSTATUS = 0xC000014B;
+            pub const STATUS_PIPE_BUSY: NTSTATUS = 0xC00000AE;
+            pub const STATUS_PORT_DISCONNECTED: NTSTATUS = 0xC0000037;
+            pub const STATUS_POSSIBLE_DEADLOCK: NTSTATUS = 0xC0000194;
+            pub const STATUS_PRIVILEGED_INSTRUCTION: NTSTATUS = 0xC0000096;
+            pub const STATUS_PRIVILEGE_NOT_HELD: NTSTATUS = 0xC0000061;
+            pub const STATUS_PROCEDURE_NOT_FOUND: NTSTATUS = 0xC000007A;
+            pub const STATUS_PROCESS_IS_PROTECTED: NTSTATUS = 0xC0000712;
+            pub const STATUS_PROCESS_IS_TERMINATING: NTSTATUS = 0xC000010A;
+            pub const STATUS_QUOTA_EXCEEDED: NTSTATUS = 0xC0000044;
+            pub const STATUS_RANGE_NOT_LOCKED: NTSTATUS = 0xC000007E;
+            pub const STATUS_REPARSE: NTSTATUS = 0x00000104;
+            pub const STATUS_REQUEST_NOT_ACCEPTED: NTSTATUS = 0xC00000D0;
+            pub const STATUS_RETRY: NTSTATUS = 0xC000022D;
+            pub const STATUS_SECTION_NOT_IMAGE: NTSTATUS = 0xC0000049;
+            pub const STATUS_SECTION_TOO_BIG: NTSTATUS = 0xC0000040;
+            pub const STATUS_SEMAPHORE_LIMIT_EXCEEDED: NTSTATUS = 0xC0000047;
+            pub const STATUS_SHARING_VIOLATION: NTSTATUS = 0xC0000043;
+            pub const STATUS_SINGLE_STEP: NTSTATUS = 0x80000004;
+            pub const STATUS_SOME_NOT_MAPPED: NTSTATUS = 0x00000107;
+            pub const STATUS_STACK_OVERFLOW: NTSTATUS = 0xC00000FD;
+            pub const STATUS_SUCCESS: NTSTATUS = 0x00000000;
+            pub const STATUS_SUSPEND_COUNT_EXCEEDED: NTSTATUS = 0xC000004A;
+            pub const STATUS_SYNCHRONIZATION_REQUIRED: NTSTATUS = 0xC0000134;
+            pub const STATUS_THREAD_IS_TERMINATING: NTSTATUS = 0xC000004B;
+            pub const STATUS_TIMEOUT: NTSTATUS = 0x00000102;
+            pub const STATUS_TOO_MANY_LINKS: NTSTATUS = 0xC0000265;
+            pub const STATUS_TOO_MANY_OPENED_FILES: NTSTATUS = 0xC000011F;
+            pub const STATUS_UNABLE_TO_FREE_VM: NTSTATUS = 0xC000001A;
+            pub const STATUS_UNEXPECTED_IO_ERROR: NTSTATUS = 0xC00000E9;
+            pub const STATUS_UNMAPPABLE_CHARACTER: NTSTATUS = 0xC0000162;
+            pub const STATUS_UNSUCCESSFUL: NTSTATUS = 0xC0000001;
+            pub const STATUS_USER_APC: NTSTATUS = 0x000000C0;
+            pub const STATUS_WORKING_SET_QUOTA: NTSTATUS = 0xC00000A1;
+            pub const STATUS_WRONG_PASSWORD: NTSTATUS = 0xC000006A;
+        }
+    }
+
+    pub mod um {
+        pub mod winnt {
+            pub type FIRMWARE_TYPE = u32;
+            pub const FirmwareTypeUnknown: FIRMWARE_TYPE = 0;
+            pub const FirmwareTypeBios: FIRMWARE_TYPE = 1;
+            pub const FirmwareTypeUefi: FIRMWARE_TYPE = 2;
+
+            pub const PAGE_READWRITE: u32 = 0x04;
+        }
+    }
+}
+
+pub mod ntapi {
+    use super::winapi::shared::basetsd::{SIZE_T, ULONG_PTR};
+    use super::winapi::shared::guiddef::GUID;
+    use super::winapi::shared::ntdef::*;
+    use super::winapi::um::winnt::FIRMWARE_TYPE;
+
+    /// `ntapi` structures are zero-initialized by `Default` (its `impl-default` feature).
+    macro_rules! impl_zeroed_default {
+        ($($ty:ty),* $(,)?) => {
+            $(impl Default for $ty {
+                fn default() -> Self {
+                    unsafe { core::mem::zeroed() }
+                }
+            })*
+        };
+    }
+
+    pub mod ntapi_base {
+        use super::*;
+
+        pub type KPRIORITY = LONG;
+
+        #[repr(C)]
+        #[derive(Copy, Clone)]
+        pub struct CLIENT_ID {
+            pub UniqueProcess: HANDLE,
+            pub UniqueThread: HANDLE,
+        }
+
+        impl_zeroed_default!(CLIENT_ID);
+    }
+
+    pub mod ntkeapi {
+        pub type KTHREAD_STATE = u32;
+        pub const Initialized: KTHREAD_STATE = 0;
+        pub const Ready: KTHREAD_STATE = 1;
+        pub const Running: KTHREAD_STATE = 2;
+        pub const Standby: KTHREAD_STATE = 3;
+        pub const Terminated: KTHREAD_STATE = 4;
+        pub const Waiting: KTHREAD_STATE = 5;
+        pub const Transition: KTHREAD_STATE = 6;
+        pub const DeferredReady: KTHREAD_STATE = 7;
+        pub const GateWaitObsolete: KTHREAD_STATE = 8;
+        pub const WaitingForProcessInSwap: KTHREAD_STATE = 9;
+
+        pub type KWAIT_REASON = u32;
+        pub const Executive: KWAIT_REASON = 0;
+        pub const UserRequest: KWAIT_REASON = 6;
+        pub const WrQueue: KWAIT_REASON = 15;
+    }
+
+    pub mod ntexapi {
+        use super::*;
+        use super::ntapi_base::{CLIENT_ID, KPRIORITY};
+        use super::ntkeapi::{KTHREAD_STATE, KWAIT_REASON};
+
+        pub type SYSTEM_INFORMATION_CLASS = u32;
+        pub const SystemProcessInformation: SYSTEM_INFORMATION_CLASS = 5;
+        pub const SystemModuleInformation: SYSTEM_INFORMATION_CLASS = 11;
+        pub const SystemKernelDebuggerInformation: SYSTEM_INFORMATION_CLASS = 35;
+        pub const SystemExtendedHandleInformation: SYSTEM_INFORMATION_CLASS = 64;
+        pub const SystemBigPoolInformation: SYSTEM_INFORMATION_CLASS = 66;
+        pub const SystemBootEnvironmentInformation: SYSTEM_INFORMATION_CLASS = 90;
+        pub const SystemCodeIntegrityInformation: SYSTEM_INFORMATION_CLASS = 103;
+
+        #[repr(C)]
+        #[derive(Copy, Clone)]
+        pub struct SYSTEM_THREAD_INFORMATION {
+            pub KernelTime: LARGE_INTEGER,
+            pub UserTime: LARGE_INTEGER,
+            pub CreateTime: LARGE_INTEGER,
+            pub WaitTime: ULONG,
+            pub StartAddress: PVOID,
+            pub ClientId: CLIENT_ID,
+            pub Priority: KPRIORITY,
+            pub BasePriority: LONG,
+            pub ContextSwitches: ULONG,
+            pub ThreadState: KTHREAD_STATE,
+            pub WaitReason: KWAIT_REASON,
+        }
+
+        #[repr(C)]
+        #[derive(Copy, Clone)]
+        pub struct SYSTEM_PROCESS_INFORMATION {
+            pub NextEntryOffset: ULONG,
+            pub NumberOfThreads: ULONG,
+            pub WorkingSetPrivateSize: LARGE_INTEGER,
+            pub HardFaultCount: ULONG,
+            pub NumberOfThreadsHighWatermark: ULONG,
+            pub CycleTime: ULONGLONG,
+            pub CreateTime: LARGE_INTEGER,
+            pub UserTime: LARGE_INTEGER,
+            pub KernelTime: LARGE_INTEGER,
+            pub ImageName: UNICODE_STRING,
+            pub BasePriority: KPRIORITY,
+            pub UniqueProcessId: HANDLE,
+            pub InheritedFromUniqueProcessId: HANDLE,
+            pub HandleCount: ULONG,
+            pub SessionId: ULONG,
+            pub UniqueProcessKey: ULONG_PTR,
+            pub PeakVirtualSize: SIZE_T,
+            pub VirtualSize: SIZE_T,
+            pub PageFaultCount: ULONG,
+            pub PeakWorkingSetSize: SIZE_T,
+            pub WorkingSetSize: SIZE_T,
+            pub QuotaPeakPagedPoolUsage: SIZE_T,
+            pub QuotaPagedPoolUsage: SIZE_T,
+            pub QuotaPeakNonPagedPoolUsage: SIZE_T,
+            pub QuotaNonPagedPoolUsage: SIZE_T,
+            pub PagefileUsage: SIZE_T,
+            pub PeakPagefileUsage: SIZE_T,
+            pub PrivatePageCount: SIZE_T,
+            pub ReadOperationCount: LARGE_INTEGER,
+            pub WriteOperationCount: LARGE_INTEGER,
+            pub OtherOperationCount: LARGE_INTEGER,
+            pub ReadTransferCount: LARGE_INTEGER,
+            pub WriteTransferCount: LARGE_INTEGER,
+            pub OtherTransferCount: LARGE_INTEGER,
+            pub Threads: [SYSTEM_THREAD_INFORMATION; 1],
+        }
+
+        #[repr(C)]
+        #[derive(Copy, Clone)]
+        pub struct SYSTEM_KERNEL_DEBUGGER_INFORMATION {
+            pub KernelDebuggerEnabled: BOOLEAN,
+            pub KernelDebuggerNotPresent: BOOLEAN,
+        }
+
+        #[repr(C)]
+        #[derive(Copy, Clone)]
+        pub struct SYSTEM_HANDLE_TABLE_ENTRY_INFO_EX {
+            pub Object: PVOID,
+            pub UniqueProcessId: ULONG_PTR,
+            pub HandleValue: ULONG_PTR,
+            pub GrantedAccess: ULONG,
+            pub CreatorBackTraceIndex: USHORT,
+            pub ObjectTypeIndex: USHORT,
+            pub HandleAttributes: ULONG,
+            pub Reserved: ULONG,
+        }
+
+        #[repr(C)]
+        #[derive(Copy, Clone)]
+        pub struct SYSTEM_HANDLE_INFORMATION_EX {
+            pub NumberOfHandles: ULONG_PTR,
+            pub Reserved: ULONG_PTR,
+            pub Handles: [SYSTEM_HANDLE_TABLE_ENTRY_INFO_EX; 1],
+        }
+
+        #[repr(C)]
+        #[derive(Copy, Clone)]
+        pub union SYSTEM_BIGPOOL_ENTRY_u1 {
+            pub VirtualAddress: PVOID,
+            pub Bitfields: ULONG_PTR,
+        }
+
+        impl SYSTEM_BIGPOOL_ENTRY_u1 {
+            pub unsafe fn NonPaged(&self) -> ULONG_PTR {
+                self.Bitfields & 1
+            }
+
+            pub unsafe fn set_NonPaged(&mut self, val: ULONG_PTR) {
+                self.Bitfields = (self.Bitfields & !1) | (val & 1);
+            }
+        }
+
+        #[repr(C)]
+        #[derive(Copy, Clone)]
+        pub union SYSTEM_BIGPOOL_ENTRY_u2 {
+            pub Tag: [UCHAR; 4],
+            pub TagUlong: ULONG,
+        }
+
+        #[repr(C)]
+        #[derive(Copy, Clone)]
+        pub struct SYSTEM_BIGPOOL_ENTRY {
+            pub u1: SYSTEM_BIGPOOL_ENTRY_u1,
+            pub SizeInBytes: SIZE_T,
+            pub u2: SYSTEM_BIGPOOL_ENTRY_u2,
+        }
+
+        #[repr(C)]
+        #[derive(Copy, Clone)]
+        pub struct SYSTEM_BIGPOOL_INFORMATION {
+            pub Count: ULONG,
+            pub AllocatedInfo: [SYSTEM_BIGPOOL_ENTRY; 1],
+        }
+
+        #[repr(C)]
+        #[derive(Copy, Clone)]
+        pub struct SYSTEM_BOOT_ENVIRONMENT_INFORMATION {
+            pub BootIdentifier: GUID,
+            pub FirmwareType: FIRMWARE_TYPE,
+            pub BootFlags: ULONGLONG,
+        }
+
+        #[repr(C)]
+        #[derive(Copy, Clone)]
+        pub struct SYSTEM_CODEINTEGRITY_INFORMATION {
+            pub Length: ULONG,
+            pub CodeIntegrityOptions: ULONG,
+        }
+
+        impl_zeroed_default!(
+            SYSTEM_THREAD_INFORMATION,
+            SYSTEM_PROCESS_INFORMATION,
+            SYSTEM_KERNEL_DEBUGGER_INFORMATION,
+            SYSTEM_HANDLE_TABLE_ENTRY_INFO_EX,
+            SYSTEM_HANDLE_INFORMATION_EX,
+            SYSTEM_BIGPOOL_ENTRY,
+            SYSTEM_BIGPOOL_INFORMATION,
+            SYSTEM_BOOT_ENVIRONMENT_INFORMATION,
+            SYSTEM_CODEINTEGRITY_INFORMATION,
+        );
+    }
+
+    pub mod ntldr {
+        use super::*;
+
+        #[repr(C)]
+        #[derive(Copy, Clone)]
+        pub struct RTL_PROCESS_MODULE_INFORMATION {
+            pub Section: HANDLE,
+            pub MappedBase: PVOID,
+            pub ImageBase: PVOID,
+            pub ImageSize: ULONG,
+            pub Flags: ULONG,
+            pub LoadOrderIndex: USHORT,
+            pub InitOrderIndex: USHORT,
+            pub LoadCount: USHORT,
+            pub OffsetToFileName: USHORT,
+            pub FullPathName: [UCHAR; 256],
+        }
+
+        #[repr(C)]
+        #[derive(Copy, Clone)]
+        pub struct RTL_PROCESS_MODULES {
+            pub NumberOfModules: ULONG,
+            pub Modules: [RTL_PROCESS_MODULE_INFORMATION; 1],
+        }
+
+        impl_zeroed_default!(RTL_PROCESS_MODULE_INFORMATION, RTL_PROCESS_MODULES);
+    }
+
+    pub mod ntobapi {
+        use super::*;
+
+        #[repr(C)]
+        #[derive(Copy, Clone)]
+        pub struct OBJECT_NAME_INFORMATION {
+            pub Name: UNICODE_STRING,
+        }
+        pub type POBJECT_NAME_INFORMATION = *mut OBJECT_NAME_INFORMATION;
+
+        impl_zeroed_default!(OBJECT_NAME_INFORMATION);
+    }
+
+    pub mod ntpebteb {
+        /// Only ever handled by pointer; read remote PEBs through [`crate::memory`].
+        #[repr(C)]
+        pub struct PEB {
+            _opaque: [u8; 0],
+        }
+        pub type PPEB = *mut PEB;
+    }
+}
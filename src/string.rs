@@ -1,8 +1,134 @@
-use alloc::prelude::v1::*;
+use alloc::{string::String, vec::Vec};
 use alloc::string::FromUtf16Error;
-use core::fmt::Debug;
-use crate::basedef::ntapi::_core::fmt::Formatter;
-use crate::basedef::UNICODE_STRING;
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::hash::{Hash, Hasher};
+use core::fmt::{self, Debug, Display, Write};
+use core::ops::Deref;
+use core::fmt::Formatter;
+use crate::basedef::{ntstatus, ANSI_STRING, UNICODE_STRING};
+use crate::ntstatus::NtStatus;
+
+/// The most UTF-16 code units a counted string can hold, since its byte length is a `u16`.
+pub const UNICODE_STRING_MAX_CHARS: usize = u16::MAX as usize / 2;
+
+/// Builds a `'static` [`UnicodeString`] from a string literal at compile time.
+///
+/// ```ignore
+/// const SOFTWARE: UnicodeString = unicode_str!("\\Registry\\Machine\\Software");
+/// ```
+///
+/// The buffer is null-terminated, but the terminator is only counted in `maximum_length`.
+#[macro_export]
+macro_rules! unicode_str {
+    ($s:expr) => {
+        $crate::string::UnicodeString::from_static($crate::wide_str!($s))
+    };
+}
+
+/// Encodes a string literal as a null-terminated `&'static [u16]` at compile time.
+#[macro_export]
+macro_rules! wide_str {
+    ($s:expr) => {{
+        const WIDE: &[u16] = &$crate::string::encode_utf16::<{ $crate::string::utf16_len($s) + 1 }>($s);
+        WIDE
+    }};
+}
+
+/// The number of UTF-16 code units needed to encode `s`. Used by [`wide_str!`].
+#[doc(hidden)]
+pub const fn utf16_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    let mut len = 0;
+    while i < bytes.len() {
+        let (c, width) = decode_utf8(bytes, i);
+        len += if c >= 0x10000 { 2 } else { 1 };
+        i += width;
+    }
+    len
+}
+
+/// Encodes `s` as UTF-16 into an array of exactly `N` code units, padding with nulls. Used by
+/// [`wide_str!`].
+#[doc(hidden)]
+pub const fn encode_utf16<const N: usize>(s: &str) -> [u16; N] {
+    let bytes = s.as_bytes();
+    let mut out = [0u16; N];
+    let mut i = 0;
+    let mut len = 0;
+    while i < bytes.len() {
+        let (c, width) = decode_utf8(bytes, i);
+        if c >= 0x10000 {
+            let c = c - 0x10000;
+            out[len] = 0xD800 | (c >> 10) as u16;
+            out[len + 1] = 0xDC00 | (c & 0x3FF) as u16;
+            len += 2;
+        } else {
+            out[len] = c as u16;
+            len += 1;
+        }
+        i += width;
+    }
+    out
+}
+
+/// Decodes the character starting at `bytes[i]`, which must be valid UTF-8, returning it and
+/// its width in bytes.
+const fn decode_utf8(bytes: &[u8], i: usize) -> (u32, usize) {
+    let b = bytes[i] as u32;
+    if b < 0x80 {
+        (b, 1)
+    } else if b < 0xE0 {
+        (((b & 0x1F) << 6) | (bytes[i + 1] as u32 & 0x3F), 2)
+    } else if b < 0xF0 {
+        (((b & 0x0F) << 12) | ((bytes[i + 1] as u32 & 0x3F) << 6) | (bytes[i + 2] as u32 & 0x3F), 3)
+    } else {
+        let c = ((b & 0x07) << 18)
+            | ((bytes[i + 1] as u32 & 0x3F) << 12)
+            | ((bytes[i + 2] as u32 & 0x3F) << 6)
+            | (bytes[i + 3] as u32 & 0x3F);
+        (c, 4)
+    }
+}
+
+/// Upcases a UTF-16 code unit like `RtlUpcaseUnicodeChar`.
+///
+/// Each code unit is mapped on its own, as NT does: surrogates, and characters whose uppercase
+/// form is not a single BMP character (such as `ß`), are returned unchanged.
+pub fn upcase(c: u16) -> u16 {
+    if c < 0x80 {
+        return (c as u8).to_ascii_uppercase() as u16;
+    }
+
+    let c = match char::from_u32(c as u32) {
+        Some(ch) => ch,
+        None => return c,
+    };
+
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(u), None) if (u as u32) <= 0xFFFF => u as u16,
+        _ => c as u16,
+    }
+}
+
+fn eq_ignore_case(a: &[u16], b: &[u16]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(&x, &y)| x == y || upcase(x) == upcase(y))
+}
+
+fn find(haystack: &[u16], needle: &[u16], ignore_case: bool) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    haystack.windows(needle.len()).position(|window| {
+        if ignore_case {
+            eq_ignore_case(window, needle)
+        } else {
+            window == needle
+        }
+    })
+}
 
 /// A counted Unicode string.
 #[repr(C)]
@@ -23,15 +149,159 @@ impl UnicodeString {
     }
 
     pub unsafe fn as_slice(&self) -> &[u16] {
+        if self.buffer.is_null() {
+            return &[];
+        }
         core::slice::from_raw_parts(self.buffer, (self.length / 2) as _)
     }
 
-    pub fn from_slice(slice: &[u16]) -> Self {
-        Self {
-            length: slice.len() as _,
-            maximum_length: slice.len() as _,
-            buffer: slice.as_ptr(),
+    /// Describes a null-terminated `'static` buffer, such as one produced by [`wide_str!`]. The
+    /// terminator is excluded from `length`.
+    pub const fn from_static(wide: &'static [u16]) -> Self {
+        assert!(!wide.is_empty() && wide[wide.len() - 1] == 0, "string must be null-terminated");
+        assert!(wide.len() - 1 <= UNICODE_STRING_MAX_CHARS, "string too long for a UNICODE_STRING");
+
+        let maximum_length = if wide.len() > UNICODE_STRING_MAX_CHARS { UNICODE_STRING_MAX_CHARS } else { wide.len() };
+        Self { length: ((wide.len() - 1) * 2) as u16, maximum_length: (maximum_length * 2) as u16, buffer: wide.as_ptr() }
+    }
+
+    /// An empty string with a null buffer.
+    pub const fn empty() -> Self {
+        Self { length: 0, maximum_length: 0, buffer: core::ptr::null() }
+    }
+
+    /// Describes `slice`, which must outlive the returned value. Fails with
+    /// `STATUS_NAME_TOO_LONG` if it has more than [`UNICODE_STRING_MAX_CHARS`] code units.
+    pub fn from_slice(slice: &[u16]) -> Result<Self, NtStatus> {
+        if slice.len() > UNICODE_STRING_MAX_CHARS {
+            return Err(NtStatus(ntstatus::STATUS_NAME_TOO_LONG));
+        }
+
+        let length = (slice.len() * 2) as u16;
+        Ok(Self { length, maximum_length: length, buffer: slice.as_ptr() })
+    }
+
+    /// Encodes `s` as UTF-16 into `buf` and describes the result, with `maximum_length` covering
+    /// all of `buf` (up to the limit of a counted string). Fails with `STATUS_BUFFER_TOO_SMALL`
+    /// if the encoded string doesn't fit, or `STATUS_NAME_TOO_LONG` if no counted string could
+    /// hold it.
+    pub fn from_str(s: &str, buf: &mut [u16]) -> Result<Self, NtStatus> {
+        let mut len = 0;
+        for unit in s.encode_utf16() {
+            if len == UNICODE_STRING_MAX_CHARS {
+                return Err(NtStatus(ntstatus::STATUS_NAME_TOO_LONG));
+            }
+            match buf.get_mut(len) {
+                Some(slot) => *slot = unit,
+                None => return Err(NtStatus(ntstatus::STATUS_BUFFER_TOO_SMALL)),
+            }
+            len += 1;
         }
+
+        Ok(Self {
+            length: (len * 2) as u16,
+            maximum_length: (buf.len().min(UNICODE_STRING_MAX_CHARS) * 2) as u16,
+            buffer: buf.as_ptr(),
+        })
+    }
+
+    /// A pointer to pass as a `PUNICODE_STRING`.
+    pub fn as_ptr(&self) -> *const UNICODE_STRING {
+        self as *const Self as *const UNICODE_STRING
+    }
+
+    fn units(&self) -> &[u16] {
+        unsafe { self.as_slice() }
+    }
+
+    /// Compares two strings like `RtlEqualUnicodeString` with `CaseInSensitive` set.
+    pub fn eq_ignore_case(&self, other: &UnicodeString) -> bool {
+        eq_ignore_case(self.units(), other.units())
+    }
+
+    /// Orders two strings like `RtlCompareUnicodeString` with `CaseInSensitive` set.
+    pub fn cmp_ignore_case(&self, other: &UnicodeString) -> Ordering {
+        self.units().iter().map(|&c| upcase(c)).cmp(other.units().iter().map(|&c| upcase(c)))
+    }
+
+    pub fn starts_with(&self, prefix: &UnicodeString) -> bool {
+        self.units().starts_with(prefix.units())
+    }
+
+    /// Like `RtlPrefixUnicodeString` with `CaseInSensitive` set.
+    pub fn starts_with_ignore_case(&self, prefix: &UnicodeString) -> bool {
+        let (units, prefix) = (self.units(), prefix.units());
+        units.len() >= prefix.len() && eq_ignore_case(&units[..prefix.len()], prefix)
+    }
+
+    pub fn ends_with(&self, suffix: &UnicodeString) -> bool {
+        self.units().ends_with(suffix.units())
+    }
+
+    pub fn ends_with_ignore_case(&self, suffix: &UnicodeString) -> bool {
+        let (units, suffix) = (self.units(), suffix.units());
+        units.len() >= suffix.len() && eq_ignore_case(&units[units.len() - suffix.len()..], suffix)
+    }
+
+    /// Returns the index, in UTF-16 code units, of the first occurrence of `needle`.
+    pub fn find(&self, needle: &UnicodeString) -> Option<usize> {
+        find(self.units(), needle.units(), false)
+    }
+
+    pub fn find_ignore_case(&self, needle: &UnicodeString) -> Option<usize> {
+        find(self.units(), needle.units(), true)
+    }
+
+    pub fn contains(&self, needle: &UnicodeString) -> bool {
+        self.find(needle).is_some()
+    }
+
+    pub fn contains_ignore_case(&self, needle: &UnicodeString) -> bool {
+        self.find_ignore_case(needle).is_some()
+    }
+
+    /// Encodes as Latin-1, failing with `STATUS_UNMAPPABLE_CHARACTER` if a code unit is above
+    /// U+00FF. See [`AnsiString`] for why this is not the system code page, and
+    /// [`AnsiString::decode_latin1`] for the inverse.
+    pub fn to_latin1(&self) -> Result<AnsiStringBuf, NtStatus> {
+        let units = unsafe { self.as_slice() };
+        if units.iter().any(|&unit| unit > 0xFF) {
+            return Err(NtStatus(ntstatus::STATUS_UNMAPPABLE_CHARACTER));
+        }
+
+        let mut ansi = AnsiStringBuf::new();
+        ansi.reserve(units.len())?;
+        ansi.buf.extend(units.iter().map(|&unit| unit as u8));
+        ansi.sync();
+        Ok(ansi)
+    }
+}
+
+/// Compares the contents, case-sensitively. Use [`UnicodeString::eq_ignore_case`] for paths
+/// and names.
+impl PartialEq for UnicodeString {
+    fn eq(&self, other: &Self) -> bool {
+        self.units() == other.units()
+    }
+}
+
+impl Eq for UnicodeString {}
+
+impl PartialOrd for UnicodeString {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for UnicodeString {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.units().cmp(other.units())
+    }
+}
+
+impl Hash for UnicodeString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.units().hash(state)
     }
 }
 
@@ -41,8 +311,460 @@ impl From<UNICODE_STRING> for UnicodeString {
     }
 }
 
+impl From<&UNICODE_STRING> for UnicodeString {
+    fn from(s: &UNICODE_STRING) -> Self {
+        Self::from(*s)
+    }
+}
+
+impl From<UnicodeString> for UNICODE_STRING {
+    fn from(s: UnicodeString) -> Self {
+        Self { Length: s.length, MaximumLength: s.maximum_length, Buffer: s.buffer as _ }
+    }
+}
+
 impl Debug for UnicodeString {
-    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{:?}", self.try_to_string())
     }
-}
\ No newline at end of file
+}
+
+/// An owned, growable counted Unicode string.
+///
+/// Derefs to a [`UnicodeString`] whose `buffer` points into the owned storage and whose lengths
+/// are kept in bytes, so it can be passed wherever a `PUNICODE_STRING` is expected. Operations
+/// that would grow the string past [`UNICODE_STRING_MAX_CHARS`] fail with
+/// `STATUS_NAME_TOO_LONG`, and failed allocations with `STATUS_INSUFFICIENT_RESOURCES`.
+pub struct UnicodeStringBuf {
+    buf: Vec<u16>,
+    string: UnicodeString,
+}
+
+impl UnicodeStringBuf {
+    pub fn new() -> Self {
+        let mut this = Self { buf: Vec::new(), string: UnicodeString::empty() };
+        this.sync();
+        this
+    }
+
+    /// An empty string with room for `capacity` UTF-16 code units.
+    pub fn with_capacity(capacity: usize) -> Result<Self, NtStatus> {
+        let mut this = Self::new();
+        this.reserve(capacity)?;
+        Ok(this)
+    }
+
+    /// Encodes `s` as UTF-16.
+    pub fn try_from_str(s: &str) -> Result<Self, NtStatus> {
+        let mut this = Self::new();
+        this.push_str(s)?;
+        Ok(this)
+    }
+
+    /// Copies a slice of UTF-16 code units.
+    pub fn try_from_slice(slice: &[u16]) -> Result<Self, NtStatus> {
+        let mut this = Self::new();
+        this.push_slice(slice)?;
+        Ok(this)
+    }
+
+    /// Decodes `ansi` as Latin-1. See [`AnsiString`] for the mapping.
+    ///
+    /// A `UNICODE_STRING` holds at most 32767 characters, so this fails with
+    /// `STATUS_NAME_TOO_LONG` for longer strings, and otherwise only if allocating fails.
+    pub fn from_latin1(ansi: &AnsiString) -> Result<Self, NtStatus> {
+        let bytes = unsafe { ansi.as_bytes() };
+        let mut this = Self::with_capacity(bytes.len())?;
+        this.buf.extend(bytes.iter().map(|&byte| byte as u16));
+        this.sync();
+        Ok(this)
+    }
+
+    /// Updates the counted string after the storage changed. Until storage is allocated, `buffer`
+    /// is null rather than the dangling pointer of an empty `Vec`.
+    fn sync(&mut self) {
+        let capacity = self.buf.capacity().min(UNICODE_STRING_MAX_CHARS);
+        self.string = UnicodeString {
+            length: (self.buf.len() * 2) as u16,
+            maximum_length: (capacity * 2) as u16,
+            buffer: if capacity == 0 { core::ptr::null() } else { self.buf.as_ptr() },
+        };
+    }
+
+    /// Makes room for `additional` more UTF-16 code units.
+    pub fn reserve(&mut self, additional: usize) -> Result<(), NtStatus> {
+        match self.buf.len().checked_add(additional) {
+            Some(len) if len <= UNICODE_STRING_MAX_CHARS => {}
+            _ => return Err(NtStatus(ntstatus::STATUS_NAME_TOO_LONG)),
+        }
+
+        self.buf
+            .try_reserve(additional)
+            .map_err(|_| NtStatus(ntstatus::STATUS_INSUFFICIENT_RESOURCES))?;
+        self.sync();
+        Ok(())
+    }
+
+    /// Appends UTF-16 code units.
+    pub fn push_slice(&mut self, slice: &[u16]) -> Result<(), NtStatus> {
+        self.reserve(slice.len())?;
+        self.buf.extend_from_slice(slice);
+        self.sync();
+        Ok(())
+    }
+
+    /// Appends `s`, encoded as UTF-16. Nothing is appended if it doesn't fit.
+    pub fn push_str(&mut self, s: &str) -> Result<(), NtStatus> {
+        self.reserve(s.encode_utf16().count())?;
+        self.buf.extend(s.encode_utf16());
+        self.sync();
+        Ok(())
+    }
+
+    pub fn push(&mut self, c: char) -> Result<(), NtStatus> {
+        let mut units = [0u16; 2];
+        self.push_slice(c.encode_utf16(&mut units))
+    }
+
+    /// Appends a path component, inserting a `\` separator unless the string is empty or
+    /// already ends with one. Leading separators of `component` are skipped.
+    pub fn push_component(&mut self, component: &str) -> Result<(), NtStatus> {
+        let component = component.trim_start_matches('\\');
+        let needs_separator = matches!(self.buf.last(), Some(&last) if last != b'\\' as u16);
+
+        let len = component.encode_utf16().count() + needs_separator as usize;
+        self.reserve(len)?;
+        if needs_separator {
+            self.buf.push(b'\\' as u16);
+        }
+        self.buf.extend(component.encode_utf16());
+        self.sync();
+        Ok(())
+    }
+
+    /// Shortens the string to `len` UTF-16 code units.
+    pub fn truncate(&mut self, len: usize) {
+        self.buf.truncate(len);
+        self.sync();
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// The number of UTF-16 code units.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[u16] {
+        &self.buf
+    }
+
+    /// A pointer to pass as a `PUNICODE_STRING`. It is valid until the string is modified or
+    /// dropped.
+    pub fn as_ptr(&self) -> *const UNICODE_STRING {
+        &self.string as *const UnicodeString as *const UNICODE_STRING
+    }
+
+    /// Consumes the string, returning its UTF-16 code units.
+    pub fn into_vec(self) -> Vec<u16> {
+        self.buf
+    }
+}
+
+impl Default for UnicodeStringBuf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for UnicodeStringBuf {
+    type Target = UnicodeString;
+
+    fn deref(&self) -> &UnicodeString {
+        &self.string
+    }
+}
+
+impl PartialEq for UnicodeStringBuf {
+    fn eq(&self, other: &Self) -> bool {
+        self.buf == other.buf
+    }
+}
+
+impl Eq for UnicodeStringBuf {}
+
+impl PartialEq<UnicodeString> for UnicodeStringBuf {
+    fn eq(&self, other: &UnicodeString) -> bool {
+        self.string == *other
+    }
+}
+
+impl Hash for UnicodeStringBuf {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.buf.hash(state)
+    }
+}
+
+impl Clone for UnicodeStringBuf {
+    fn clone(&self) -> Self {
+        let mut this = Self { buf: self.buf.clone(), string: self.string };
+        this.sync();
+        this
+    }
+}
+
+impl TryFrom<&str> for UnicodeStringBuf {
+    type Error = NtStatus;
+
+    fn try_from(s: &str) -> Result<Self, NtStatus> {
+        Self::try_from_str(s)
+    }
+}
+
+impl TryFrom<&[u16]> for UnicodeStringBuf {
+    type Error = NtStatus;
+
+    fn try_from(slice: &[u16]) -> Result<Self, NtStatus> {
+        Self::try_from_slice(slice)
+    }
+}
+
+impl Debug for UnicodeStringBuf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", String::from_utf16_lossy(&self.buf))
+    }
+}
+
+impl Display for UnicodeStringBuf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for c in char::decode_utf16(self.buf.iter().copied()) {
+            f.write_char(c.unwrap_or(char::REPLACEMENT_CHARACTER))?;
+        }
+        Ok(())
+    }
+}
+
+/// A counted 8-bit string, laid out like `ANSI_STRING`.
+///
+/// The kernel stores these in the system code page, which this crate does not decode. The
+/// conversions to and from [`UnicodeString`] are Latin-1 instead: each byte maps to the code
+/// point with the same value, so any byte string survives a round trip, ASCII converts exactly,
+/// and a Unicode string converts only if every code unit is at most U+00FF. Use
+/// `RtlAnsiStringToUnicodeString` for a code page conversion.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct AnsiString {
+    /// The length in bytes of the string stored in `buffer`.
+    pub length: u16,
+    /// The length in bytes of `buffer`.
+    pub maximum_length: u16,
+    pub buffer: *const u8,
+}
+
+impl AnsiString {
+    /// Describes `bytes`, which must outlive the returned value. Fails with
+    /// `STATUS_NAME_TOO_LONG` if it is longer than `u16::MAX` bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, NtStatus> {
+        if bytes.len() > u16::MAX as usize {
+            return Err(NtStatus(ntstatus::STATUS_NAME_TOO_LONG));
+        }
+
+        Ok(Self { length: bytes.len() as u16, maximum_length: bytes.len() as u16, buffer: bytes.as_ptr() })
+    }
+
+    /// Describes the null-terminated string at `ptr`, reading at most `max_len` bytes. The
+    /// terminator is counted in `maximum_length` when it is within `max_len`.
+    pub unsafe fn from_ptr_bounded(ptr: *const u8, max_len: usize) -> Self {
+        let max_len = max_len.min(u16::MAX as usize);
+        let mut len = 0;
+        while len < max_len && *ptr.add(len) != 0 {
+            len += 1;
+        }
+
+        let maximum_length = if len < max_len { len + 1 } else { len };
+        Self { length: len as u16, maximum_length: maximum_length as u16, buffer: ptr }
+    }
+
+    pub const fn empty() -> Self {
+        Self { length: 0, maximum_length: 0, buffer: core::ptr::null() }
+    }
+
+    pub unsafe fn as_bytes(&self) -> &[u8] {
+        if self.buffer.is_null() {
+            return &[];
+        }
+        core::slice::from_raw_parts(self.buffer, self.length as _)
+    }
+
+    /// The string as `&str`, if it is valid UTF-8.
+    pub fn to_str(&self) -> Option<&str> {
+        core::str::from_utf8(unsafe { self.as_bytes() }).ok()
+    }
+
+    /// Decodes as Latin-1 into a new [`UnicodeStringBuf`], the inverse of
+    /// [`UnicodeString::to_latin1`]. Same as [`UnicodeStringBuf::from_latin1`].
+    pub fn decode_latin1(&self) -> Result<UnicodeStringBuf, NtStatus> {
+        UnicodeStringBuf::from_latin1(self)
+    }
+
+    /// A pointer to pass as a `PANSI_STRING`.
+    pub fn as_ptr(&self) -> *const ANSI_STRING {
+        self as *const Self as *const ANSI_STRING
+    }
+}
+
+impl From<ANSI_STRING> for AnsiString {
+    fn from(s: ANSI_STRING) -> Self {
+        Self { length: s.Length, maximum_length: s.MaximumLength, buffer: s.Buffer as _ }
+    }
+}
+
+impl From<AnsiString> for ANSI_STRING {
+    fn from(s: AnsiString) -> Self {
+        Self { Length: s.length, MaximumLength: s.maximum_length, Buffer: s.buffer as _ }
+    }
+}
+
+impl PartialEq for AnsiString {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { self.as_bytes() == other.as_bytes() }
+    }
+}
+
+impl Eq for AnsiString {}
+
+impl Hash for AnsiString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        unsafe { self.as_bytes() }.hash(state)
+    }
+}
+
+/// Formats the string with bytes above 0x7F shown as their Latin-1 characters.
+impl Display for AnsiString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for &byte in unsafe { self.as_bytes() } {
+            f.write_char(byte as char)?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for AnsiString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for &byte in unsafe { self.as_bytes() } {
+            for c in core::ascii::escape_default(byte) {
+                f.write_char(c as char)?;
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// An owned, growable [`AnsiString`].
+pub struct AnsiStringBuf {
+    buf: Vec<u8>,
+    string: AnsiString,
+}
+
+impl AnsiStringBuf {
+    pub fn new() -> Self {
+        Self { buf: Vec::new(), string: AnsiString::empty() }
+    }
+
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self, NtStatus> {
+        let mut this = Self::new();
+        this.push_bytes(bytes)?;
+        Ok(this)
+    }
+
+    fn sync(&mut self) {
+        let capacity = self.buf.capacity().min(u16::MAX as usize);
+        let buffer = if capacity == 0 { core::ptr::null() } else { self.buf.as_ptr() };
+        self.string = AnsiString { length: self.buf.len() as u16, maximum_length: capacity as u16, buffer };
+    }
+
+    pub fn reserve(&mut self, additional: usize) -> Result<(), NtStatus> {
+        match self.buf.len().checked_add(additional) {
+            Some(len) if len <= u16::MAX as usize => {}
+            _ => return Err(NtStatus(ntstatus::STATUS_NAME_TOO_LONG)),
+        }
+
+        self.buf
+            .try_reserve(additional)
+            .map_err(|_| NtStatus(ntstatus::STATUS_INSUFFICIENT_RESOURCES))?;
+        self.sync();
+        Ok(())
+    }
+
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> Result<(), NtStatus> {
+        self.reserve(bytes.len())?;
+        self.buf.extend_from_slice(bytes);
+        self.sync();
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+impl Default for AnsiStringBuf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for AnsiStringBuf {
+    type Target = AnsiString;
+
+    fn deref(&self) -> &AnsiString {
+        &self.string
+    }
+}
+
+impl Clone for AnsiStringBuf {
+    fn clone(&self) -> Self {
+        let mut this = Self { buf: self.buf.clone(), string: self.string };
+        this.sync();
+        this
+    }
+}
+
+impl PartialEq for AnsiStringBuf {
+    fn eq(&self, other: &Self) -> bool {
+        self.buf == other.buf
+    }
+}
+
+impl Eq for AnsiStringBuf {}
+
+impl Display for AnsiStringBuf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.string, f)
+    }
+}
+
+impl Debug for AnsiStringBuf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.string, f)
+    }
+}
@@ -0,0 +1,64 @@
+//! Executive spin locks.
+
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use crate::basedef::{KIRQL, KSPIN_LOCK};
+
+#[cfg(not(feature = "host-sim"))]
+extern "system" {
+    pub fn KeAcquireSpinLockRaiseToDpc(spin_lock: *mut KSPIN_LOCK) -> KIRQL;
+    pub fn KeReleaseSpinLock(spin_lock: *mut KSPIN_LOCK, new_irql: KIRQL);
+}
+
+#[cfg(feature = "host-sim")]
+pub use crate::sim::ntoskrnl::{KeAcquireSpinLockRaiseToDpc, KeReleaseSpinLock};
+
+/// A value protected by a `KSPIN_LOCK`.
+///
+/// Holding the lock raises the IRQL to `DISPATCH_LEVEL`, so it can be taken at any IRQL up to
+/// `DISPATCH_LEVEL`, and the protected value must not be paged.
+pub struct SpinLock<T> {
+    lock: UnsafeCell<KSPIN_LOCK>,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for SpinLock<T> {}
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+
+impl<T> SpinLock<T> {
+    pub const fn new(value: T) -> Self {
+        Self { lock: UnsafeCell::new(0), value: UnsafeCell::new(value) }
+    }
+
+    /// Acquires the lock, raising the IRQL to `DISPATCH_LEVEL` until the guard is dropped.
+    pub fn lock(&self) -> SpinLockGuard<'_, T> {
+        let old_irql = unsafe { KeAcquireSpinLockRaiseToDpc(self.lock.get()) };
+        SpinLockGuard { lock: self, old_irql }
+    }
+}
+
+/// Releases the [`SpinLock`] and restores the previous IRQL on drop.
+pub struct SpinLockGuard<'a, T> {
+    lock: &'a SpinLock<T>,
+    old_irql: KIRQL,
+}
+
+impl<T> Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T> DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T> Drop for SpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        unsafe { KeReleaseSpinLock(self.lock.lock.get(), self.old_irql) };
+    }
+}
@@ -1,7 +1,7 @@
-pub unsafe fn str_from_slice_unchecked<'a>(slice: &'a [u8]) -> &'a str {
-    let mut len = libc::strlen(slice.as_ptr() as _);
-    if len > slice.len() {
-        len = slice.len();
-    }
-    core::str::from_utf8_unchecked(&slice[0..len])
-}
\ No newline at end of file
+/// Reads a string up to the first null, or the whole slice if it has none.
+/// # Safety
+/// The bytes before the null must be valid UTF-8.
+pub unsafe fn str_from_slice_unchecked(slice: &[u8]) -> &str {
+    let len = slice.iter().position(|&b| b == 0).unwrap_or(slice.len());
+    core::str::from_utf8_unchecked(&slice[..len])
+}
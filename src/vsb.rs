@@ -13,7 +13,10 @@ use core::{
     ptr::{self, NonNull},
     slice::{from_raw_parts, from_raw_parts_mut},
 };
-use core::ops::{Deref, DerefMut};
+use core::fmt;
+use core::ops::Deref;
+use crate::basedef::ntstatus;
+use crate::ntstatus::NtStatus;
 
 /// This is a smart pointer type for holding FFI types whose size varies.
 /// Most commonly this is with an array member as the last field whose size is specified
@@ -101,6 +104,9 @@ impl<T> VariableSizedBox<T> {
     pub fn len(&self) -> usize {
         self.size
     }
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
     /// Given a pointer to a specific field, upgrades the provenance of the pointer to the entire
     /// allocation to work around stacked borrows.
     /// # Safety
@@ -280,3 +286,183 @@ impl<T> Default for VariableSizedBox<T> {
         }
     }
 }
+
+/// An FFI struct that is chained to the next entry in the same buffer by a byte offset relative
+/// to its own start, with an offset of `0` marking the last entry.
+/// # Safety
+/// `next_entry_offset` must return the value of the struct's `NextEntryOffset` field.
+pub unsafe trait NextEntryOffset {
+    fn next_entry_offset(&self) -> u32;
+}
+
+macro_rules! impl_next_entry_offset {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            unsafe impl NextEntryOffset for $ty {
+                fn next_entry_offset(&self) -> u32 {
+                    self.NextEntryOffset
+                }
+            }
+        )*
+    };
+}
+
+impl_next_entry_offset!(crate::basedef::ntapi::ntexapi::SYSTEM_PROCESS_INFORMATION);
+
+// The host simulation doesn't define the file and registry information classes.
+#[cfg(not(all(feature = "host-sim", not(windows))))]
+impl_next_entry_offset!(
+    crate::basedef::ntapi::ntioapi::FILE_DIRECTORY_INFORMATION,
+    crate::basedef::ntapi::ntioapi::FILE_FULL_DIR_INFORMATION,
+    crate::basedef::ntapi::ntioapi::FILE_ID_FULL_DIR_INFORMATION,
+    crate::basedef::ntapi::ntioapi::FILE_BOTH_DIR_INFORMATION,
+    crate::basedef::ntapi::ntioapi::FILE_ID_BOTH_DIR_INFORMATION,
+    crate::basedef::ntapi::ntioapi::FILE_NAMES_INFORMATION,
+    crate::basedef::ntapi::ntioapi::FILE_STREAM_INFORMATION,
+    crate::basedef::ntapi::ntioapi::FILE_FULL_EA_INFORMATION,
+    crate::basedef::ntapi::ntregapi::REG_NOTIFY_INFORMATION,
+);
+
+impl<T: NextEntryOffset> VariableSizedBox<T> {
+    /// Iterates over the chain of entries starting at the beginning of the allocation.
+    /// An offset that is misaligned or would place an entry outside of the allocation yields an
+    /// [`EntryError`] and ends the iteration.
+    /// # Safety
+    /// Every entry in the chain must be valid for `T`.
+    pub unsafe fn entries(&self) -> Entries<'_, T> {
+        let next = if self.is_empty() { None } else { Some(Ok(0)) };
+        Entries { vsb: self, next }
+    }
+}
+
+/// Why [`Entries`] could not continue along the chain.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum EntryError {
+    /// The allocation is too small to hold the first entry.
+    Truncated { len: usize },
+    /// The `NextEntryOffset` of the entry at `offset` is misaligned, overlaps the entry itself or
+    /// points past the end of the allocation.
+    BadNextEntryOffset { offset: usize, next_entry_offset: u32 },
+}
+
+impl fmt::Display for EntryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntryError::Truncated { len } => write!(f, "{:#x} bytes are too few for the first entry", len),
+            EntryError::BadNextEntryOffset { offset, next_entry_offset } => {
+                write!(f, "entry at {:#x} has a bad next entry offset of {:#x}", offset, next_entry_offset)
+            }
+        }
+    }
+}
+
+impl From<EntryError> for NtStatus {
+    fn from(_: EntryError) -> Self {
+        NtStatus(ntstatus::STATUS_DATA_ERROR)
+    }
+}
+
+/// Iterator returned by [`VariableSizedBox::entries`].
+pub struct Entries<'a, T> {
+    vsb: &'a VariableSizedBox<T>,
+    next: Option<Result<usize, EntryError>>,
+}
+
+impl<'a, T: NextEntryOffset> Entries<'a, T> {
+    fn is_valid_offset(&self, offset: usize) -> bool {
+        offset.is_multiple_of(align_of::<T>())
+            && matches!(offset.checked_add(size_of::<T>()), Some(end) if end <= self.vsb.len())
+    }
+}
+
+impl<'a, T: NextEntryOffset> Iterator for Entries<'a, T> {
+    type Item = Result<Entry<'a, T>, EntryError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = match self.next.take()? {
+            Ok(offset) => offset,
+            Err(e) => return Some(Err(e)),
+        };
+        if !self.is_valid_offset(offset) {
+            return Some(Err(EntryError::Truncated { len: self.vsb.len() }));
+        }
+
+        let entry = unsafe { &*(self.vsb.as_ptr() as *const u8).add(offset).cast::<T>() };
+        let end = match entry.next_entry_offset() {
+            0 => self.vsb.len(),
+            next_entry_offset => match offset.checked_add(next_entry_offset as usize) {
+                // Entries only ever chain forwards and must not overlap.
+                Some(next) if next - offset >= size_of::<T>() && self.is_valid_offset(next) => {
+                    self.next = Some(Ok(next));
+                    next
+                }
+                _ => {
+                    self.next = Some(Err(EntryError::BadNextEntryOffset { offset, next_entry_offset }));
+                    self.vsb.len()
+                }
+            },
+        };
+
+        Some(Ok(Entry { vsb: self.vsb, entry, offset, len: end - offset }))
+    }
+}
+
+/// A borrowed entry of a chained buffer, including its variable-length tail.
+pub struct Entry<'a, T> {
+    vsb: &'a VariableSizedBox<T>,
+    entry: &'a T,
+    offset: usize,
+    len: usize,
+}
+
+impl<'a, T> Entry<'a, T> {
+    /// The fixed size part of the entry.
+    pub fn get(&self) -> &'a T {
+        self.entry
+    }
+    /// The byte offset of this entry from the start of the allocation.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+    /// The size of this entry in bytes, up to the next entry or the end of the allocation.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// All bytes belonging to this entry, starting with the fixed size part.
+    pub fn bytes(&self) -> &'a [u8] {
+        unsafe { from_raw_parts((self.vsb.as_ptr() as *const u8).add(self.offset), self.len) }
+    }
+    /// The bytes following the fixed size part of the entry.
+    pub fn tail(&self) -> &'a [u8] {
+        &self.bytes()[size_of::<T>()..]
+    }
+    /// Given a pointer to a variable sized array field of this entry and the length of the array
+    /// in elements, returns a slice to the entire array.
+    /// Will return `None` if the slice is not entirely within this entry.
+    /// # Safety
+    /// The data must be valid for the specified type.
+    pub unsafe fn try_slice_from_count<U>(&self, ptr: *const U, count: usize) -> Option<&'a [U]> {
+        let start = self.bytes().as_ptr() as usize;
+        let end = start + self.len;
+        let bytes = count.checked_mul(size_of::<U>())?;
+        if (ptr as usize) >= start
+            && (ptr as usize).checked_add(bytes)? <= end
+            && (ptr as usize).is_multiple_of(align_of::<U>())
+        {
+            Some(from_raw_parts(self.vsb.sanitize_ptr(ptr), count))
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a, T> Deref for Entry<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.entry
+    }
+}
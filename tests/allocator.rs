@@ -0,0 +1,223 @@
+#![cfg(feature = "host-sim")]
+
+mod common;
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use winkernel::allocator::{pool_tag, stats, KernelAlloc, OomPolicy, Pool, PoolType};
+use winkernel::sim::POOL_FILL;
+
+use common::{kernel, outstanding};
+
+#[test]
+fn over_aligned_allocations() {
+    let tag = pool_tag(*b"Tal1");
+    let alloc = KernelAlloc::new(PoolType::NonPagedPoolNx, tag);
+    kernel();
+
+    for align in [1, 8, 16, 32, 64, 256, 4096] {
+        for size in [1, 24, 100, 4096] {
+            let layout = Layout::from_size_align(size, align).unwrap();
+            unsafe {
+                let ptr = alloc.alloc(layout);
+                assert!(!ptr.is_null());
+                assert_eq!(ptr as usize % align, 0, "size {} align {}", size, align);
+                ptr.write_bytes(0x11, size);
+                assert_eq!(outstanding(tag), 1);
+                alloc.dealloc(ptr, layout);
+                assert_eq!(outstanding(tag), 0);
+            }
+        }
+    }
+}
+
+#[test]
+fn alloc_zeroed_clears_pool_memory() {
+    let tag = pool_tag(*b"Tal2");
+    let alloc = KernelAlloc::new(PoolType::NonPagedPoolNx, tag);
+    kernel();
+
+    for align in [8, 64] {
+        let layout = Layout::from_size_align(300, align).unwrap();
+        unsafe {
+            let raw = alloc.alloc(layout);
+            assert!(std::slice::from_raw_parts(raw, layout.size()).iter().all(|&b| b == POOL_FILL));
+            alloc.dealloc(raw, layout);
+
+            let zeroed = alloc.alloc_zeroed(layout);
+            assert!(std::slice::from_raw_parts(zeroed, layout.size()).iter().all(|&b| b == 0));
+            alloc.dealloc(zeroed, layout);
+        }
+    }
+    assert_eq!(outstanding(tag), 0);
+}
+
+#[test]
+fn realloc_keeps_contents_and_shrinks_in_place() {
+    let tag = pool_tag(*b"Tal3");
+    let alloc = KernelAlloc::new(PoolType::PagedPool, tag);
+    kernel();
+
+    for align in [8, 128] {
+        let layout = Layout::from_size_align(64, align).unwrap();
+        unsafe {
+            let ptr = alloc.alloc(layout);
+            for i in 0..64 {
+                ptr.add(i).write(i as u8);
+            }
+
+            let grown = alloc.realloc(ptr, layout, 1000);
+            assert_eq!(grown as usize % align, 0);
+            assert_eq!(std::slice::from_raw_parts(grown, 64), (0..64).collect::<Vec<u8>>().as_slice());
+            assert_eq!(outstanding(tag), 1);
+
+            let grown_layout = Layout::from_size_align(1000, align).unwrap();
+            let shrunk = alloc.realloc(grown, grown_layout, 10);
+            assert_eq!(shrunk, grown);
+
+            alloc.dealloc(shrunk, Layout::from_size_align(10, align).unwrap());
+        }
+    }
+    assert_eq!(outstanding(tag), 0);
+}
+
+#[test]
+fn failed_allocations_return_null_and_run_the_hook() {
+    static FAILURES: AtomicUsize = AtomicUsize::new(0);
+
+    fn hook(layout: Layout, pool: Pool) {
+        assert_eq!(layout.size(), isize::MAX as usize / 2);
+        assert_eq!(pool.tag(), pool_tag(*b"Tal4"));
+        FAILURES.fetch_add(1, Ordering::SeqCst);
+    }
+
+    let alloc = KernelAlloc::new(PoolType::NonPagedPoolNx, pool_tag(*b"Tal4")).oom_policy(OomPolicy::Hook(hook));
+    kernel();
+
+    let huge = Layout::from_size_align(isize::MAX as usize / 2, 8).unwrap();
+    unsafe {
+        assert!(alloc.alloc(huge).is_null());
+        assert!(alloc.alloc_zeroed(huge).is_null());
+
+        let small = Layout::from_size_align(16, 8).unwrap();
+        let ptr = alloc.alloc(small);
+        assert!(alloc.realloc(ptr, small, huge.size()).is_null());
+        alloc.dealloc(ptr, small);
+    }
+    assert_eq!(FAILURES.load(Ordering::SeqCst), 3);
+    assert_eq!(outstanding(pool_tag(*b"Tal4")), 0);
+}
+
+#[cfg(feature = "alloc-tracking")]
+#[test]
+fn tracking_records_live_allocations() {
+    use winkernel::allocator::tracking::{self, AllocationRecord};
+
+    let tag = pool_tag(*b"Tal5");
+    let alloc = KernelAlloc::new(PoolType::NonPagedPoolNx, tag);
+    kernel();
+
+    let ours = || {
+        let mut records = vec![
+            AllocationRecord { address: 0, size: 0, align: 0, pool_type: PoolType::NonPagedPool, tag: 0, sequence: 0 };
+            1024
+        ];
+        let count = tracking::live_allocations(&mut records).min(records.len());
+        records.truncate(count);
+        records.retain(|record| record.tag == tag);
+        records
+    };
+
+    unsafe {
+        let small = Layout::from_size_align(40, 8).unwrap();
+        let page = Layout::from_size_align(100, 4096).unwrap();
+        let a = alloc.alloc(small);
+        let b = alloc.alloc(page);
+        assert_eq!(b as usize % 4096, 0);
+
+        let records = ours();
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].address, records[0].size, records[0].align), (b as usize, 100, 4096));
+        assert_eq!((records[1].address, records[1].size), (a as usize, 40));
+        assert!(records[0].sequence > records[1].sequence);
+
+        let a = alloc.realloc(a, small, 400);
+        let records = ours();
+        assert_eq!((records[0].address, records[0].size), (a as usize, 400));
+
+        alloc.dealloc(a, Layout::from_size_align(400, 8).unwrap());
+        alloc.dealloc(b, page);
+    }
+
+    assert!(ours().is_empty());
+    assert_eq!(outstanding(tag), 0);
+    assert!(tracking::counters().total_frees >= 3);
+}
+
+#[cfg(feature = "alloc-tracking")]
+#[test]
+fn paged_allocations_are_not_tracked() {
+    use winkernel::allocator::tracking::{self, AllocationRecord};
+
+    let tag = pool_tag(*b"Tal7");
+    let alloc = KernelAlloc::new(PoolType::PagedPool, tag);
+    kernel();
+
+    unsafe {
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        let ptr = alloc.alloc(layout);
+        assert!(!ptr.is_null());
+        assert_eq!(outstanding(tag), 1);
+
+        let mut records = vec![
+            AllocationRecord { address: 0, size: 0, align: 0, pool_type: PoolType::NonPagedPool, tag: 0, sequence: 0 };
+            1024
+        ];
+        let count = tracking::live_allocations(&mut records).min(records.len());
+        assert!(records[..count].iter().all(|record| record.tag != tag));
+
+        // Without a header, the block is exactly what the pool handed out.
+        assert!(kernel().outstanding_pools().iter().any(|pool| pool.3 == tag && pool.0 == ptr as usize));
+
+        let ptr = alloc.realloc(ptr, layout, 256);
+        alloc.dealloc(ptr, Layout::from_size_align(256, 8).unwrap());
+    }
+    assert_eq!(outstanding(tag), 0);
+}
+
+#[test]
+fn stats_follow_usage_per_pool_type() {
+    // No other test allocates from this pool type, so the numbers are exact.
+    let pool_type = PoolType::NonPagedPoolSessionNx;
+    let alloc = KernelAlloc::new(pool_type, pool_tag(*b"Tal6"));
+    kernel();
+
+    let before = stats::pool(pool_type);
+    assert_eq!(before, stats::PoolStats::default());
+
+    unsafe {
+        let small = Layout::from_size_align(100, 8).unwrap();
+        let large = Layout::from_size_align(1000, 8).unwrap();
+        let a = alloc.alloc(small);
+        let b = alloc.alloc(large);
+        let in_use = stats::pool(pool_type).bytes_in_use;
+        assert!(in_use >= 1100);
+
+        let b = alloc.realloc(b, large, 10);
+        assert_eq!(stats::pool(pool_type).bytes_in_use, in_use - 990);
+
+        assert!(alloc.alloc(Layout::from_size_align(isize::MAX as usize / 2, 8).unwrap()).is_null());
+
+        alloc.dealloc(a, small);
+        alloc.dealloc(b, Layout::from_size_align(10, 8).unwrap());
+
+        let after = stats::pool(pool_type);
+        assert_eq!(after.bytes_in_use, 0);
+        assert_eq!(after.peak_bytes, in_use);
+        assert_eq!((after.allocations, after.frees, after.failures), (2, 2, 1));
+    }
+
+    assert!(stats::pools().any(|(pool, _)| pool == pool_type));
+    assert!(stats::total().allocations >= 2);
+}
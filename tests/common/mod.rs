@@ -0,0 +1,18 @@
+//! Fixtures shared by the integration tests.
+#![allow(dead_code)]
+
+use std::sync::{Arc, OnceLock};
+
+use winkernel::sim::{set_backend, SimKernel};
+
+/// The simulated kernel every test in the binary shares. Call it before anything that goes
+/// through the shims, or they install a default backend of their own.
+pub fn kernel() -> &'static Arc<SimKernel> {
+    static KERNEL: OnceLock<Arc<SimKernel>> = OnceLock::new();
+    KERNEL.get_or_init(|| set_backend(SimKernel::new()))
+}
+
+/// How many pool allocations under `tag` are still outstanding.
+pub fn outstanding(tag: u32) -> usize {
+    kernel().outstanding_pools().iter().filter(|pool| pool.3 == tag).count()
+}
@@ -0,0 +1,15 @@
+#!/bin/sh
+# Rebuilds sample.dll, the linker-produced fixture for tests/pe.rs, with the LLVM tools.
+#   LLD: a linker that understands `-flavor link`, e.g. the rust-lld shipped with rustup.
+set -e
+cd "$(dirname "$0")"
+LLD=${LLD:-rust-lld}
+tmp=$(mktemp -d)
+trap 'rm -rf "$tmp"' EXIT
+
+llvm-mc -triple x86_64-pc-windows-msvc -filetype=obj sample.s -o "$tmp/sample.obj"
+"$LLD" -flavor link /lib /def:kernel32.def /machine:x64 /out:"$tmp/kernel32.lib"
+"$LLD" -flavor link /lib /def:user32.def /machine:x64 /out:"$tmp/user32.lib"
+"$LLD" -flavor link /dll /noentry /nodefaultlib /brepro /machine:x64 /def:sample.def /out:"$tmp/sample.dll" \
+    "$tmp/sample.obj" "$tmp/kernel32.lib" "$tmp/user32.lib"
+cp "$tmp/sample.dll" sample.dll
@@ -0,0 +1,28 @@
+# Source of sample.dll, see build.sh. `table` holds absolute addresses so the image has base
+# relocations, and `ticks`/`beep` call through the import address table.
+    .text
+    .globl add_one
+add_one:
+    leal 1(%rcx), %eax
+    retq
+
+    .globl ticks
+ticks:
+    jmpq *__imp_GetTickCount(%rip)
+
+    .globl hidden
+hidden:
+    xorl %eax, %eax
+    retq
+
+    .globl beep
+beep:
+    movq __imp_MessageBeep(%rip), %rax
+    jmpq *%rax
+
+    .data
+    .globl table
+table:
+    .quad add_one
+    .quad ticks
+    .quad hidden
@@ -0,0 +1,280 @@
+#![cfg(feature = "host-sim")]
+
+mod common;
+
+use std::ptr::null_mut;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+use winkernel::allocator::{pool_tag, ExAllocatePoolWithTag, ExFreePoolWithTag, PoolType};
+use winkernel::basedef::ntapi::ntexapi::{SystemBigPoolInformation, SystemExtendedHandleInformation, SYSTEM_BIGPOOL_INFORMATION};
+use winkernel::basedef::ntapi::ntkeapi::{Executive, Running, UserRequest, Waiting, WrQueue};
+use winkernel::basedef::ntstatus;
+use winkernel::basedef::winapi::shared::guiddef::GUID;
+use winkernel::basedef::winapi::um::winnt::FirmwareTypeBios;
+use winkernel::kernel::{get_big_pool_allocations, get_boot_environment, get_code_integrity_options, get_kernel_debugger_info, get_system_handles};
+use winkernel::kernel::{find_kernel_module, get_kernel_modules, module_containing, ModuleAddress, ProcessModuleInformation};
+use winkernel::kernel::{get_process_list, query_system_information, CodeIntegrityOptions, KernelDebuggerInfo, ProcessSnapshot, ThreadState};
+use winkernel::ntstatus::NtStatus;
+use winkernel::sim::{SimHandle, SimModule, SimSystemInfo, SimThread};
+
+use common::kernel;
+
+const NTOSKRNL: usize = 0xffff_f800_0000_0000;
+const ACPI: usize = 0xffff_f800_0020_0000;
+
+/// Loads `ntoskrnl.exe` and `ACPI.sys` once, with a gap between them. Tests that query modules
+/// hold the returned guard, so one test misreporting the module count doesn't affect the others.
+fn load_modules() -> MutexGuard<'static, ()> {
+    static LOADED: OnceLock<()> = OnceLock::new();
+    static QUERIES: Mutex<()> = Mutex::new(());
+    LOADED.get_or_init(|| {
+        kernel().add_module(SimModule {
+            full_path: "\\SystemRoot\\system32\\ntoskrnl.exe".into(),
+            base: NTOSKRNL,
+            size: 0x10_0000,
+            exports: [("PsGetProcessPeb".to_string(), NTOSKRNL + 0x4_5000)].into(),
+        });
+        kernel().add_module(SimModule {
+            full_path: "\\SystemRoot\\System32\\drivers\\ACPI.sys".into(),
+            base: ACPI,
+            size: 0x2000,
+            exports: Default::default(),
+        });
+    });
+    QUERIES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn snapshot(pid: u64) -> ProcessSnapshot {
+    let processes = unsafe { get_process_list() }.unwrap();
+    processes.into_iter().find(|p| p.process_id == pid).unwrap()
+}
+
+#[test]
+fn process_list_includes_every_thread() {
+    kernel().add_process(4001, "worker.exe");
+    kernel().add_thread(4001, SimThread { tid: 10, start_address: 0x1000, state: Running, wait_reason: Executive, priority: 8 });
+    kernel().add_thread(4001, SimThread { tid: 11, start_address: 0x2000, state: Waiting, wait_reason: UserRequest, priority: 9 });
+    kernel().add_thread(4001, SimThread { tid: 12, start_address: 0x2000, state: Waiting, wait_reason: WrQueue, priority: 10 });
+
+    let process = snapshot(4001);
+    assert_eq!(process.image_name, "worker.exe");
+    assert_eq!(process.threads.len(), 3);
+
+    let thread = process.threads[1];
+    assert_eq!((thread.thread_id, thread.process_id, thread.start_address), (11, 4001, 0x2000));
+    assert_eq!((thread.state, thread.wait_reason, thread.priority), (ThreadState::Waiting, UserRequest, 9));
+    assert_eq!(process.threads[0].state, ThreadState::Running);
+
+    let ids: Vec<_> = process.threads_by_start_address(0x2000).map(|t| t.thread_id).collect();
+    assert_eq!(ids, [11, 12]);
+    assert_eq!(process.threads_by_start_address(0x3000).count(), 0);
+}
+
+#[test]
+fn process_list_covers_processes_without_threads() {
+    kernel().add_process(4002, "idle.exe");
+    kernel().add_process(4003, "a-rather-long-image-name.exe");
+    kernel().add_thread(4003, SimThread { tid: 20, start_address: 0x4000, state: Running, wait_reason: Executive, priority: 1 });
+
+    let idle = snapshot(4002);
+    assert_eq!(idle.image_name, "idle.exe");
+    assert!(idle.threads.is_empty());
+
+    // `EPROCESS` only keeps the first 15 characters of the image name.
+    let long = snapshot(4003);
+    assert_eq!(long.image_name, "a-rather-long-i");
+    assert_eq!(long.threads.len(), 1);
+
+    let process = unsafe { long.to_process() }.unwrap();
+    assert_eq!(unsafe { process.file_name() }.to_str(), Some("a-rather-long-i"));
+}
+
+#[test]
+fn handle_table_is_retried_until_it_fits() {
+    for handle in 1..=4 {
+        kernel().add_handle(SimHandle {
+            process_id: 4010,
+            handle: handle * 4,
+            object: 0xffff_8000_0000_0000 + handle * 0x100,
+            granted_access: 0x1f_ffff,
+            object_type_index: 7,
+            attributes: 0x2,
+        });
+    }
+
+    // Each forced mismatch stands for the table growing between sizing and filling the buffer.
+    kernel().force_length_mismatch(SystemExtendedHandleInformation, 3);
+    let before = kernel().query_count(SystemExtendedHandleInformation);
+    let handles = unsafe { get_system_handles() }.unwrap();
+    assert_eq!(kernel().query_count(SystemExtendedHandleInformation) - before, 4);
+
+    let handles: Vec<_> = handles.into_iter().filter(|h| h.process_id == 4010).collect();
+    assert_eq!(handles.len(), 4);
+    let handle = handles[2];
+    assert_eq!((handle.handle, handle.object), (12, 0xffff_8000_0000_0300));
+    assert_eq!((handle.granted_access, handle.object_type_index, handle.attributes), (0x1f_ffff, 7, 0x2));
+}
+
+#[test]
+fn big_pool_queries_give_up_after_eight_attempts() {
+    kernel();
+    let big = unsafe { ExAllocatePoolWithTag(PoolType::NonPagedPoolNx, 0x2000, pool_tag(*b"Big1")) };
+    let paged = unsafe { ExAllocatePoolWithTag(PoolType::PagedPool, 0x1000, pool_tag(*b"Big2")) };
+    let small = unsafe { ExAllocatePoolWithTag(PoolType::NonPagedPool, 0x800, pool_tag(*b"Big3")) };
+
+    let mut entries: Vec<_> = unsafe { get_big_pool_allocations() }
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.tag & 0x00ff_ffff == pool_tag(*b"Big\0"))
+        .map(|e| (e.address, e.size, e.tag, e.non_paged))
+        .collect();
+    entries.sort_by_key(|e| e.2);
+    assert_eq!(entries, [(big as usize, 0x2000, pool_tag(*b"Big1"), true), (paged as usize, 0x1000, pool_tag(*b"Big2"), false)]);
+
+    let before = kernel().query_count(SystemBigPoolInformation);
+    kernel().force_length_mismatch(SystemBigPoolInformation, 8);
+    let result = unsafe { query_system_information::<SYSTEM_BIGPOOL_INFORMATION>(SystemBigPoolInformation) };
+    assert_eq!(result.err(), Some(NtStatus(ntstatus::STATUS_INFO_LENGTH_MISMATCH)));
+    assert_eq!(kernel().query_count(SystemBigPoolInformation) - before, 8);
+
+    kernel().force_length_mismatch(SystemBigPoolInformation, 7);
+    assert!(unsafe { get_big_pool_allocations() }.is_ok());
+    assert_eq!(kernel().query_count(SystemBigPoolInformation) - before, 16);
+
+    unsafe {
+        ExFreePoolWithTag(big, pool_tag(*b"Big1"));
+        ExFreePoolWithTag(paged, pool_tag(*b"Big2"));
+        ExFreePoolWithTag(small, pool_tag(*b"Big3"));
+    }
+}
+
+#[test]
+fn fixed_size_classes() {
+    kernel();
+    let defaults = unsafe { get_code_integrity_options() }.unwrap();
+    assert!(defaults.is_enabled() && !defaults.is_test_signing() && !defaults.is_hvci());
+    assert_eq!(unsafe { get_kernel_debugger_info() }, Ok(KernelDebuggerInfo { enabled: false, present: false }));
+
+    let identifier = GUID { Data1: 0x1234_5678, Data2: 0x9abc, Data3: 0xdef0, Data4: *b"bootguid" };
+    kernel().set_system_info(SimSystemInfo {
+        code_integrity_options: CodeIntegrityOptions::ENABLED | CodeIntegrityOptions::TESTSIGN | CodeIntegrityOptions::HVCI_KMCI_ENABLED,
+        kernel_debugger_enabled: true,
+        kernel_debugger_present: true,
+        boot_identifier: identifier,
+        firmware_type: FirmwareTypeBios,
+        boot_flags: 0x30,
+    });
+
+    let options = unsafe { get_code_integrity_options() }.unwrap();
+    assert!(options.is_enabled() && options.is_test_signing() && options.is_hvci());
+    assert!(!options.contains(CodeIntegrityOptions::DEBUGMODE_ENABLED));
+    assert_eq!(unsafe { get_kernel_debugger_info() }, Ok(KernelDebuggerInfo { enabled: true, present: true }));
+
+    let boot = unsafe { get_boot_environment() }.unwrap();
+    assert_eq!((boot.boot_identifier.Data1, boot.boot_identifier.Data4), (0x1234_5678, *b"bootguid"));
+    assert_eq!((boot.firmware_type, boot.boot_flags), (FirmwareTypeBios, 0x30));
+
+    kernel().set_system_info(SimSystemInfo::default());
+}
+
+#[test]
+fn unknown_classes_are_rejected() {
+    kernel();
+    let result = unsafe { query_system_information::<u64>(0x7fff) };
+    assert_eq!(result.err(), Some(NtStatus(ntstatus::STATUS_INVALID_INFO_CLASS)));
+}
+
+#[test]
+fn kernel_modules_are_found_by_file_name() {
+    let _modules = load_modules();
+
+    let modules = unsafe { get_kernel_modules() }.unwrap();
+    let names: Vec<_> = modules.iter().map(|m| m.file_name()).collect();
+    assert_eq!(names, [b"ntoskrnl.exe".as_slice(), b"ACPI.sys"]);
+
+    unsafe {
+        let ntoskrnl = find_kernel_module("NTOSKRNL.EXE").unwrap().unwrap();
+        assert_eq!((ntoskrnl.image_base, ntoskrnl.image_size), (NTOSKRNL, 0x10_0000));
+        assert_eq!(ntoskrnl.full_path(), b"\\SystemRoot\\system32\\ntoskrnl.exe");
+        assert_eq!(ntoskrnl.get_export("PsGetProcessPeb").map(|p| p.as_ptr() as usize), Some(NTOSKRNL + 0x4_5000));
+        assert!(ntoskrnl.get_export("PsGetProcessWow64Process").is_none());
+
+        let acpi = |name| find_kernel_module(name).unwrap().map(|m| m.image_base);
+        assert_eq!(acpi("acpi.sys"), Some(ACPI));
+        assert_eq!(acpi("C:\\Windows\\System32\\drivers\\ACPI.SYS"), Some(ACPI));
+        assert_eq!(acpi("drivers/acpi.sys"), Some(ACPI));
+        assert_eq!(acpi("acpi"), None);
+        assert_eq!(acpi("missing.sys"), None);
+    }
+}
+
+#[test]
+fn addresses_resolve_to_their_module() {
+    let _modules = load_modules();
+
+    let resolved = unsafe { module_containing(ACPI + 0x1234) }.unwrap().unwrap();
+    assert_eq!((resolved.module.image_base, resolved.offset), (ACPI, 0x1234));
+    assert_eq!(resolved.to_string(), "ACPI.sys+0x1234");
+    assert_eq!(
+        format!("{:?}", resolved),
+        "ModuleAddress { module: \"ACPI.sys\", image_base: 0xfffff80000200000, image_size: 0x2000, offset: 0x1234 }"
+    );
+
+    unsafe {
+        assert_eq!(module_containing(NTOSKRNL).unwrap().unwrap().to_string(), "ntoskrnl.exe+0x0");
+        assert_eq!(module_containing(NTOSKRNL + 0xf_ffff).unwrap().unwrap().offset, 0xf_ffff);
+        // The end of a module and the gap after it.
+        assert!(module_containing(NTOSKRNL + 0x10_0000).unwrap().is_none());
+        assert!(module_containing(ACPI + 0x2000).unwrap().is_none());
+        assert!(module_containing(0x1000).unwrap().is_none());
+    }
+
+    let modules = unsafe { get_kernel_modules() }.unwrap();
+    assert_eq!(ModuleAddress::resolve(&modules, NTOSKRNL + 0x10).unwrap().offset, 0x10);
+    assert!(ModuleAddress::resolve(&[], NTOSKRNL).is_none());
+}
+
+#[test]
+fn module_names_need_not_be_terminated_or_utf8() {
+    let mut module = ProcessModuleInformation {
+        section: null_mut(),
+        mapped_base: 0,
+        image_base: ACPI,
+        image_size: 0x2000,
+        flags: 0,
+        load_order_index: 0,
+        init_order_index: 0,
+        load_count: 1,
+        offset_to_file_name: 8,
+        full_path_name: [b'x'; 256],
+    };
+    module.full_path_name[..8].copy_from_slice(b"drivers\\");
+    assert_eq!(module.full_path().len(), 256);
+    assert_eq!(module.file_name(), [b'x'; 248]);
+
+    module.full_path_name[8..16].copy_from_slice(b"\xe9t\xe9.sys\0");
+    assert_eq!(module.full_path(), b"drivers\\\xe9t\xe9.sys");
+    assert_eq!(module.file_name(), b"\xe9t\xe9.sys");
+    let address = ModuleAddress { module, offset: 0x10 };
+    assert_eq!(address.to_string(), "\u{fffd}t\u{fffd}.sys+0x10");
+    assert!(format!("{:?}", address).starts_with("ModuleAddress { module: \"\u{fffd}t\u{fffd}.sys\", "));
+
+    // An offset past the buffer leaves an empty name.
+    module.offset_to_file_name = 300;
+    assert_eq!(module.file_name(), b"");
+}
+
+#[test]
+fn module_counts_beyond_the_buffer_are_rejected() {
+    let _modules = load_modules();
+
+    kernel().set_reported_module_count(Some(1000));
+    let result = unsafe { get_kernel_modules() };
+    kernel().set_reported_module_count(None);
+    assert_eq!(result.err(), Some(NtStatus(ntstatus::STATUS_INFO_LENGTH_MISMATCH)));
+
+    kernel().set_reported_module_count(Some(1));
+    let modules = unsafe { get_kernel_modules() };
+    kernel().set_reported_module_count(None);
+    assert_eq!(modules.unwrap().len(), 1);
+}
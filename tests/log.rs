@@ -0,0 +1,43 @@
+#![cfg(feature = "host-sim")]
+
+mod common;
+
+use std::sync::Mutex;
+
+use winkernel::log::{DbgPrintEx, __kernel_print, __kernel_print_fixed};
+
+use common::kernel;
+
+/// Runs `print` and returns everything it printed. Tests share the debug output, so they take
+/// turns.
+fn printed(print: impl FnOnce()) -> String {
+    static OUTPUT: Mutex<()> = Mutex::new(());
+    let _lock = OUTPUT.lock().unwrap();
+    kernel().take_debug_output();
+    print();
+    kernel().take_debug_output().concat()
+}
+
+#[test]
+fn dbg_print_formats_its_arguments() {
+    let wide: Vec<u16> = "wide\0".encode_utf16().collect();
+    let text = printed(|| unsafe {
+        DbgPrintEx(0, 0, c"%s=%d %u%% %ws %c|".as_ptr() as _, c"name".as_ptr(), -5i32, 7u32, wide.as_ptr(), b'x' as i32);
+        DbgPrintEx(0, 0, c"%08x %X %#x %I64x %llu %p|".as_ptr() as _, 0xbeefu32, 0xabcu32, 1u32, u64::MAX, 1u64 << 40, 0x1000usize);
+        DbgPrintEx(0, 0, c"[%5d] [%-5d] [%05d] [%.2s] [%5s]|".as_ptr() as _, 42i32, 42i32, -42i32, c"abc".as_ptr(), c"ab".as_ptr());
+        DbgPrintEx(0, 0, c"%s %q %".as_ptr() as _, std::ptr::null::<u8>());
+    });
+    assert_eq!(
+        text,
+        "name=-5 7% wide x|\
+         0000beef ABC 1 ffffffffffffffff 1099511627776 0000000000001000|\
+         [   42] [42   ] [-0042] [ab] [   ab]|\
+         (null) %q %"
+    );
+}
+
+#[test]
+fn kernel_print_does_not_interpret_percent_signs() {
+    assert_eq!(printed(|| __kernel_print("100% %s %d".into())), "100% %s %d\n");
+    assert_eq!(printed(|| __kernel_print_fixed(format_args!("{}% %n", 50))), "50% %n\n");
+}
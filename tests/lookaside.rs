@@ -0,0 +1,122 @@
+#![cfg(feature = "host-sim")]
+
+mod common;
+
+use std::sync::Arc;
+
+use winkernel::allocator::{pool_tag, stats, PoolType};
+use winkernel::lookaside::{LookasideBox, LookasideList};
+
+use common::{kernel, outstanding};
+
+#[derive(Debug, PartialEq)]
+struct Record {
+    key: u64,
+    value: [u32; 12],
+}
+
+#[test]
+fn freed_entries_are_reused() {
+    let tag = pool_tag(*b"Tla1");
+    kernel();
+    let list = LookasideList::<Record>::new(PoolType::NonPagedPoolNx, tag).unwrap();
+
+    let first = list.alloc(Record { key: 1, value: [1; 12] }).unwrap();
+    let address = &*first as *const Record;
+    assert_eq!(first.key, 1);
+    drop(first);
+    assert_eq!(list.cached(), 1);
+
+    let mut second = list.alloc(Record { key: 2, value: [2; 12] }).unwrap();
+    assert_eq!(&*second as *const Record, address);
+    second.value[3] = 7;
+    assert_eq!(LookasideBox::into_inner(second), Record { key: 2, value: [2, 2, 2, 7, 2, 2, 2, 2, 2, 2, 2, 2] });
+
+    assert_eq!(list.allocation_counts(), (2, 1));
+
+    // The list header plus the one cached entry.
+    assert_eq!(outstanding(tag), 2);
+    drop(list);
+    assert_eq!(outstanding(tag), 0);
+}
+
+#[test]
+fn entries_beyond_the_depth_go_back_to_the_pool() {
+    let tag = pool_tag(*b"Tla2");
+    kernel();
+    let list = LookasideList::<u64>::new(PoolType::PagedPool, tag).unwrap();
+
+    let boxes: Vec<_> = (0..10).map(|i| list.alloc(i).unwrap()).collect();
+    assert_eq!(boxes.iter().map(|b| **b).sum::<u64>(), 45);
+    assert_eq!(outstanding(tag), 11);
+    drop(boxes);
+
+    let cached = list.cached() as usize;
+    assert!(cached > 0 && cached < 10);
+    assert_eq!(outstanding(tag), 1 + cached);
+    drop(list);
+    assert_eq!(outstanding(tag), 0);
+}
+
+#[test]
+fn values_are_dropped_in_place() {
+    let tag = pool_tag(*b"Tla3");
+    kernel();
+    let list = LookasideList::<Arc<u32>>::new(PoolType::NonPagedPoolNx, tag).unwrap();
+
+    let shared = Arc::new(5);
+    let boxed = list.alloc(shared.clone()).unwrap();
+    assert_eq!(Arc::strong_count(&shared), 2);
+    drop(boxed);
+    assert_eq!(Arc::strong_count(&shared), 1);
+}
+
+#[test]
+fn counters_are_exact_across_threads() {
+    let tag = pool_tag(*b"Tla4");
+    kernel();
+    let list = LookasideList::<u64>::new(PoolType::NonPagedPoolNx, tag).unwrap();
+
+    std::thread::scope(|scope| {
+        for thread in 0..4u64 {
+            let list = &list;
+            scope.spawn(move || {
+                for i in 0..1000 {
+                    let boxed = list.alloc(thread * 1000 + i).unwrap();
+                    assert_eq!(*boxed, thread * 1000 + i);
+                }
+            });
+        }
+    });
+
+    let (total_allocates, allocate_misses) = list.allocation_counts();
+    assert_eq!(total_allocates, 4000);
+    assert!((1..=4).contains(&allocate_misses));
+    assert_eq!(outstanding(tag), 1 + list.cached() as usize);
+}
+
+#[test]
+fn blocks_are_counted_in_the_pool_stats() {
+    // No other test allocates from this pool type.
+    let pool_type = PoolType::NonPagedPoolCacheAligned;
+    let tag = pool_tag(*b"Tla5");
+    kernel();
+    assert_eq!(stats::pool(pool_type), stats::PoolStats::default());
+
+    // The header comes from non-paged pool, so only blocks are counted here.
+    let list = LookasideList::<[u64; 4]>::new(pool_type, tag).unwrap();
+    let boxes: Vec<_> = (0..10).map(|i| list.alloc([i; 4]).unwrap()).collect();
+    let in_use = stats::pool(pool_type);
+    assert_eq!((in_use.allocations, in_use.bytes_in_use), (10, 10 * 32));
+
+    // Cached blocks stay counted until the list gives them back to the pool.
+    drop(boxes);
+    let cached = list.cached() as usize;
+    let after_drop = stats::pool(pool_type);
+    assert_eq!((after_drop.frees, after_drop.bytes_in_use), (10 - cached, cached * 32));
+
+    drop(list);
+    let after_delete = stats::pool(pool_type);
+    assert_eq!((after_delete.frees, after_delete.bytes_in_use, after_delete.peak_bytes), (10, 0, 10 * 32));
+    assert_eq!(outstanding(tag), 0);
+}
@@ -0,0 +1,111 @@
+#![cfg(feature = "host-sim")]
+
+mod common;
+
+use std::ptr::null_mut;
+
+use winkernel::basedef::winapi::um::winnt::PAGE_READWRITE;
+use winkernel::basedef::{ntstatus, KProcessorMode};
+use winkernel::kernel::{safe_copy, IoAllocateMdl, IoFreeMdl, MmMapLockedPagesSpecifyCache, MmProbeAndLockPages, MmUnlockPages, MmUnmapLockedPages};
+use winkernel::kernel::{LOCK_OPERATION, MEMORY_CACHING_TYPE};
+use winkernel::mdl::{Mdl, MmPagePriority};
+use winkernel::ntstatus::NtStatus;
+
+use common::kernel;
+
+/// The `(length, locked, mapped)` state of the MDL describing `buf`, if one is outstanding.
+fn mdl_state(buf: &[u8]) -> Option<(u32, bool, bool)> {
+    kernel()
+        .outstanding_mdls()
+        .into_iter()
+        .find(|&(address, ..)| address == buf.as_ptr() as usize)
+        .map(|(_, length, locked, mapped)| (length, locked, mapped))
+}
+
+#[test]
+fn dropping_an_allocated_mdl_only_frees_it() {
+    kernel();
+    let mut buf = [0u8; 32];
+    let mdl = unsafe { Mdl::builder(buf.as_mut_ptr().cast(), buf.len()).allocate() }.unwrap();
+    assert_eq!(mdl.len(), 32);
+    assert_eq!(mdl_state(&buf), Some((32, false, false)));
+
+    drop(mdl);
+    assert_eq!(mdl_state(&buf), None);
+}
+
+#[test]
+fn dropping_a_locked_mdl_unlocks_before_freeing() {
+    kernel();
+    let mut buf = [0u8; 48];
+    let mdl = unsafe { Mdl::builder(buf.as_mut_ptr().cast(), buf.len()).operation(LOCK_OPERATION::IoWriteAccess).allocate().unwrap().lock() };
+    assert_eq!(mdl_state(&buf), Some((48, true, false)));
+
+    // The sim panics if `IoFreeMdl` sees a locked MDL.
+    drop(mdl);
+    assert_eq!(mdl_state(&buf), None);
+}
+
+#[test]
+fn dropping_a_mapped_mdl_unmaps_unlocks_and_frees() {
+    kernel();
+    let mut buf = [0u8; 64];
+    let mut mdl = unsafe {
+        Mdl::builder(buf.as_mut_ptr().cast(), buf.len())
+            .cache_type(MEMORY_CACHING_TYPE::MmNonCached)
+            .priority(MmPagePriority::HighPagePriority)
+            .no_execute(true)
+            .allocate()
+            .unwrap()
+            .lock()
+            .map()
+            .unwrap()
+    };
+    assert_eq!(mdl_state(&buf), Some((64, true, true)));
+
+    unsafe {
+        assert_eq!(mdl.protect(PAGE_READWRITE), Ok(()));
+        mdl.as_mut_slice()[..4].copy_from_slice(b"mdl!");
+        assert_eq!(&mdl.as_slice()[..4], b"mdl!");
+    }
+
+    drop(mdl);
+    assert_eq!(mdl_state(&buf), None);
+    assert_eq!(&buf[..4], b"mdl!");
+}
+
+#[test]
+fn oversized_mdls_are_rejected() {
+    kernel();
+    let mut buf = [0u8; 1];
+    let result = unsafe { Mdl::builder(buf.as_mut_ptr().cast(), u32::MAX as usize + 1).allocate() };
+    assert_eq!(result.err(), Some(NtStatus(ntstatus::STATUS_INVALID_PARAMETER)));
+    assert_eq!(mdl_state(&buf), None);
+}
+
+#[test]
+fn safe_copy_releases_its_mdl() {
+    kernel();
+    let mut buf = [0u8; 16];
+    unsafe { safe_copy(b"copied through".as_ptr(), buf.as_mut_ptr(), 14) }.unwrap();
+    assert_eq!(&buf[..14], b"copied through");
+    assert_eq!(mdl_state(&buf), None);
+}
+
+#[test]
+fn raw_mdl_functions_are_reexported() {
+    kernel();
+    let mut buf = [0u8; 8];
+    unsafe {
+        let mdl = IoAllocateMdl(buf.as_mut_ptr().cast(), buf.len() as u32, 0, 0, null_mut());
+        MmProbeAndLockPages(mdl, KProcessorMode::KernelMode, LOCK_OPERATION::IoModifyAccess);
+        let mapping = MmMapLockedPagesSpecifyCache(mdl, KProcessorMode::KernelMode, MEMORY_CACHING_TYPE::MmCached, null_mut(), 0, 0);
+        assert_eq!(mapping, buf.as_mut_ptr().cast());
+        assert_eq!(mdl_state(&buf), Some((8, true, true)));
+
+        MmUnmapLockedPages(mapping, mdl);
+        MmUnlockPages(mdl);
+        IoFreeMdl(mdl);
+    }
+    assert_eq!(mdl_state(&buf), None);
+}
@@ -0,0 +1,106 @@
+#![cfg(feature = "host-sim")]
+
+mod common;
+
+use winkernel::basedef::ntstatus;
+use winkernel::kernel::PhysicalMemory;
+use winkernel::memory::{MemoryReader, MemoryWriter};
+use winkernel::ntstatus::NtStatus;
+use winkernel::process::{PeProcess, ProcessRef};
+
+use common::kernel;
+
+const PAGE: u64 = 0x1000;
+
+/// A process with two mapped pages at `0x10000`, followed by an unmapped page.
+fn process(pid: u64) -> ProcessRef {
+    kernel().add_process(pid, "memory.exe");
+    kernel().map_process_memory(pid, 0x10000, 2 * PAGE as usize);
+    unsafe { PeProcess::by_pid(pid) }.unwrap()
+}
+
+#[test]
+fn typed_reads_and_writes() {
+    let process = process(2001);
+    kernel().write_process_memory(2001, 0x10010, &0x1122_3344_5566_7788u64.to_le_bytes());
+
+    unsafe {
+        assert_eq!(process.read::<u64>(0x10010), Ok(0x1122_3344_5566_7788));
+        assert_eq!(process.read::<u16>(0x10016), Ok(0x1122));
+        assert_eq!(process.read::<[u8; 3]>(0x10010), Ok([0x88, 0x77, 0x66]));
+
+        process.write(0x10100, &-5i32).unwrap();
+        process.write_slice(0x10104, &[1u32, 2, 3]).unwrap();
+        let mut values = [0i32; 4];
+        process.read_slice(0x10100, &mut values).unwrap();
+        assert_eq!(values, [-5, 1, 2, 3]);
+
+        let sim = kernel().process_memory(2001).unwrap();
+        assert_eq!(sim.read::<u32>(0x10108), Ok(2));
+        sim.write(0x10108, &7u32).unwrap();
+        assert_eq!(process.read::<u32>(0x10108), Ok(7));
+    }
+}
+
+#[test]
+fn short_copies_report_status_and_count() {
+    let process = process(2002);
+    let end = 0x10000 + 2 * PAGE;
+
+    unsafe {
+        let mut buf = [0xCCu8; 16];
+        assert_eq!(process.read_memory(end - 6, &mut buf), Err((NtStatus(ntstatus::STATUS_PARTIAL_COPY), 6)));
+        assert_eq!(&buf[..6], &[0; 6]);
+
+        let mut values = [0u32; 4];
+        assert_eq!(process.read_slice(end - 10, &mut values), Err((NtStatus(ntstatus::STATUS_PARTIAL_COPY), 2)));
+        assert_eq!(process.read::<u64>(end - 4), Err(NtStatus(ntstatus::STATUS_PARTIAL_COPY)));
+        assert_eq!(process.write_memory(end - 3, &[1; 8]), Err((NtStatus(ntstatus::STATUS_PARTIAL_COPY), 3)));
+
+        let sim = kernel().process_memory(2002).unwrap();
+        assert_eq!(sim.read_memory(end - 6, &mut buf), Err((NtStatus(ntstatus::STATUS_PARTIAL_COPY), 6)));
+        assert_eq!(sim.read::<u8>(end), Err(NtStatus(ntstatus::STATUS_PARTIAL_COPY)));
+    }
+}
+
+#[test]
+fn strings_stop_at_the_terminator_or_the_cap() {
+    let process = process(2003);
+    let end = 0x10000 + 2 * PAGE;
+
+    kernel().write_process_memory(2003, 0x10000, b"kernel32.dll\0junk");
+    let wide: Vec<u8> = "ntdll.dll\0".encode_utf16().flat_map(u16::to_le_bytes).collect();
+    kernel().write_process_memory(2003, 0x10100, &wide);
+
+    // A long string whose terminator is the last mapped byte.
+    kernel().write_process_memory(2003, end - 601, &[b'a'; 600]);
+
+    unsafe {
+        assert_eq!(process.read_cstring(0x10000, 64).unwrap(), b"kernel32.dll");
+        assert_eq!(process.read_cstring(0x10000, 12).unwrap(), b"kernel32.dll");
+        assert_eq!(process.read_cstring(0x10000, 11), Err(NtStatus(ntstatus::STATUS_NAME_TOO_LONG)));
+        assert_eq!(process.read_cstring(0x1000d, 64).unwrap(), b"junk");
+        assert_eq!(process.read_cstring(0x10000, usize::MAX).unwrap(), b"kernel32.dll");
+
+        let name = process.read_wide_string(0x10100, 64).unwrap();
+        assert_eq!(String::from_utf16(&name).unwrap(), "ntdll.dll");
+        assert_eq!(process.read_wide_string(0x10100, 8), Err(NtStatus(ntstatus::STATUS_NAME_TOO_LONG)));
+        assert_eq!(process.read_wide_string(0x10100, usize::MAX).unwrap(), name);
+
+        assert_eq!(process.read_cstring(end - 601, 1000).unwrap().len(), 600);
+        kernel().write_process_memory(2003, end - 1, b"a");
+        assert_eq!(process.read_cstring(end - 601, 1000), Err(NtStatus(ntstatus::STATUS_PARTIAL_COPY)));
+    }
+}
+
+#[test]
+fn physical_memory_reader() {
+    let base = 0x7_0000_0ff0;
+    kernel().write_physical(base, &0xdead_beef_u32.to_le_bytes());
+    kernel().write_physical(base + 0x10, b"phys\0");
+
+    unsafe {
+        assert_eq!(PhysicalMemory.read::<u32>(base), Ok(0xdead_beef));
+        assert_eq!(PhysicalMemory.read_cstring(base + 0x10, 16).unwrap(), b"phys");
+    }
+}
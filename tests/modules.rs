@@ -0,0 +1,200 @@
+#![cfg(feature = "host-sim")]
+
+mod common;
+
+use winkernel::basedef::ntstatus;
+use winkernel::ntstatus::NtStatus;
+use winkernel::process::{PeProcess, ProcessRef, UserModule};
+
+use common::kernel;
+
+const PEB: u64 = 0x7ff0_0000;
+const LDR: u64 = PEB + 0x100;
+const LIST_HEAD: u64 = LDR + 0x10;
+const ENTRIES: u64 = PEB + 0x200;
+const STRINGS: u64 = PEB + 0x1000;
+const IMAGES: u64 = 0x7ffa_0000_0000;
+const IMAGE_SIZE: usize = 0x1000;
+
+fn put(image: &mut [u8], offset: usize, bytes: &[u8]) {
+    image[offset..offset + bytes.len()].copy_from_slice(bytes);
+}
+
+/// A mapped PE32+ image whose exports are either code at an RVA or a forwarder string. Names
+/// must be sorted, as in a real export directory.
+fn image(name: &str, exports: &[(&str, Option<&str>)]) -> Vec<u8> {
+    let mut image = vec![0u8; IMAGE_SIZE];
+    put(&mut image, 0, b"MZ");
+    put(&mut image, 0x3C, &0x40u32.to_le_bytes());
+    put(&mut image, 0x40, b"PE\0\0");
+    put(&mut image, 0x44, &0x8664u16.to_le_bytes());
+    put(&mut image, 0x54, &0xF0u16.to_le_bytes());
+
+    let optional = 0x58;
+    put(&mut image, optional, &0x20Bu16.to_le_bytes());
+    put(&mut image, optional + 56, &(IMAGE_SIZE as u32).to_le_bytes());
+    put(&mut image, optional + 60, &0x200u32.to_le_bytes());
+    put(&mut image, optional + 108, &16u32.to_le_bytes());
+    put(&mut image, optional + 112, &0x200u32.to_le_bytes());
+    put(&mut image, optional + 116, &0x400u32.to_le_bytes());
+
+    let (functions, names, ordinals) = (0x240, 0x280, 0x2C0);
+    let mut strings = 0x300;
+    let mut string = |image: &mut Vec<u8>, s: &str| {
+        let rva = strings;
+        put(image, rva, s.as_bytes());
+        strings += s.len() + 1;
+        rva as u32
+    };
+
+    let name_rva = string(&mut image, name);
+    put(&mut image, 0x200 + 12, &name_rva.to_le_bytes());
+    put(&mut image, 0x200 + 16, &1u32.to_le_bytes());
+    put(&mut image, 0x200 + 20, &(exports.len() as u32).to_le_bytes());
+    put(&mut image, 0x200 + 24, &(exports.len() as u32).to_le_bytes());
+    put(&mut image, 0x200 + 28, &(functions as u32).to_le_bytes());
+    put(&mut image, 0x200 + 32, &(names as u32).to_le_bytes());
+    put(&mut image, 0x200 + 36, &(ordinals as u32).to_le_bytes());
+
+    for (i, (export, forwarder)) in exports.iter().enumerate() {
+        let target = match forwarder {
+            Some(forwarder) => string(&mut image, forwarder),
+            None => 0x800 + i as u32 * 0x10,
+        };
+        let export = string(&mut image, export);
+        put(&mut image, functions + i * 4, &target.to_le_bytes());
+        put(&mut image, names + i * 4, &export.to_le_bytes());
+        put(&mut image, ordinals + i * 2, &(i as u16).to_le_bytes());
+    }
+    image
+}
+
+fn unicode_string(length: usize, buffer: u64) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(length as u16).to_le_bytes());
+    bytes.extend_from_slice(&(length as u16).to_le_bytes());
+    bytes.extend_from_slice(&[0; 4]);
+    bytes.extend_from_slice(&buffer.to_le_bytes());
+    bytes
+}
+
+/// Builds a process whose loader list holds `modules` in order, each mapped with its image.
+fn process(pid: u64, modules: &[(&str, Vec<u8>)]) -> ProcessRef {
+    kernel().add_process(pid, "modules.exe");
+    kernel().set_process_peb(pid, PEB as usize);
+    kernel().map_process_memory(pid, PEB, 0x2000);
+    kernel().write_process_memory(pid, PEB + 0x18, &LDR.to_le_bytes());
+
+    let entry = |i: usize| ENTRIES + i as u64 * 0x100;
+    let link = |i: usize| if i < modules.len() { entry(i) } else { LIST_HEAD };
+    let last = if modules.is_empty() { LIST_HEAD } else { entry(modules.len() - 1) };
+    kernel().write_process_memory(pid, LIST_HEAD, &[link(0).to_le_bytes(), last.to_le_bytes()].concat());
+
+    for (i, (path, image)) in modules.iter().enumerate() {
+        let base = IMAGES + i as u64 * 0x10000;
+        kernel().map_process_memory(pid, base, IMAGE_SIZE);
+        kernel().write_process_memory(pid, base, image);
+
+        let full: Vec<u8> = path.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let base_name = path.rsplit('\\').next().unwrap();
+        let full_buffer = STRINGS + i as u64 * 0x100;
+        let base_buffer = full_buffer + (full.len() - base_name.len() * 2) as u64;
+        kernel().write_process_memory(pid, full_buffer, &full);
+
+        let previous = if i == 0 { LIST_HEAD } else { entry(i - 1) };
+        let mut bytes = [link(i + 1).to_le_bytes(), previous.to_le_bytes()].concat();
+        bytes.resize(0x30, 0);
+        bytes.extend_from_slice(&base.to_le_bytes());
+        bytes.extend_from_slice(&(base + 0x800).to_le_bytes());
+        bytes.extend_from_slice(&(IMAGE_SIZE as u64).to_le_bytes());
+        bytes.extend_from_slice(&unicode_string(full.len(), full_buffer));
+        bytes.extend_from_slice(&unicode_string(base_name.len() * 2, base_buffer));
+        kernel().write_process_memory(pid, entry(i), &bytes);
+    }
+
+    unsafe { PeProcess::by_pid(pid) }.unwrap()
+}
+
+fn standard_modules() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("C:\\Program Files\\App\\app.exe", image("app.exe", &[])),
+        ("C:\\Windows\\SYSTEM32\\ntdll.dll", image("ntdll.dll", &[("NtClose", None), ("RtlAllocateHeap", None)])),
+        (
+            "C:\\Windows\\System32\\KERNEL32.DLL",
+            image(
+                "KERNEL32.dll",
+                &[("CreateFileW", Some("api-ms-win-core-file-l1-1-0.CreateFileW")), ("HeapAlloc", Some("NTDLL.RtlAllocateHeap")), ("Sleep", None)],
+            ),
+        ),
+    ]
+}
+
+#[test]
+fn modules_come_from_the_load_order_list() {
+    let process = process(3001, &standard_modules());
+
+    let modules = unsafe { process.modules() }.unwrap();
+    let names: Vec<_> = modules.iter().map(|m| m.base_name.as_str()).collect();
+    assert_eq!(names, ["app.exe", "ntdll.dll", "KERNEL32.DLL"]);
+    assert_eq!(
+        modules[1],
+        UserModule {
+            base: IMAGES + 0x10000,
+            size: IMAGE_SIZE as u32,
+            full_name: "C:\\Windows\\SYSTEM32\\ntdll.dll".into(),
+            base_name: "ntdll.dll".into(),
+        }
+    );
+    assert!(modules[1].contains(IMAGES + 0x10fff));
+    assert!(!modules[1].contains(IMAGES + 0x11000));
+
+    unsafe {
+        assert_eq!(process.find_module("NTDLL.DLL").unwrap().unwrap().base, IMAGES + 0x10000);
+        assert_eq!(process.find_module("C:\\Windows\\System32\\kernel32.dll").unwrap().unwrap().base, IMAGES + 0x20000);
+        assert_eq!(process.find_module("user32.dll"), Ok(None));
+    }
+}
+
+#[test]
+fn exports_resolve_through_forwarders() {
+    let process = process(3002, &standard_modules());
+
+    unsafe {
+        let kernel32 = process.find_module("kernel32.dll").unwrap().unwrap();
+        assert_eq!(process.module_export(&kernel32, "Sleep"), Ok(Some(kernel32.base + 0x820)));
+        assert_eq!(process.module_export(&kernel32, "#3"), Ok(Some(kernel32.base + 0x820)));
+        assert_eq!(process.module_export(&kernel32, "HeapAlloc"), Ok(Some(IMAGES + 0x10000 + 0x810)));
+        assert_eq!(process.module_export(&kernel32, "ExitProcess"), Ok(None));
+        assert_eq!(process.module_export(&kernel32, "#9"), Ok(None));
+        assert_eq!(process.module_export(&kernel32, "CreateFileW"), Err(NtStatus(ntstatus::STATUS_DLL_NOT_FOUND)));
+
+        let app = process.find_module("app.exe").unwrap().unwrap();
+        assert_eq!(process.module_export(&app, "main"), Ok(None));
+    }
+}
+
+#[test]
+fn missing_or_broken_loader_data() {
+    kernel().add_process(3003, "nopeb.exe");
+    let no_peb = unsafe { PeProcess::by_pid(3003) }.unwrap();
+    assert_eq!(unsafe { no_peb.modules() }, Ok(Vec::new()));
+
+    let empty = process(3004, &[]);
+    assert_eq!(unsafe { empty.modules() }, Ok(Vec::new()));
+
+    // An entry that links to itself never gets back to the list head.
+    let cyclic = process(3005, &standard_modules()[..1]);
+    kernel().write_process_memory(3005, ENTRIES, &ENTRIES.to_le_bytes());
+    assert_eq!(unsafe { cyclic.modules() }, Err(NtStatus(ntstatus::STATUS_DATA_ERROR)));
+
+    // The loader list points at memory that isn't mapped.
+    let unmapped = process(3006, &standard_modules());
+    kernel().write_process_memory(3006, LIST_HEAD, &0x1234_0000u64.to_le_bytes());
+    assert_eq!(unsafe { unmapped.modules() }, Err(NtStatus(ntstatus::STATUS_PARTIAL_COPY)));
+
+    // A module whose headers have been paged out.
+    let paged_out = process(3007, &standard_modules());
+    let ntdll = unsafe { paged_out.find_module("ntdll.dll") }.unwrap().unwrap();
+    let moved = UserModule { base: 0x5555_0000, ..ntdll };
+    assert_eq!(unsafe { paged_out.module_export(&moved, "NtClose") }, Err(NtStatus(ntstatus::STATUS_PARTIAL_COPY)));
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "host-sim")]
+
+use winkernel::basedef::ntstatus;
+use winkernel::ntstatus::{errno, nt_error, nt_information, nt_success, nt_warning, win32, NtStatus, NtStatusType};
+
+fn status(code: u32) -> NtStatus {
+    NtStatus(code as i32)
+}
+
+#[test]
+fn accessors_split_the_status_fields() {
+    let denied = NtStatus(ntstatus::STATUS_ACCESS_DENIED);
+    assert_eq!(denied.severity(), NtStatusType::Error);
+    assert!(!denied.is_customer());
+    assert_eq!(denied.facility(), 0);
+    assert_eq!(denied.code(), 0x22);
+
+    let custom = status(0xE0DE_0042);
+    assert_eq!(custom.severity(), NtStatusType::Error);
+    assert!(custom.is_customer());
+    assert_eq!(custom.facility(), 0xDE);
+    assert_eq!(custom.code(), 0x42);
+
+    assert_eq!(status(0x0000_0000).severity(), NtStatusType::Success);
+    assert_eq!(status(0x4000_0000).severity(), NtStatusType::Information);
+    assert_eq!(status(0x8001_0001).severity(), NtStatusType::Warning);
+    assert_eq!(status(0x8001_0001).facility(), 1);
+}
+
+#[test]
+fn severity_predicates_and_results() {
+    for (code, success, information, warning, error) in [
+        (0x0000_0103, true, false, false, false),
+        (0x4000_0000, true, true, false, false),
+        (0x8000_0005, false, false, true, false),
+        (0xC000_0022, false, false, false, true),
+    ] {
+        assert_eq!(nt_success(code as i32), success, "{code:#x}");
+        assert_eq!(nt_information(code as i32), information, "{code:#x}");
+        assert_eq!(nt_warning(code as i32), warning, "{code:#x}");
+        assert_eq!(nt_error(code as i32), error, "{code:#x}");
+
+        let s = status(code);
+        assert_eq!(s.is_warning(), warning);
+        assert_eq!(s.is_error(), error);
+        assert_eq!(s.to_result().is_err(), error);
+        assert_eq!(s.to_result_with_value(7), if error { Err(s) } else { Ok(7) });
+    }
+}
+
+#[test]
+fn display_uses_the_name_or_the_hex_code() {
+    let denied = NtStatus(ntstatus::STATUS_ACCESS_DENIED);
+    assert_eq!(denied.name(), Some("STATUS_ACCESS_DENIED"));
+    assert_eq!(denied.to_string(), "STATUS_ACCESS_DENIED");
+    assert_eq!(format!("{denied:?}"), "STATUS_ACCESS_DENIED (0xC0000022)");
+
+    let unknown = status(0xC0DE_0001);
+    assert_eq!(unknown.name(), None);
+    assert_eq!(unknown.to_string(), "0xC0DE0001");
+    assert_eq!(format!("{unknown:?}"), "NtStatus(0xC0DE0001)");
+    assert_eq!(status(0x42).to_string(), "0x00000042");
+}
+
+#[test]
+fn to_win32_passes_ntwin32_codes_through() {
+    assert_eq!(status(0xC007_0005).to_win32(), win32::ERROR_ACCESS_DENIED);
+    assert_eq!(status(0x8007_00EA).to_win32(), win32::ERROR_MORE_DATA);
+    assert_eq!(status(0xC007_FFFF).to_win32(), 0xFFFF);
+}
+
+#[test]
+fn to_win32_maps_known_and_unknown_statuses() {
+    assert_eq!(NtStatus(ntstatus::STATUS_SUCCESS).to_win32(), win32::ERROR_SUCCESS);
+    assert_eq!(NtStatus(ntstatus::STATUS_ACCESS_DENIED).to_win32(), win32::ERROR_ACCESS_DENIED);
+    assert_eq!(NtStatus(ntstatus::STATUS_BUFFER_OVERFLOW).to_win32(), win32::ERROR_MORE_DATA);
+    assert_eq!(status(0xC0DE_0001).to_win32(), win32::ERROR_MR_MID_NOT_FOUND);
+}
+
+#[test]
+fn exception_statuses_map_like_rtl_nt_status_to_dos_error() {
+    assert_eq!(NtStatus(ntstatus::STATUS_ACCESS_VIOLATION).to_win32(), win32::ERROR_NOACCESS);
+    assert_eq!(NtStatus(ntstatus::STATUS_BREAKPOINT).to_win32(), win32::EXCEPTION_BREAKPOINT);
+    assert_eq!(NtStatus(ntstatus::STATUS_INTEGER_DIVIDE_BY_ZERO).to_win32(), win32::EXCEPTION_INT_DIVIDE_BY_ZERO);
+
+    // The exception codes are the statuses themselves.
+    for code in [0x8000_0001, 0x8000_0003, 0x8000_0004, 0xC000_001D, 0xC000_0025, 0xC000_008C, 0xC000_0094, 0xC000_0096] {
+        assert_eq!(status(code).to_win32(), code, "{code:#x}");
+    }
+}
+
+#[test]
+fn to_errno_maps_known_statuses_and_falls_back_by_severity() {
+    assert_eq!(NtStatus(ntstatus::STATUS_ACCESS_DENIED).to_errno(), errno::EACCES);
+    assert_eq!(NtStatus(ntstatus::STATUS_OBJECT_NAME_NOT_FOUND).to_errno(), errno::ENOENT);
+    assert_eq!(NtStatus(ntstatus::STATUS_SUCCESS).to_errno(), 0);
+
+    assert_eq!(status(0x0000_0042).to_errno(), 0);
+    assert_eq!(status(0x40DE_0001).to_errno(), 0);
+    assert_eq!(status(0x80DE_0001).to_errno(), errno::EIO);
+    assert_eq!(status(0xC0DE_0001).to_errno(), errno::EIO);
+}
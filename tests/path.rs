@@ -0,0 +1,175 @@
+#![cfg(feature = "host-sim")]
+
+use winkernel::basedef::ntstatus;
+use winkernel::ntstatus::NtStatus;
+use winkernel::path::{DriveMap, Namespace, NtPath};
+
+fn drives() -> DriveMap {
+    let mut map = DriveMap::new();
+    map.insert('C', "\\Device\\HarddiskVolume3").unwrap();
+    map.insert('d', "\\Device\\CdRom0\\").unwrap();
+    map.set_system_root("C:\\Windows\\").unwrap();
+    map
+}
+
+#[test]
+fn namespaces_are_classified_by_prefix() {
+    let cases = [
+        ("\\Device\\HarddiskVolume3\\Windows", Namespace::Device, "\\Device\\HarddiskVolume3"),
+        ("\\device\\Mup\\server\\share", Namespace::Device, "\\device\\Mup"),
+        ("\\??\\C:\\Windows", Namespace::DosDevices, "\\??\\C:"),
+        ("\\DosDevices\\C:\\Windows", Namespace::DosDevices, "\\DosDevices\\C:"),
+        ("\\??\\UNC\\server\\share\\dir", Namespace::DosDevices, "\\??\\UNC\\server\\share"),
+        ("\\\\?\\C:\\Windows", Namespace::DosDevices, "\\\\?\\C:"),
+        ("\\\\.\\PhysicalDrive0", Namespace::DosDevices, "\\\\.\\PhysicalDrive0"),
+        ("\\GLOBAL??\\C:\\Windows", Namespace::GlobalDosDevices, "\\GLOBAL??\\C:"),
+        ("\\SystemRoot\\system32\\ntoskrnl.exe", Namespace::SystemRoot, "\\SystemRoot"),
+        ("\\REGISTRY\\Machine\\Software", Namespace::Registry, "\\REGISTRY"),
+        ("\\\\server\\share\\dir\\file", Namespace::Unc, "\\\\server\\share"),
+        ("C:\\Windows", Namespace::Drive, "C:"),
+        ("\\BaseNamedObjects\\Event", Namespace::Object, ""),
+        ("\\Devices\\Thing", Namespace::Object, ""),
+        ("\\SystemRootX", Namespace::Object, ""),
+        ("system32\\drivers", Namespace::Relative, ""),
+        ("C:drivers", Namespace::Relative, ""),
+        ("", Namespace::Relative, ""),
+        ("日本\\x", Namespace::Relative, ""),
+        ("😀\\x", Namespace::Relative, ""),
+        ("é:\\x", Namespace::Relative, ""),
+        ("C日\\x", Namespace::Relative, ""),
+        ("C:日本", Namespace::Relative, ""),
+        ("C:\\日本", Namespace::Drive, "C:"),
+        ("\\Device\\日本\\x", Namespace::Device, "\\Device\\日本"),
+    ];
+
+    for (path, namespace, root) in cases.iter() {
+        let parsed = NtPath::parse(path);
+        assert_eq!(parsed.namespace(), *namespace, "{}", path);
+        assert_eq!(parsed.root(), *root, "{}", path);
+        assert_eq!(parsed.is_absolute(), *namespace != Namespace::Relative, "{}", path);
+    }
+}
+
+#[test]
+fn components_skip_the_root_and_empty_parts() {
+    let path = NtPath::parse("\\Device\\HarddiskVolume3\\\\Windows\\.\\System32\\");
+    assert_eq!(path.components().collect::<Vec<_>>(), ["Windows", ".", "System32"]);
+    assert_eq!(path.file_name(), Some("System32"));
+
+    let path = NtPath::parse("\\\\server\\share\\dir\\file.txt");
+    assert_eq!(path.components().collect::<Vec<_>>(), ["dir", "file.txt"]);
+    assert_eq!(path.file_name(), Some("file.txt"));
+
+    assert_eq!(NtPath::parse("\\SystemRoot\\system32\\ntoskrnl.exe").file_name(), Some("ntoskrnl.exe"));
+    assert_eq!(NtPath::parse("C:\\").file_name(), None);
+    assert_eq!(NtPath::parse("\\Device\\HarddiskVolume3").components().count(), 0);
+}
+
+#[test]
+fn normalize_resolves_dots_without_leaving_the_root() {
+    let cases = [
+        ("\\Device\\HarddiskVolume3\\Windows\\.\\System32\\..\\SysWOW64\\", "\\Device\\HarddiskVolume3\\Windows\\SysWOW64"),
+        ("\\Device\\HarddiskVolume3\\..\\..\\Windows", "\\Device\\HarddiskVolume3\\Windows"),
+        ("\\Device\\HarddiskVolume3\\Windows\\..", "\\Device\\HarddiskVolume3\\"),
+        ("\\Device\\HarddiskVolume3", "\\Device\\HarddiskVolume3"),
+        ("\\??\\C:\\..\\Windows", "\\??\\C:\\Windows"),
+        ("\\??\\UNC\\server\\share\\..\\dir", "\\??\\UNC\\server\\share\\dir"),
+        ("\\\\server\\share\\a\\..\\..\\b", "\\\\server\\share\\b"),
+        ("C:\\Windows\\\\System32\\.", "C:\\Windows\\System32"),
+        ("C:\\..", "C:\\"),
+        ("\\Registry\\Machine\\Software\\..\\System", "\\Registry\\Machine\\System"),
+        ("\\BaseNamedObjects\\..\\..", "\\"),
+        ("a\\.\\b\\..\\..\\..\\c", "..\\c"),
+        ("..\\..\\a", "..\\..\\a"),
+        ("a\\..", "."),
+    ];
+
+    for (path, normalized) in cases.iter() {
+        assert_eq!(NtPath::parse(path).normalize(), *normalized, "{}", path);
+    }
+}
+
+#[test]
+fn dos_paths_convert_to_device_paths() {
+    let map = drives();
+    let cases = [
+        ("C:\\Windows\\System32\\..\\notepad.exe", "\\Device\\HarddiskVolume3\\Windows\\notepad.exe"),
+        ("c:/Windows/System32", "\\Device\\HarddiskVolume3\\Windows\\System32"),
+        ("C:\\", "\\Device\\HarddiskVolume3\\"),
+        ("D:\\setup.exe", "\\Device\\CdRom0\\setup.exe"),
+        ("\\??\\C:\\Windows", "\\Device\\HarddiskVolume3\\Windows"),
+        ("\\??\\C:", "\\Device\\HarddiskVolume3"),
+        ("\\DosDevices\\d:\\setup.exe", "\\Device\\CdRom0\\setup.exe"),
+        ("\\GLOBAL??\\C:\\Windows", "\\Device\\HarddiskVolume3\\Windows"),
+        ("\\\\?\\C:\\Windows", "\\Device\\HarddiskVolume3\\Windows"),
+        ("\\\\server\\share\\dir", "\\Device\\Mup\\server\\share\\dir"),
+        ("//server/share/dir", "\\Device\\Mup\\server\\share\\dir"),
+        ("\\??\\UNC\\server\\share\\dir", "\\Device\\Mup\\server\\share\\dir"),
+        ("\\\\.\\PhysicalDrive0", "\\??\\PhysicalDrive0"),
+        ("\\SystemRoot\\system32\\.\\ntoskrnl.exe", "\\SystemRoot\\system32\\ntoskrnl.exe"),
+        ("\\Registry\\Machine\\Software", "\\Registry\\Machine\\Software"),
+        ("\\Device\\HarddiskVolume3\\a/b", "\\Device\\HarddiskVolume3\\a/b"),
+    ];
+
+    for (dos, nt) in cases.iter() {
+        assert_eq!(map.to_nt(dos).as_deref(), Ok(*nt), "{}", dos);
+    }
+
+    assert_eq!(map.to_nt("E:\\file"), Err(NtStatus(ntstatus::STATUS_OBJECT_PATH_NOT_FOUND)));
+    assert_eq!(map.to_nt("Windows\\System32"), Err(NtStatus(ntstatus::STATUS_OBJECT_PATH_SYNTAX_BAD)));
+    assert_eq!(map.to_nt("C:Windows"), Err(NtStatus(ntstatus::STATUS_OBJECT_PATH_SYNTAX_BAD)));
+}
+
+#[test]
+fn device_paths_convert_to_dos_paths() {
+    let map = drives();
+    let cases = [
+        ("\\Device\\HarddiskVolume3\\Windows\\System32", "C:\\Windows\\System32"),
+        ("\\device\\harddiskvolume3\\Windows\\..\\Users", "C:\\Users"),
+        ("\\Device\\HarddiskVolume3\\", "C:\\"),
+        ("\\Device\\CdRom0\\setup.exe", "D:\\setup.exe"),
+        ("\\Device\\Mup\\server\\share\\dir", "\\\\server\\share\\dir"),
+        ("\\??\\C:\\Windows", "C:\\Windows"),
+        ("\\GLOBAL??\\e:\\file", "E:\\file"),
+        ("\\??\\UNC\\server\\share\\dir", "\\\\server\\share\\dir"),
+        ("\\??\\PhysicalDrive0", "\\\\.\\PhysicalDrive0"),
+        ("\\SystemRoot\\system32\\ntoskrnl.exe", "C:\\Windows\\system32\\ntoskrnl.exe"),
+        ("C:\\Windows\\.\\System32", "C:\\Windows\\System32"),
+        ("\\\\server\\share\\..\\dir", "\\\\server\\share\\dir"),
+    ];
+
+    for (nt, dos) in cases.iter() {
+        assert_eq!(map.to_dos(nt).as_deref(), Ok(*dos), "{}", nt);
+    }
+
+    assert_eq!(map.to_dos("\\Device\\HarddiskVolume7\\file"), Err(NtStatus(ntstatus::STATUS_OBJECT_PATH_NOT_FOUND)));
+    assert_eq!(map.to_dos("\\Registry\\Machine"), Err(NtStatus(ntstatus::STATUS_OBJECT_PATH_SYNTAX_BAD)));
+    assert_eq!(map.to_dos("\\BaseNamedObjects\\Event"), Err(NtStatus(ntstatus::STATUS_OBJECT_PATH_SYNTAX_BAD)));
+    assert_eq!(
+        DriveMap::new().to_dos("\\SystemRoot\\system32"),
+        Err(NtStatus(ntstatus::STATUS_OBJECT_PATH_NOT_FOUND))
+    );
+}
+
+#[test]
+fn conversions_round_trip() {
+    let map = drives();
+    for dos in ["C:\\Windows\\System32\\drivers\\etc\\hosts", "D:\\", "\\\\server\\share\\a\\b"].iter() {
+        let nt = map.to_nt(dos).unwrap();
+        assert_eq!(map.to_dos(&nt).unwrap(), *dos);
+    }
+}
+
+#[test]
+fn drive_map_rejects_bad_entries() {
+    let mut map = DriveMap::new();
+    assert_eq!(map.insert('1', "\\Device\\HarddiskVolume1"), Err(NtStatus(ntstatus::STATUS_OBJECT_NAME_INVALID)));
+    assert_eq!(map.insert('C', "C:\\"), Err(NtStatus(ntstatus::STATUS_OBJECT_PATH_SYNTAX_BAD)));
+    assert_eq!(map.set_system_root("\\SystemRoot"), Err(NtStatus(ntstatus::STATUS_OBJECT_PATH_SYNTAX_BAD)));
+    assert_eq!(map.device('C'), None);
+
+    map.insert('c', "\\Device\\HarddiskVolume1").unwrap();
+    map.insert('C', "\\Device\\HarddiskVolume3").unwrap();
+    assert_eq!(map.device('c'), Some("\\Device\\HarddiskVolume3"));
+    assert_eq!(map.device('?'), None);
+}
@@ -0,0 +1,505 @@
+#![cfg(feature = "host-sim")]
+
+mod common;
+
+use common::kernel;
+use winkernel::basedef::ntstatus;
+use winkernel::kernel::find_kernel_module;
+use winkernel::ntstatus::NtStatus;
+use winkernel::sim::SimModule;
+use winkernel::pe::{DataDirectory, Export, ExportTarget, ImageLayout, ImageReader, Import, ImportModule, ImportName, PeError, PeImage, Relocation};
+use winkernel::pe::{IMAGE_DIRECTORY_ENTRY_BASERELOC, IMAGE_DIRECTORY_ENTRY_EXPORT, IMAGE_DIRECTORY_ENTRY_IMPORT, IMAGE_REL_BASED_DIR64, IMAGE_REL_BASED_HIGHLOW};
+
+const NT: usize = 0x80;
+const SIZE_OF_HEADERS: usize = 0x400;
+const SIZE_OF_IMAGE: usize = 0x5000;
+const EXPORTS: u32 = 0x2000;
+const EXPORTS_SIZE: u32 = 0x200;
+const IMPORTS: u32 = 0x2200;
+const RELOCS: u32 = 0x3000;
+
+/// `(name, virtual address, virtual size, pointer to raw data, size of raw data)`. `.bss` has no
+/// file data, and the tail of `.rdata` past its raw data only exists once mapped.
+const SECTIONS: [(&[u8; 8], u32, u32, u32, u32); 4] = [
+    (b".text\0\0\0", 0x1000, 0x100, 0x400, 0x200),
+    (b".rdata\0\0", 0x2000, 0x700, 0x600, 0x600),
+    (b".reloc\0\0", 0x3000, 0x20, 0xC00, 0x200),
+    (b".bss\0\0\0\0", 0x4000, 0x1000, 0, 0),
+];
+
+fn put(image: &mut [u8], offset: usize, bytes: &[u8]) {
+    image[offset..offset + bytes.len()].copy_from_slice(bytes);
+}
+
+fn put16(image: &mut [u8], offset: usize, value: u16) {
+    put(image, offset, &value.to_le_bytes());
+}
+
+fn put32(image: &mut [u8], offset: usize, value: u32) {
+    put(image, offset, &value.to_le_bytes());
+}
+
+fn put_thunk(image: &mut [u8], is_64bit: bool, offset: usize, value: u64) {
+    match is_64bit {
+        true => put(image, offset, &value.to_le_bytes()),
+        false => put32(image, offset, value as u32),
+    }
+}
+
+/// A test DLL as laid out by the loader, with exports, imports and relocations.
+fn mapped_image(is_64bit: bool) -> Vec<u8> {
+    let mut image = vec![0u8; SIZE_OF_IMAGE];
+    put(&mut image, 0, b"MZ");
+    put32(&mut image, 0x3C, NT as u32);
+    put(&mut image, NT, b"PE\0\0");
+
+    let file_header = NT + 4;
+    put16(&mut image, file_header, if is_64bit { 0x8664 } else { 0x14C });
+    put16(&mut image, file_header + 2, SECTIONS.len() as u16);
+    put32(&mut image, file_header + 4, 0x5F00_0000);
+    let size_of_optional_header = if is_64bit { 0xF0 } else { 0xE0 };
+    put16(&mut image, file_header + 16, size_of_optional_header);
+    put16(&mut image, file_header + 18, 0x2022);
+
+    let optional = file_header + 20;
+    let directories = if is_64bit {
+        put16(&mut image, optional, 0x20B);
+        put(&mut image, optional + 24, &0x1_8000_0000u64.to_le_bytes());
+        optional + 108
+    } else {
+        put16(&mut image, optional, 0x10B);
+        put32(&mut image, optional + 28, 0x1000_0000);
+        optional + 92
+    };
+    put32(&mut image, optional + 16, 0x1000);
+    put32(&mut image, optional + 32, 0x1000);
+    put32(&mut image, optional + 36, 0x200);
+    put32(&mut image, optional + 56, SIZE_OF_IMAGE as u32);
+    put32(&mut image, optional + 60, SIZE_OF_HEADERS as u32);
+    put32(&mut image, optional + 64, 0xABCD);
+    put16(&mut image, optional + 68, 1);
+    put16(&mut image, optional + 70, 0x160);
+    put32(&mut image, directories, 16);
+    let directory = |image: &mut Vec<u8>, index: usize, rva: u32, size: u32| {
+        put32(image, directories + 4 + index * 8, rva);
+        put32(image, directories + 8 + index * 8, size);
+    };
+    directory(&mut image, IMAGE_DIRECTORY_ENTRY_EXPORT, EXPORTS, EXPORTS_SIZE);
+    directory(&mut image, IMAGE_DIRECTORY_ENTRY_IMPORT, IMPORTS, 0x3C);
+    directory(&mut image, IMAGE_DIRECTORY_ENTRY_BASERELOC, RELOCS, 0x1C);
+
+    let section_table = optional + size_of_optional_header as usize;
+    for (i, (name, virtual_address, virtual_size, pointer, raw_size)) in SECTIONS.iter().enumerate() {
+        let header = section_table + i * 40;
+        put(&mut image, header, &name[..]);
+        put32(&mut image, header + 8, *virtual_size);
+        put32(&mut image, header + 12, *virtual_address);
+        put32(&mut image, header + 16, *raw_size);
+        put32(&mut image, header + 20, *pointer);
+        put32(&mut image, header + 36, 0x4000_0040);
+    }
+
+    // Five functions with ordinal base 5: code, a gap, a forwarder, code, and an ordinal-only
+    // export. The name table is sorted.
+    let e = EXPORTS as usize;
+    put32(&mut image, e + 12, 0x2100);
+    put32(&mut image, e + 16, 5);
+    put32(&mut image, e + 20, 5);
+    put32(&mut image, e + 24, 3);
+    put32(&mut image, e + 28, 0x2040);
+    put32(&mut image, e + 32, 0x2060);
+    put32(&mut image, e + 36, 0x2070);
+    for (i, rva) in [0x1010, 0, 0x2130, 0x1020, 0x1030].into_iter().enumerate() {
+        put32(&mut image, 0x2040 + i * 4, rva);
+    }
+    for (i, (name, index)) in [(0x2110, 0), (0x2116, 3), (0x211B, 2)].into_iter().enumerate() {
+        put32(&mut image, 0x2060 + i * 4, name);
+        put16(&mut image, 0x2070 + i * 2, index);
+    }
+    put(&mut image, 0x2100, b"test.dll\0");
+    put(&mut image, 0x2110, b"Alpha\0Beta\0Forward\0");
+    put(&mut image, 0x2130, b"NTDLL.RtlForwarded\0");
+
+    // KERNEL32.dll has a lookup table, ntdll.dll only its IAT as in a bound image.
+    let i = IMPORTS as usize;
+    put32(&mut image, i, 0x2300);
+    put32(&mut image, i + 12, 0x2480);
+    put32(&mut image, i + 16, 0x2340);
+    put32(&mut image, i + 32, 0x2490);
+    put32(&mut image, i + 36, 0x2380);
+    let (thunk, ordinal_flag) = if is_64bit { (8, 1u64 << 63) } else { (4, 1u64 << 31) };
+    for table in [0x2300, 0x2340] {
+        put_thunk(&mut image, is_64bit, table, 0x2400);
+        put_thunk(&mut image, is_64bit, table + thunk, ordinal_flag | 0x2A);
+    }
+    put_thunk(&mut image, is_64bit, 0x2380, 0x2420);
+    put16(&mut image, 0x2400, 0x10);
+    put(&mut image, 0x2402, b"Sleep\0");
+    put16(&mut image, 0x2420, 3);
+    put(&mut image, 0x2422, b"NtClose\0");
+    put(&mut image, 0x2480, b"KERNEL32.dll\0");
+    put(&mut image, 0x2490, b"ntdll.dll\0");
+
+    let kind = (if is_64bit { IMAGE_REL_BASED_DIR64 } else { IMAGE_REL_BASED_HIGHLOW }) as u16;
+    let r = RELOCS as usize;
+    put32(&mut image, r, 0x1000);
+    put32(&mut image, r + 4, 16);
+    for (j, entry) in [(kind << 12) | 0x10, (kind << 12) | 0x18, 0, (kind << 12) | 0x20].into_iter().enumerate() {
+        put16(&mut image, r + 8 + j * 2, entry);
+    }
+    put32(&mut image, r + 16, 0x2000);
+    put32(&mut image, r + 20, 12);
+    put16(&mut image, r + 24, (kind << 12) | 0x8);
+
+    image
+}
+
+/// The same image as it would be stored on disk.
+fn file_image(is_64bit: bool) -> Vec<u8> {
+    let mapped = mapped_image(is_64bit);
+    let mut file = vec![0u8; 0xE00];
+    file[..SIZE_OF_HEADERS].copy_from_slice(&mapped[..SIZE_OF_HEADERS]);
+    for (_, virtual_address, _, pointer, raw_size) in SECTIONS {
+        let (va, pointer, raw_size) = (virtual_address as usize, pointer as usize, raw_size as usize);
+        file[pointer..pointer + raw_size].copy_from_slice(&mapped[va..va + raw_size]);
+    }
+    file
+}
+
+/// Every combination of PE32/PE32+ and file/mapped layout.
+fn images() -> Vec<(bool, ImageLayout, Vec<u8>)> {
+    [false, true]
+        .into_iter()
+        .flat_map(|is_64bit| [(is_64bit, ImageLayout::File, file_image(is_64bit)), (is_64bit, ImageLayout::Mapped, mapped_image(is_64bit))])
+        .collect()
+}
+
+#[test]
+fn headers_of_pe32_and_pe32_plus() {
+    let pe32_bytes = file_image(false);
+    let pe32 = PeImage::from_file_bytes(&pe32_bytes).unwrap();
+    let headers = pe32.headers();
+    assert!(!headers.is_64bit);
+    assert_eq!((headers.machine, headers.image_base), (0x14C, 0x1000_0000));
+
+    let pe64_bytes = file_image(true);
+    let pe64 = PeImage::from_file_bytes(&pe64_bytes).unwrap();
+    let headers = pe64.headers();
+    assert!(headers.is_64bit);
+    assert_eq!((headers.machine, headers.image_base), (0x8664, 0x1_8000_0000));
+
+    for image in [&pe32, &pe64] {
+        let headers = image.headers();
+        assert_eq!((headers.time_date_stamp, headers.characteristics), (0x5F00_0000, 0x2022));
+        assert_eq!((headers.address_of_entry_point, headers.section_alignment, headers.file_alignment), (0x1000, 0x1000, 0x200));
+        assert_eq!((headers.size_of_image, headers.size_of_headers), (SIZE_OF_IMAGE as u32, SIZE_OF_HEADERS as u32));
+        assert_eq!((headers.checksum, headers.subsystem, headers.dll_characteristics), (0xABCD, 1, 0x160));
+
+        assert_eq!(image.data_directories().len(), 16);
+        assert_eq!(image.data_directory(IMAGE_DIRECTORY_ENTRY_EXPORT), Some(DataDirectory { virtual_address: EXPORTS, size: EXPORTS_SIZE }));
+        assert_eq!(image.data_directory(2), None);
+        assert_eq!(image.data_directory(16), None);
+    }
+}
+
+#[test]
+fn sections_are_parsed() {
+    let bytes = file_image(true);
+    let image = PeImage::from_file_bytes(&bytes).unwrap();
+
+    let names: Vec<_> = image.sections().iter().map(|s| s.name()).collect();
+    assert_eq!(names, [".text", ".rdata", ".reloc", ".bss"]);
+
+    let rdata = image.section_by_name(".rdata").unwrap();
+    assert_eq!((rdata.virtual_address, rdata.virtual_size, rdata.pointer_to_raw_data, rdata.size_of_raw_data), (0x2000, 0x700, 0x600, 0x600));
+    assert_eq!(rdata.characteristics, 0x4000_0040);
+    assert!(image.section_by_name(".data").is_none());
+
+    assert_eq!(image.section_containing(0x10ff).unwrap().name(), ".text");
+    // The raw data is larger than the virtual size, so it still counts.
+    assert_eq!(image.section_containing(0x11ff).unwrap().name(), ".text");
+    assert!(image.section_containing(0x1200).is_none());
+    assert_eq!(image.section_containing(0x4fff).unwrap().name(), ".bss");
+}
+
+#[test]
+fn rva_to_offset_depends_on_the_layout() {
+    let file_bytes = file_image(false);
+    let file = PeImage::from_file_bytes(&file_bytes).unwrap();
+    assert_eq!(file.rva_to_offset(0x3C), Ok(0x3C));
+    assert_eq!(file.rva_to_offset(0x1010), Ok(0x410));
+    assert_eq!(file.rva_to_offset(0x2004), Ok(0x604));
+    assert_eq!(file.rva_to_offset(0x3000), Ok(0xC00));
+    // Past the raw data of `.rdata`, in `.bss` and outside every section.
+    assert_eq!(file.rva_to_offset(0x2650), Err(PeError::BadRva(0x2650)));
+    assert_eq!(file.rva_to_offset(0x4000), Err(PeError::BadRva(0x4000)));
+    assert_eq!(file.rva_to_offset(0x9000), Err(PeError::BadRva(0x9000)));
+
+    let mapped_bytes = mapped_image(false);
+    let mapped = PeImage::from_mapped_bytes(&mapped_bytes).unwrap();
+    for rva in [0x3C, 0x1010, 0x2650, 0x4000, 0x9000] {
+        assert_eq!(mapped.rva_to_offset(rva), Ok(rva as usize));
+    }
+
+    assert_eq!(file.read_u32_rva(0x2000 + 16), Ok(5));
+    assert_eq!(mapped.read_u32_rva(0x2000 + 16), Ok(5));
+    assert_eq!(mapped.read_u32_rva(0x9000), Err(PeError::OutOfBounds { offset: 0x9000, len: 4 }));
+}
+
+#[test]
+fn imports_of_every_layout() {
+    for (is_64bit, layout, bytes) in images() {
+        let image = PeImage::parse(&bytes[..], layout).unwrap();
+        let thunk = if is_64bit { 8 } else { 4 };
+        assert_eq!(
+            image.imports().unwrap(),
+            [
+                ImportModule {
+                    name: "KERNEL32.dll".into(),
+                    imports: vec![
+                        Import { name: ImportName::Name { hint: 0x10, name: "Sleep".into() }, iat_rva: 0x2340 },
+                        Import { name: ImportName::Ordinal(0x2A), iat_rva: 0x2340 + thunk },
+                    ],
+                },
+                ImportModule {
+                    name: "ntdll.dll".into(),
+                    imports: vec![Import { name: ImportName::Name { hint: 3, name: "NtClose".into() }, iat_rva: 0x2380 }],
+                },
+            ],
+            "{:?} {:?}",
+            is_64bit,
+            layout
+        );
+    }
+}
+
+#[test]
+fn relocations_of_every_layout() {
+    for (is_64bit, layout, bytes) in images() {
+        let image = PeImage::parse(&bytes[..], layout).unwrap();
+        let kind = if is_64bit { IMAGE_REL_BASED_DIR64 } else { IMAGE_REL_BASED_HIGHLOW };
+        let expected: Vec<_> = [0x1010, 0x1018, 0x1020, 0x2008].into_iter().map(|rva| Relocation { rva, kind }).collect();
+        assert_eq!(image.relocations().unwrap(), expected, "{:?} {:?}", is_64bit, layout);
+    }
+}
+
+#[test]
+fn exports_by_name_and_ordinal() {
+    for (is_64bit, layout, bytes) in images() {
+        let image = PeImage::parse(&bytes[..], layout).unwrap();
+        let context = format!("{:?} {:?}", is_64bit, layout);
+        let forwarder = ExportTarget::Forwarder("NTDLL.RtlForwarded".into());
+
+        assert_eq!(image.export_name().unwrap(), "test.dll", "{}", context);
+        assert_eq!(
+            image.exports().unwrap(),
+            [
+                Export { name: Some("Alpha".into()), ordinal: 5, target: ExportTarget::Rva(0x1010) },
+                Export { name: Some("Forward".into()), ordinal: 7, target: forwarder.clone() },
+                Export { name: Some("Beta".into()), ordinal: 8, target: ExportTarget::Rva(0x1020) },
+                Export { name: None, ordinal: 9, target: ExportTarget::Rva(0x1030) },
+            ],
+            "{}",
+            context
+        );
+
+        assert_eq!(image.export_by_name("Alpha"), Ok(Some(ExportTarget::Rva(0x1010))));
+        assert_eq!(image.export_by_name("Beta"), Ok(Some(ExportTarget::Rva(0x1020))));
+        assert_eq!(image.export_by_name("Forward"), Ok(Some(forwarder.clone())));
+        assert_eq!(image.export_by_name("Gamma"), Ok(None));
+        assert_eq!(image.export_by_name("Aardvark"), Ok(None));
+
+        assert_eq!(image.export_by_ordinal(4), Ok(None));
+        assert_eq!(image.export_by_ordinal(5), Ok(Some(ExportTarget::Rva(0x1010))));
+        assert_eq!(image.export_by_ordinal(6), Ok(None));
+        assert_eq!(image.export_by_ordinal(7), Ok(Some(forwarder)));
+        assert_eq!(image.export_by_ordinal(9), Ok(Some(ExportTarget::Rva(0x1030))));
+        assert_eq!(image.export_by_ordinal(10), Ok(None));
+    }
+}
+
+#[test]
+fn malformed_headers() {
+    let good = file_image(true);
+    let parse = |bytes: &[u8]| PeImage::from_file_bytes(bytes).err();
+
+    let mut bytes = good.clone();
+    bytes[0] = b'X';
+    assert_eq!(parse(&bytes), Some(PeError::BadDosSignature));
+
+    let mut bytes = good.clone();
+    bytes[NT] = b'N';
+    assert_eq!(parse(&bytes), Some(PeError::BadNtSignature));
+
+    let mut bytes = good.clone();
+    put16(&mut bytes, NT + 24, 0x107);
+    assert_eq!(parse(&bytes), Some(PeError::BadOptionalHeaderMagic(0x107)));
+
+    let mut bytes = good.clone();
+    put32(&mut bytes, 0x3C, 0xFFFF_FFF0);
+    assert_eq!(parse(&bytes), Some(PeError::OutOfBounds { offset: 0xFFFF_FFF0, len: 4 }));
+
+    assert_eq!(parse(&good[..0x3E]), Some(PeError::OutOfBounds { offset: 0x3C, len: 4 }));
+    assert_eq!(parse(&good[..0x100]), Some(PeError::OutOfBounds { offset: 0x104, len: 4 }));
+    assert_eq!(parse(&[]), Some(PeError::OutOfBounds { offset: 0, len: 2 }));
+
+    // NumberOfSections claims more headers than there are bytes.
+    let mut bytes = good.clone();
+    put16(&mut bytes, NT + 6, 0x400);
+    assert!(matches!(parse(&bytes[..SIZE_OF_HEADERS]), Some(PeError::OutOfBounds { .. })));
+
+    // NumberOfRvaAndSizes is clamped to what fits in the optional header.
+    let mut bytes = good.clone();
+    put32(&mut bytes, NT + 24 + 108, 0xFFFF);
+    assert_eq!(PeImage::from_file_bytes(&bytes).unwrap().data_directories().len(), 16);
+    let mut bytes = good.clone();
+    put16(&mut bytes, NT + 20, 0xF0 - 8 * 4);
+    assert_eq!(PeImage::from_file_bytes(&bytes).unwrap().data_directories().len(), 12);
+
+    let error = PeError::BadOptionalHeaderMagic(0x107);
+    assert_eq!(NtStatus::from(error), NtStatus(ntstatus::STATUS_INVALID_IMAGE_FORMAT));
+    assert_eq!(error.to_string(), "bad optional header magic 0x107");
+}
+
+#[test]
+fn malformed_directories_and_names() {
+    let mut bytes = mapped_image(true);
+
+    // An invalid UTF-8 name spanning several read chunks reports where it starts.
+    put(&mut bytes, 0x2500, &[b'a'; 40]);
+    put(&mut bytes, 0x2528, b"\xff\0");
+    // An unterminated name runs into the length limit.
+    put(&mut bytes, 0x2600, &[b'b'; 0x100]);
+    put(&mut bytes, 0x2700, &[b'c'; 0x200]);
+    put(&mut bytes, SIZE_OF_IMAGE - 4, b"tail");
+
+    let image = PeImage::from_mapped_bytes(&bytes).unwrap();
+    assert_eq!(image.read_cstr_rva(0x2402), Ok("Sleep".into()));
+    assert_eq!(image.read_cstr_rva(0x2500), Err(PeError::BadName(0x2500)));
+    assert_eq!(image.read_cstr_rva(0x2600), Err(PeError::BadName(0x2600)));
+    // The end of the image cuts the name short.
+    assert_eq!(image.read_cstr_rva(SIZE_OF_IMAGE as u32 - 4), Err(PeError::OutOfBounds { offset: SIZE_OF_IMAGE, len: 1 }));
+
+    // Without an export directory.
+    put32(&mut bytes, NT + 24 + 112, 0);
+    let image = PeImage::from_mapped_bytes(&bytes).unwrap();
+    assert_eq!(image.exports(), Err(PeError::MissingDirectory(IMAGE_DIRECTORY_ENTRY_EXPORT)));
+    assert_eq!(image.export_by_name("Alpha"), Err(PeError::MissingDirectory(IMAGE_DIRECTORY_ENTRY_EXPORT)));
+    assert_eq!(NtStatus::from(PeError::MissingDirectory(0)), NtStatus(ntstatus::STATUS_NOT_FOUND));
+
+    // A name table entry that points outside the image.
+    let mut bytes = mapped_image(true);
+    put32(&mut bytes, 0x2060, 0x8000_0000);
+    let image = PeImage::from_mapped_bytes(&bytes).unwrap();
+    assert_eq!(image.exports(), Err(PeError::OutOfBounds { offset: 0x8000_0000, len: 1 }));
+    assert_eq!(NtStatus::from(image.exports().unwrap_err()), NtStatus(ntstatus::STATUS_INVALID_IMAGE_FORMAT));
+}
+
+/// A DLL produced by a real linker, see tests/data/build.sh.
+const SAMPLE: &[u8] = include_bytes!("data/sample.dll");
+
+#[repr(C, align(4096))]
+struct Mapped([u8; 0x5000]);
+
+/// Lays out `SAMPLE` the way the loader maps it.
+fn map_sample() -> Box<Mapped> {
+    let file = PeImage::from_file_bytes(SAMPLE).unwrap();
+    let mut mapped = Box::new(Mapped([0; 0x5000]));
+    let headers = file.headers().size_of_headers as usize;
+    mapped.0[..headers].copy_from_slice(&SAMPLE[..headers]);
+    for section in file.sections() {
+        let raw = section.pointer_to_raw_data as usize;
+        let len = section.size_of_raw_data.min(section.virtual_size) as usize;
+        put(&mut mapped.0, section.virtual_address as usize, &SAMPLE[raw..raw + len]);
+    }
+    mapped
+}
+
+fn assert_sample(image: &PeImage<impl ImageReader>) {
+    assert_eq!(image.headers().machine, 0x8664);
+    assert!(image.headers().is_64bit);
+    assert_eq!(image.headers().image_base, 0x1_8000_0000);
+    assert_eq!(image.headers().size_of_image, 0x5000);
+    let names: Vec<_> = image.sections().iter().map(|s| s.name()).collect();
+    assert_eq!(names, [".text", ".rdata", ".data", ".reloc"]);
+
+    let sleepy = ExportTarget::Forwarder("KERNEL32.Sleep".into());
+    assert_eq!(image.export_name().unwrap(), "sample.dll");
+    assert_eq!(
+        image.exports().unwrap(),
+        [
+            Export { name: Some("add_one".into()), ordinal: 1, target: ExportTarget::Rva(0x1000) },
+            Export { name: Some("ticks".into()), ordinal: 2, target: ExportTarget::Rva(0x1004) },
+            Export { name: Some("table".into()), ordinal: 3, target: ExportTarget::Rva(0x3000) },
+            Export { name: Some("Sleepy".into()), ordinal: 4, target: sleepy.clone() },
+            Export { name: None, ordinal: 7, target: ExportTarget::Rva(0x100A) },
+        ]
+    );
+    assert_eq!(image.export_by_name("ticks"), Ok(Some(ExportTarget::Rva(0x1004))));
+    assert_eq!(image.export_by_name("Sleepy"), Ok(Some(sleepy)));
+    assert_eq!(image.export_by_name("hidden"), Ok(None));
+    assert_eq!(image.export_by_ordinal(5), Ok(None));
+    assert_eq!(image.export_by_ordinal(7), Ok(Some(ExportTarget::Rva(0x100A))));
+
+    assert_eq!(
+        image.imports().unwrap(),
+        [
+            ImportModule {
+                name: "KERNEL32.dll".into(),
+                imports: vec![Import { name: ImportName::Name { hint: 0, name: "GetTickCount".into() }, iat_rva: 0x2110 }],
+            },
+            ImportModule { name: "USER32.dll".into(), imports: vec![Import { name: ImportName::Ordinal(5), iat_rva: 0x2120 }] },
+        ]
+    );
+
+    let dir64 = |rva| Relocation { rva, kind: IMAGE_REL_BASED_DIR64 };
+    assert_eq!(image.relocations().unwrap(), [dir64(0x3000), dir64(0x3008), dir64(0x3010)]);
+}
+
+#[test]
+fn linker_produced_image() {
+    assert_sample(&PeImage::from_file_bytes(SAMPLE).unwrap());
+    let mapped = map_sample();
+    assert_sample(&PeImage::from_mapped_bytes(&mapped.0).unwrap());
+}
+
+#[test]
+fn import_walk_stops_at_the_directory_size() {
+    let nt = u32::from_le_bytes(SAMPLE[0x3C..0x40].try_into().unwrap()) as usize;
+    let import_size = nt + 24 + 112 + 8 * IMAGE_DIRECTORY_ENTRY_IMPORT + 4;
+
+    // Only the first descriptor fits, even though the second one isn't null.
+    let mut bytes = SAMPLE.to_vec();
+    put32(&mut bytes, import_size, 20);
+    let imports = PeImage::from_file_bytes(&bytes).unwrap().imports().unwrap();
+    assert_eq!(imports.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), ["KERNEL32.dll"]);
+
+    put32(&mut bytes, import_size, 19);
+    assert_eq!(PeImage::from_file_bytes(&bytes).unwrap().imports(), Ok(Vec::new()));
+}
+
+#[test]
+fn loaded_modules_are_read_a_page_at_a_time() {
+    kernel();
+    let mapped = map_sample();
+    let base = mapped.0.as_ptr() as usize;
+    kernel().add_module(SimModule {
+        full_path: "\\SystemRoot\\System32\\drivers\\sample.dll".into(),
+        base,
+        size: 0x5000,
+        exports: Default::default(),
+    });
+
+    let module = unsafe { find_kernel_module("sample.dll") }.unwrap().unwrap();
+    let image = unsafe { module.image() }.unwrap();
+    assert_sample(&image);
+
+    // The discardable .reloc section is gone once the driver has initialized.
+    kernel().set_address_valid(base + 0x4000, false);
+    let status = NtStatus(ntstatus::STATUS_PARTIAL_COPY);
+    assert_eq!(image.relocations(), Err(PeError::ReadFailed { offset: 0x4000, status }));
+    assert_eq!(image.read_u64_rva(0x3FFC), Err(PeError::ReadFailed { offset: 0x3FFC, status }));
+    assert_eq!(image.export_by_name("add_one"), Ok(Some(ExportTarget::Rva(0x1000))));
+    kernel().set_address_valid(base + 0x4000, true);
+    assert_eq!(image.relocations().unwrap().len(), 3);
+}
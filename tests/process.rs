@@ -0,0 +1,104 @@
+#![cfg(feature = "host-sim")]
+
+mod common;
+
+use winkernel::process::{PeProcess, ProcessRef};
+
+use common::kernel;
+
+#[test]
+fn lookups_release_their_reference_on_drop() {
+    let eprocess = kernel().add_process(1001, "lookup.exe");
+
+    let process = unsafe { PeProcess::by_pid(1001) }.unwrap();
+    assert_eq!(process.as_ptr(), eprocess);
+    assert_eq!(unsafe { process.file_name() }.to_str(), Some("lookup.exe"));
+    assert_eq!(kernel().object_references(eprocess as _), 1);
+
+    drop(process);
+    assert_eq!(kernel().object_references(eprocess as _), 0);
+
+    assert!(unsafe { PeProcess::by_pid(999_999) }.is_none());
+}
+
+#[test]
+fn clones_take_their_own_reference() {
+    let eprocess = kernel().add_process(1002, "clone.exe");
+
+    let first = unsafe { PeProcess::by_pid(1002) }.unwrap();
+    let second = first.clone();
+    let third = unsafe { second.reference() };
+    assert_eq!(kernel().object_references(eprocess as _), 3);
+
+    drop(first);
+    drop(third);
+    assert_eq!(kernel().object_references(eprocess as _), 1);
+    assert_eq!(second.as_ptr(), eprocess);
+
+    drop(second);
+    assert_eq!(kernel().object_references(eprocess as _), 0);
+}
+
+#[test]
+fn raw_references_can_be_handed_over() {
+    let eprocess = kernel().add_process(1003, "raw.exe");
+
+    let process = ProcessRef::into_raw(unsafe { PeProcess::by_pid(1003) }.unwrap());
+    assert_eq!(kernel().object_references(eprocess as _), 1);
+
+    drop(unsafe { ProcessRef::from_raw(process) });
+    assert_eq!(kernel().object_references(eprocess as _), 0);
+}
+
+#[test]
+fn borrowed_processes_take_no_reference() {
+    let current = unsafe { PeProcess::current() };
+    assert_eq!(kernel().object_references(current.as_ptr() as _), 0);
+}
+
+#[test]
+fn attach_guards_detach_in_order() {
+    let outer = kernel().add_process(1004, "outer.exe");
+    let inner = kernel().add_process(1005, "inner.exe");
+    assert_eq!(kernel().attached_process(), None);
+
+    let outer_process = unsafe { PeProcess::by_pid(1004) }.unwrap();
+    let inner_process = unsafe { PeProcess::by_pid(1005) }.unwrap();
+
+    let depth = outer_process.with_attached(|outer_guard| {
+        assert_eq!(outer_guard.process().as_ptr(), outer);
+        assert_eq!(kernel().attached_process(), Some(outer));
+
+        let depth = inner_process.with_attached(|_| {
+            assert_eq!(kernel().attached_process(), Some(inner));
+            2
+        });
+        assert_eq!(kernel().attached_process(), Some(outer));
+        depth
+    });
+    assert_eq!(depth, 2);
+    assert_eq!(kernel().attached_process(), None);
+}
+
+#[test]
+fn panics_while_attached_still_detach() {
+    kernel().add_process(1007, "panic.exe");
+    let process = unsafe { PeProcess::by_pid(1007) }.unwrap();
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        process.with_attached(|_| panic!("attached"));
+    }));
+    assert!(result.is_err());
+    assert_eq!(kernel().attached_process(), None);
+}
+
+#[test]
+fn attachment_belongs_to_the_attaching_thread() {
+    kernel().add_process(1006, "thread.exe");
+    let process = unsafe { PeProcess::by_pid(1006) }.unwrap();
+
+    process.with_attached(|_| {
+        assert!(kernel().attached_process().is_some());
+        std::thread::spawn(|| assert_eq!(kernel().attached_process(), None)).join().unwrap();
+    });
+}
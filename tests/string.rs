@@ -0,0 +1,237 @@
+#![cfg(feature = "host-sim")]
+
+use winkernel::basedef::UNICODE_STRING;
+use winkernel::string::{UnicodeString, UnicodeStringBuf, UNICODE_STRING_MAX_CHARS};
+
+fn utf16(s: &str) -> Vec<u16> {
+    s.encode_utf16().collect()
+}
+
+#[test]
+fn buf_keeps_byte_lengths_in_sync() {
+    let mut path = UnicodeStringBuf::try_from_str("\\Registry\\Machine").unwrap();
+    assert_eq!(path.length as usize, path.len() * 2);
+    assert!(path.maximum_length >= path.length);
+    assert_eq!(path.buffer, path.as_slice().as_ptr());
+
+    path.push_component("Software").unwrap();
+    path.push_component("\\Vendor\\").unwrap();
+    path.push_component("Product").unwrap();
+    assert_eq!(path.to_string(), "\\Registry\\Machine\\Software\\Vendor\\Product");
+    assert_eq!(path.as_slice(), utf16("\\Registry\\Machine\\Software\\Vendor\\Product").as_slice());
+    assert_eq!(unsafe { (*path).as_slice() }.len(), path.len());
+
+    let raw = unsafe { &*path.as_ptr() };
+    assert_eq!(raw.Length, path.length);
+    assert_eq!(raw.Buffer as *const u16, path.buffer);
+}
+
+#[test]
+fn empty_bufs_have_a_null_buffer() {
+    let mut s = UnicodeStringBuf::new();
+    assert!(s.buffer.is_null());
+    assert_eq!((s.length, s.maximum_length), (0, 0));
+    assert!(unsafe { (*s.as_ptr()).Buffer }.is_null());
+    assert!(UnicodeStringBuf::default().buffer.is_null());
+    assert!(s.clone().buffer.is_null());
+
+    // Once allocated, the storage stays valid even when the string is emptied again.
+    s.push('a').unwrap();
+    s.clear();
+    assert_eq!(s.buffer, s.as_slice().as_ptr());
+    assert!(!s.buffer.is_null());
+    assert_eq!(s.length, 0);
+}
+
+#[test]
+fn buf_rejects_strings_longer_than_a_counted_string() {
+    let mut s = UnicodeStringBuf::try_from_slice(&vec![b'a' as u16; UNICODE_STRING_MAX_CHARS]).unwrap();
+    assert_eq!(s.length, (UNICODE_STRING_MAX_CHARS * 2) as u16);
+    assert!(s.push('b').is_err());
+    assert!(s.push_component("c").is_err());
+    assert_eq!(s.len(), UNICODE_STRING_MAX_CHARS);
+
+    s.truncate(3);
+    s.push('😀').unwrap();
+    assert_eq!(s.len(), 5);
+    assert_eq!(format!("{}", s.clone()), "aaa😀");
+}
+
+#[test]
+fn from_slice_uses_byte_lengths() {
+    let wide = utf16("\\Device\\HarddiskVolume1");
+    let s = UnicodeString::from_slice(&wide).unwrap();
+    assert_eq!(s.length as usize, wide.len() * 2);
+    assert_eq!(s.maximum_length, s.length);
+    assert_eq!(unsafe { s.as_slice() }, wide.as_slice());
+    assert_eq!(s.try_to_string().unwrap(), "\\Device\\HarddiskVolume1");
+
+    let longest = vec![0x41u16; UNICODE_STRING_MAX_CHARS];
+    assert_eq!(UnicodeString::from_slice(&longest).unwrap().length, 0xFFFE);
+    let too_long = vec![0x41u16; UNICODE_STRING_MAX_CHARS + 1];
+    assert!(UnicodeString::from_slice(&too_long).is_err());
+
+    let empty = UnicodeString::from_slice(&[]).unwrap();
+    assert_eq!(unsafe { empty.as_slice() }, &[] as &[u16]);
+    assert_eq!(unsafe { UnicodeString::empty().as_slice() }, &[] as &[u16]);
+}
+
+#[test]
+fn from_str_encodes_into_the_caller_buffer() {
+    let mut buf = [0u16; 16];
+    let s = UnicodeString::from_str("ntdll.dll", &mut buf).unwrap();
+    assert_eq!(s.length, 18);
+    assert_eq!(s.maximum_length, 32);
+    assert_eq!(s.try_to_string().unwrap(), "ntdll.dll");
+
+    // Characters outside the BMP take two code units.
+    let mut buf = [0u16; 3];
+    assert_eq!(UnicodeString::from_str("a😀", &mut buf).unwrap().length, 6);
+    assert!(UnicodeString::from_str("ab😀", &mut buf).is_err());
+    assert_eq!(UnicodeString::from_str("", &mut []).unwrap().length, 0);
+
+    let mut huge = vec![0u16; UNICODE_STRING_MAX_CHARS + 10];
+    let long = "x".repeat(UNICODE_STRING_MAX_CHARS + 1);
+    assert!(UnicodeString::from_str(&long, &mut huge).is_err());
+    assert_eq!(UnicodeString::from_str("x", &mut huge).unwrap().maximum_length, 0xFFFE);
+}
+
+#[test]
+fn round_trips_through_unicode_string() {
+    let mut buf = [0u16; 32];
+    let original = UnicodeString::from_str("\\SystemRoot\\System32", &mut buf).unwrap();
+
+    let raw: UNICODE_STRING = original.into();
+    assert_eq!((raw.Length, raw.MaximumLength), (original.length, original.maximum_length));
+    assert_eq!(raw.Buffer as *const u16, original.buffer);
+
+    let back = UnicodeString::from(&raw);
+    assert_eq!(back.try_to_string().unwrap(), "\\SystemRoot\\System32");
+    assert_eq!(unsafe { (*back.as_ptr()).Length }, back.length);
+}
+
+const SOFTWARE: UnicodeString = winkernel::unicode_str!("\\Registry\\Machine\\Software");
+const MIXED: &[u16] = winkernel::wide_str!("é€😀x");
+
+#[test]
+fn unicode_str_is_built_at_compile_time() {
+    assert_eq!(SOFTWARE.length as usize, "\\Registry\\Machine\\Software".len() * 2);
+    assert_eq!(SOFTWARE.maximum_length, SOFTWARE.length + 2);
+    assert_eq!(SOFTWARE.try_to_string().unwrap(), "\\Registry\\Machine\\Software");
+
+    let mut expected = utf16("é€😀x");
+    expected.push(0);
+    assert_eq!(MIXED, expected.as_slice());
+    assert_eq!(winkernel::unicode_str!("").length, 0);
+}
+
+#[test]
+fn upcase_matches_nt_rules() {
+    use winkernel::string::upcase;
+
+    assert_eq!(upcase(b'a' as u16), b'A' as u16);
+    assert_eq!(upcase(b'1' as u16), b'1' as u16);
+    assert_eq!(upcase('é' as u16), 'É' as u16);
+    assert_eq!(upcase('я' as u16), 'Я' as u16);
+    // No single-character uppercase form, and lone surrogates, are left alone.
+    assert_eq!(upcase('ß' as u16), 'ß' as u16);
+    assert_eq!(upcase(0xD83D), 0xD83D);
+}
+
+#[test]
+fn case_insensitive_comparison_and_search() {
+    let path = UnicodeStringBuf::try_from_str("\\REGISTRY\\Machine\\SYSTEM\\ControlSet001\\Services\\Été").unwrap();
+    let lower = UnicodeStringBuf::try_from_str("\\registry\\machine\\system\\controlset001\\services\\été").unwrap();
+
+    assert!(path.eq_ignore_case(&lower));
+    assert!(*path != *lower);
+    assert_eq!(path.cmp_ignore_case(&lower), std::cmp::Ordering::Equal);
+    assert_eq!(path.cmp_ignore_case(&SOFTWARE), std::cmp::Ordering::Greater);
+
+    let prefix = winkernel::unicode_str!("\\Registry\\MACHINE");
+    assert!(path.starts_with_ignore_case(&prefix));
+    assert!(!path.starts_with(&prefix));
+    assert!(path.starts_with(&winkernel::unicode_str!("\\REGISTRY")));
+
+    let suffix = winkernel::unicode_str!("services\\ÉTÉ");
+    assert!(path.ends_with_ignore_case(&suffix));
+    assert!(!path.ends_with(&suffix));
+    assert!(!suffix.ends_with_ignore_case(&path));
+
+    let needle = winkernel::unicode_str!("controlset001");
+    assert_eq!(path.find_ignore_case(&needle), Some(25));
+    assert!(path.contains_ignore_case(&needle));
+    assert!(!path.contains(&needle));
+    assert!(path.contains(&winkernel::unicode_str!("ControlSet001")));
+    assert!(path.contains(&UnicodeString::empty()));
+}
+
+#[test]
+fn equal_strings_hash_equally() {
+    use std::collections::HashSet;
+
+    let a = UnicodeStringBuf::try_from_str("ntoskrnl.exe").unwrap();
+    let b = winkernel::unicode_str!("ntoskrnl.exe");
+    assert!(a == b);
+
+    let set: HashSet<UnicodeString> = [*a, b].into_iter().collect();
+    assert_eq!(set.len(), 1);
+}
+
+#[test]
+fn ansi_strings_round_trip_through_latin1() {
+    use winkernel::basedef::{ntstatus, ANSI_STRING};
+    use winkernel::ntstatus::NtStatus;
+    use winkernel::string::{AnsiString, AnsiStringBuf};
+
+    // Not UTF-8, but every byte still maps to exactly one code point and back.
+    let bytes = [b'c', b'a', b'f', 0xE9, 0x80, 0xFF, b'.', b'e', b'x', b'e'];
+    let ansi = AnsiString::from_bytes(&bytes).unwrap();
+    assert_eq!(ansi.to_str(), None);
+    assert_eq!(ansi.to_string(), "café\u{80}ÿ.exe");
+    assert_eq!(format!("{:?}", ansi), "\"caf\\xe9\\x80\\xff.exe\"");
+
+    let unicode = UnicodeStringBuf::from_latin1(&ansi).unwrap();
+    assert_eq!(unicode.len(), bytes.len());
+    let back = unicode.to_latin1().unwrap();
+    assert_eq!(back.as_bytes(), &bytes);
+    assert!(*back == ansi);
+    assert_eq!(ansi.decode_latin1().unwrap(), unicode);
+    assert_eq!(back.decode_latin1().unwrap(), unicode);
+    assert!(AnsiStringBuf::new().buffer.is_null());
+
+    let raw: ANSI_STRING = ansi.into();
+    assert_eq!(raw.Length, 10);
+    assert!(AnsiString::from(raw) == ansi);
+
+    let wide = UnicodeStringBuf::try_from_str("smile😀").unwrap();
+    assert_eq!(wide.to_latin1().err(), Some(NtStatus(ntstatus::STATUS_UNMAPPABLE_CHARACTER)));
+    assert_eq!(AnsiStringBuf::try_from_bytes(b"lsass.exe").unwrap().to_str(), Some("lsass.exe"));
+}
+
+#[test]
+fn bounded_ansi_strings_stop_at_the_terminator_or_the_limit() {
+    use winkernel::string::AnsiString;
+
+    let name = *b"svchost.exe\0\0\0\0";
+    let s = unsafe { AnsiString::from_ptr_bounded(name.as_ptr(), 15) };
+    assert_eq!((s.length, s.maximum_length), (11, 12));
+
+    let full = *b"averylongname.e";
+    let s = unsafe { AnsiString::from_ptr_bounded(full.as_ptr(), full.len()) };
+    assert_eq!((s.length, s.maximum_length), (15, 15));
+}
+
+#[test]
+fn latin1_decoding_is_limited_to_a_unicode_string() {
+    use winkernel::basedef::ntstatus;
+    use winkernel::ntstatus::NtStatus;
+    use winkernel::string::AnsiString;
+
+    let bytes = vec![b'a'; UNICODE_STRING_MAX_CHARS + 1];
+    let fits = AnsiString::from_bytes(&bytes[..UNICODE_STRING_MAX_CHARS]).unwrap();
+    assert_eq!(UnicodeStringBuf::from_latin1(&fits).unwrap().len(), UNICODE_STRING_MAX_CHARS);
+
+    let too_long = AnsiString::from_bytes(&bytes).unwrap();
+    assert_eq!(UnicodeStringBuf::from_latin1(&too_long).err(), Some(NtStatus(ntstatus::STATUS_NAME_TOO_LONG)));
+}
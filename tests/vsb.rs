@@ -0,0 +1,98 @@
+#![cfg(feature = "host-sim")]
+
+use std::mem::size_of;
+
+use winkernel::basedef::ntstatus;
+use winkernel::ntstatus::NtStatus;
+use winkernel::vsb::{EntryError, NextEntryOffset, VariableSizedBox};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct Record {
+    next_entry_offset: u32,
+    value: u32,
+}
+
+unsafe impl NextEntryOffset for Record {
+    fn next_entry_offset(&self) -> u32 {
+        self.next_entry_offset
+    }
+}
+
+/// A buffer of `size` bytes with a record written at each `(offset, next entry offset, value)`.
+fn chain(size: usize, records: &[(usize, u32, u32)]) -> VariableSizedBox<Record> {
+    let mut buf = VariableSizedBox::<Record>::new(size);
+    for &(offset, next_entry_offset, value) in records {
+        unsafe {
+            buf.as_mut_ptr().cast::<u8>().add(offset).cast::<Record>().write(Record { next_entry_offset, value });
+        }
+    }
+    buf
+}
+
+/// `(offset, len, value)` of each entry, up to the first error.
+fn walk(buf: &VariableSizedBox<Record>) -> (Vec<(usize, usize, u32)>, Option<EntryError>) {
+    let mut entries = Vec::new();
+    for entry in unsafe { buf.entries() } {
+        match entry {
+            Ok(entry) => entries.push((entry.offset(), entry.len(), entry.value)),
+            Err(e) => return (entries, Some(e)),
+        }
+    }
+    (entries, None)
+}
+
+#[test]
+fn entries_follow_the_chain_to_the_last_entry() {
+    let buf = chain(0x40, &[(0, 0x10, 1), (0x10, 0x18, 2), (0x28, 0, 3)]);
+    assert_eq!(walk(&buf), (vec![(0, 0x10, 1), (0x10, 0x18, 2), (0x28, 0x18, 3)], None));
+
+    let entry = unsafe { buf.entries() }.nth(1).unwrap().unwrap();
+    assert_eq!(entry.tail().len(), 0x18 - size_of::<Record>());
+}
+
+#[test]
+fn empty_and_truncated_buffers() {
+    let empty = VariableSizedBox::<Record>::new(0);
+    assert_eq!(walk(&empty), (vec![], None));
+
+    let short = chain(4, &[]);
+    assert_eq!(walk(&short), (vec![], Some(EntryError::Truncated { len: 4 })));
+}
+
+#[test]
+fn bad_next_entry_offsets_are_reported() {
+    let bad = |offset, next_entry_offset| Some(EntryError::BadNextEntryOffset { offset, next_entry_offset });
+
+    // Past the end of the allocation.
+    let buf = chain(0x20, &[(0, 0x10, 1), (0x10, 0x10, 2)]);
+    assert_eq!(walk(&buf), (vec![(0, 0x10, 1), (0x10, 0x10, 2)], bad(0x10, 0x10)));
+
+    // Leaves too little room for the next entry.
+    let buf = chain(0x20, &[(0, 0x1c, 1)]);
+    assert_eq!(walk(&buf), (vec![(0, 0x20, 1)], bad(0, 0x1c)));
+
+    // Misaligned.
+    let buf = chain(0x20, &[(0, 0xa, 1)]);
+    assert_eq!(walk(&buf), (vec![(0, 0x20, 1)], bad(0, 0xa)));
+
+    // Overlapping the entry itself.
+    let buf = chain(0x20, &[(0, 4, 1)]);
+    assert_eq!(walk(&buf), (vec![(0, 0x20, 1)], bad(0, 4)));
+
+    // Large enough to wrap the offset on 32-bit targets.
+    let buf = chain(0x20, &[(0, 0x10, 1), (0x10, u32::MAX, 2)]);
+    assert_eq!(walk(&buf), (vec![(0, 0x10, 1), (0x10, 0x10, 2)], bad(0x10, u32::MAX)));
+}
+
+#[test]
+fn iteration_ends_after_an_error() {
+    let buf = chain(0x20, &[(0, 0x40, 1)]);
+    let mut entries = unsafe { buf.entries() };
+    assert!(entries.next().unwrap().is_ok());
+    let error = entries.next().unwrap().err().unwrap();
+    assert!(entries.next().is_none());
+
+    assert_eq!(NtStatus::from(error), NtStatus(ntstatus::STATUS_DATA_ERROR));
+    assert_eq!(error.to_string(), "entry at 0x0 has a bad next entry offset of 0x40");
+}